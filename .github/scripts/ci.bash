@@ -15,6 +15,8 @@ declare -A KEYMAPS
 KEYMAPS=(
     "keymap_basic"
     "keymap_borisfaure"
+    "keymap_zmk"
+    "keymap_kanata"
 )
 
 
@@ -52,13 +54,14 @@ run_check() {
 }
 
 run_test() {
-    cargo test
-    for FEAT in "${FEATURES[@]}"
+    HOST=$(rustc -vV | sed -n 's/^host: //p')
+    cargo test --manifest-path ferris-core/Cargo.toml --target "$HOST"
+    for KEYMAP in "${KEYMAPS[@]}" keymap_pierrec83
     do
-        for KEYMAP in "${KEYMAPS[@]}"
-        do
-            cargo test --no-default-features --features "$FEAT,$KEYMAP"
-        done
+        cargo test --manifest-path ferris-core/Cargo.toml --target "$HOST" \
+            --no-default-features --features "$KEYMAP"
+        cargo test --manifest-path tools/Cargo.toml --target "$HOST" \
+            --no-default-features --features "$KEYMAP"
     done
 }
 
@@ -84,6 +87,19 @@ run_build_release() {
     done
 }
 
+run_tools() {
+    HOST=$(rustc -vV | sed -n 's/^host: //p')
+    for KEYMAP in "${KEYMAPS[@]}" keymap_pierrec83
+    do
+        cargo run --manifest-path tools/Cargo.toml --target "$HOST" \
+            --no-default-features --features "$KEYMAP" \
+            --bin keymap-viz -- ascii
+        cargo run --manifest-path tools/Cargo.toml --target "$HOST" \
+            --no-default-features --features "$KEYMAP" \
+            --bin keymap-lint
+    done
+}
+
 case $1 in
     doc)
         run_doc
@@ -106,4 +122,7 @@ case $1 in
     build-release)
         run_build_release
         ;;
+    tools)
+        run_tools
+        ;;
 esac
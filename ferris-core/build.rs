@@ -0,0 +1,54 @@
+//! Build script importing keymaps written for other firmwares
+//!
+//! The generated layers are written in `OUT_DIR` and included by the
+//! matching `keymap_*` module.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Importer for Kanata `.kbd` files
+#[path = "build/kanata.rs"]
+mod kanata;
+/// Code generation for the 34 keys layout
+#[path = "build/layout.rs"]
+mod layout;
+/// Importer for ZMK `.keymap` files
+#[path = "build/zmk.rs"]
+mod zmk;
+
+/// Path of the keymap to import, overridable with the environment variable `var`
+///
+/// Relative paths are relative to the root of the repository.
+fn keymap_path(var: &str, default: &str) -> PathBuf {
+    println!("cargo:rerun-if-env-changed={}", var);
+    let root = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap()).join("..");
+    let path = root.join(
+        env::var_os(var)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(default)),
+    );
+    println!("cargo:rerun-if-changed={}", path.display());
+    path
+}
+
+/// Import the keymap at `path` with `parse` and write it to `OUT_DIR/out`
+fn import(path: PathBuf, out: &str, parse: fn(&str) -> Result<Vec<layout::Layer>, String>) {
+    let source = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("unable to read {}: {}", path.display(), e));
+    let layers = parse(&source).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::write(out_dir.join(out), layout::emit(&layers)).unwrap();
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build");
+    if env::var_os("CARGO_FEATURE_KEYMAP_ZMK").is_some() {
+        let path = keymap_path("FERRIS_ZMK_KEYMAP", "keymaps/ferris.keymap");
+        import(path, "keymap_zmk.rs", zmk::parse);
+    }
+    if env::var_os("CARGO_FEATURE_KEYMAP_KANATA").is_some() {
+        let path = keymap_path("FERRIS_KANATA_CONFIG", "keymaps/ferris.kbd");
+        import(path, "keymap_kanata.rs", kanata::parse);
+    }
+}
@@ -0,0 +1,628 @@
+//! Importer for Kanata `.kbd` configuration files
+//!
+//! Layers are read from `deflayer` forms, in order, and must list the 34 keys
+//! of a Ferris/Sweep. `defsrc` and `defcfg` are ignored, `defalias` can be
+//! used to name actions. The supported actions are keys and chords such as
+//! `C-S-tab`, `_`, `XX`, `tap-hold`, `tap-hold-press`, `tap-hold-release`,
+//! `layer-switch`, `layer-while-held`, `multi`, `one-shot` of a modifier or
+//! of a `layer-while-held`, `macro`, whose keys and chords are tapped in
+//! order, numbers being delays in milliseconds, and `unicode`.
+
+use crate::layout::{self, Layer, KEYS};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Kanata key names and their keyberon equivalent
+const KEYCODES: &[(&str, &str)] = &[
+    ("a", "A"),
+    ("b", "B"),
+    ("c", "C"),
+    ("d", "D"),
+    ("e", "E"),
+    ("f", "F"),
+    ("g", "G"),
+    ("h", "H"),
+    ("i", "I"),
+    ("j", "J"),
+    ("k", "K"),
+    ("l", "L"),
+    ("m", "M"),
+    ("n", "N"),
+    ("o", "O"),
+    ("p", "P"),
+    ("q", "Q"),
+    ("r", "R"),
+    ("s", "S"),
+    ("t", "T"),
+    ("u", "U"),
+    ("v", "V"),
+    ("w", "W"),
+    ("x", "X"),
+    ("y", "Y"),
+    ("z", "Z"),
+    ("1", "Kb1"),
+    ("2", "Kb2"),
+    ("3", "Kb3"),
+    ("4", "Kb4"),
+    ("5", "Kb5"),
+    ("6", "Kb6"),
+    ("7", "Kb7"),
+    ("8", "Kb8"),
+    ("9", "Kb9"),
+    ("0", "Kb0"),
+    ("grv", "Grave"),
+    ("`", "Grave"),
+    ("min", "Minus"),
+    ("-", "Minus"),
+    ("eql", "Equal"),
+    ("=", "Equal"),
+    ("bspc", "BSpace"),
+    ("tab", "Tab"),
+    ("lbrc", "LBracket"),
+    ("[", "LBracket"),
+    ("rbrc", "RBracket"),
+    ("]", "RBracket"),
+    ("bksl", "Bslash"),
+    ("\\", "Bslash"),
+    ("caps", "CapsLock"),
+    ("scln", "SColon"),
+    (";", "SColon"),
+    ("apo", "Quote"),
+    ("apos", "Quote"),
+    ("'", "Quote"),
+    ("ret", "Enter"),
+    ("ent", "Enter"),
+    ("enter", "Enter"),
+    ("comm", "Comma"),
+    (",", "Comma"),
+    (".", "Dot"),
+    ("/", "Slash"),
+    ("spc", "Space"),
+    ("esc", "Escape"),
+    ("lsft", "LShift"),
+    ("rsft", "RShift"),
+    ("lctl", "LCtrl"),
+    ("rctl", "RCtrl"),
+    ("lalt", "LAlt"),
+    ("ralt", "RAlt"),
+    ("lmet", "LGui"),
+    ("rmet", "RGui"),
+    ("menu", "Application"),
+    ("f1", "F1"),
+    ("f2", "F2"),
+    ("f3", "F3"),
+    ("f4", "F4"),
+    ("f5", "F5"),
+    ("f6", "F6"),
+    ("f7", "F7"),
+    ("f8", "F8"),
+    ("f9", "F9"),
+    ("f10", "F10"),
+    ("f11", "F11"),
+    ("f12", "F12"),
+    ("prtsc", "PScreen"),
+    ("slck", "ScrollLock"),
+    ("pause", "Pause"),
+    ("ins", "Insert"),
+    ("home", "Home"),
+    ("pgup", "PgUp"),
+    ("del", "Delete"),
+    ("end", "End"),
+    ("pgdn", "PgDown"),
+    ("left", "Left"),
+    ("down", "Down"),
+    ("up", "Up"),
+    ("rght", "Right"),
+    ("right", "Right"),
+    ("nlck", "NumLock"),
+    ("mute", "Mute"),
+    ("volu", "VolUp"),
+    ("voldwn", "VolDown"),
+    ("vold", "VolDown"),
+    ("pp", "MediaPlayPause"),
+    ("next", "MediaNextSong"),
+    ("prev", "MediaPreviousSong"),
+];
+
+/// Chord prefixes, such as `C-` in `C-b`, and the modifier they add
+const PREFIXES: &[(&str, &str)] = &[
+    ("RA-", "RAlt"),
+    ("AG-", "RAlt"),
+    ("RC-", "RCtrl"),
+    ("RS-", "RShift"),
+    ("RM-", "RGui"),
+    ("C-", "LCtrl"),
+    ("S-", "LShift"),
+    ("A-", "LAlt"),
+    ("M-", "LGui"),
+];
+
+/// A parsed s-expression
+enum Sexp {
+    /// A name, a number or a key
+    Atom(String),
+    /// A parenthesized list
+    List(Vec<Sexp>),
+}
+
+impl Sexp {
+    /// The atom, if this is one
+    fn atom(&self) -> Option<&str> {
+        match self {
+            Sexp::Atom(a) => Some(a),
+            Sexp::List(_) => None,
+        }
+    }
+}
+
+/// Parse the whole file into its top level forms, dropping comments
+fn parse_sexps(source: &str) -> Result<Vec<Sexp>, String> {
+    let mut stack: Vec<Vec<Sexp>> = vec![Vec::new()];
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            ';' if chars.peek() == Some(&';') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '#' if chars.peek() == Some(&'|') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '|' && c == '#' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            '(' => stack.push(Vec::new()),
+            ')' => {
+                let list = stack.pop().ok_or("unbalanced `)`")?;
+                stack
+                    .last_mut()
+                    .ok_or("unbalanced `)`")?
+                    .push(Sexp::List(list));
+            }
+            _ => {
+                let mut atom = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    atom.push(c);
+                    chars.next();
+                }
+                stack
+                    .last_mut()
+                    .ok_or("unbalanced `)`")?
+                    .push(Sexp::Atom(atom));
+            }
+        }
+    }
+    match (stack.pop(), stack.is_empty()) {
+        (Some(forms), true) => Ok(forms),
+        _ => Err("unbalanced `(`".to_string()),
+    }
+}
+
+/// Keyberon keycodes of a key or a chord, modifiers first
+fn keycodes(name: &str) -> Result<Vec<&'static str>, String> {
+    if let Some((prefix, modifier)) = PREFIXES
+        .iter()
+        .find(|(p, _)| name.len() > p.len() && name.starts_with(p))
+    {
+        let mut codes = vec![*modifier];
+        codes.extend(keycodes(&name[prefix.len()..])?);
+        return Ok(codes);
+    }
+    KEYCODES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, code)| vec![*code])
+        .ok_or_else(|| format!("unknown key `{}`", name))
+}
+
+/// What is needed to convert actions
+struct Context<'a> {
+    /// Aliases defined with `defalias`
+    aliases: HashMap<&'a str, &'a Sexp>,
+    /// Aliases being resolved, to find the ones referring to themselves
+    resolving: RefCell<Vec<&'a str>>,
+    /// Index of every layer, by name
+    layers: HashMap<&'a str, usize>,
+}
+
+impl<'a> Context<'a> {
+    /// Index of a named layer
+    fn layer(&self, name: Option<&Sexp>) -> Result<usize, String> {
+        let name = name.and_then(Sexp::atom).ok_or("missing layer name")?;
+        self.layers
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("unknown layer `{}`", name))
+    }
+
+    /// A number parameter
+    fn number(param: Option<&Sexp>) -> Result<u16, String> {
+        let param = param.and_then(Sexp::atom).ok_or("missing number")?;
+        param
+            .parse()
+            .map_err(|_| format!("invalid number `{}`", param))
+    }
+
+    /// Convert an action into a keyberon action expression
+    fn action(&self, sexp: &Sexp) -> Result<String, String> {
+        let list = match sexp {
+            Sexp::Atom(a) if a == "_" => return Ok("Action::Trans".to_string()),
+            Sexp::Atom(a) if a == "XX" => return Ok("Action::NoOp".to_string()),
+            Sexp::Atom(a) if a.starts_with('@') => {
+                let (name, alias) = self
+                    .aliases
+                    .get_key_value(&a[1..])
+                    .ok_or_else(|| format!("unknown alias `{}`", a))?;
+                if self.resolving.borrow().contains(name) {
+                    return Err(format!("alias `{}` refers to itself", a));
+                }
+                self.resolving.borrow_mut().push(name);
+                let action = self.action(alias);
+                self.resolving.borrow_mut().pop();
+                return action;
+            }
+            Sexp::Atom(a) => return Ok(layout::keycodes(&keycodes(a)?)),
+            Sexp::List(list) => list,
+        };
+        let name = list.first().and_then(Sexp::atom).unwrap_or("");
+        let params = &list[list.len().min(1)..];
+        match name {
+            "tap-hold" | "tap-hold-press" | "tap-hold-release" => {
+                if params.len() != 4 {
+                    return Err(format!("`{}` expects 4 parameters", name));
+                }
+                let config = match name {
+                    "tap-hold" => "Default",
+                    "tap-hold-press" => "HoldOnOtherKeyPress",
+                    _ => "PermissiveHold",
+                };
+                Ok(layout::hold_tap(
+                    Self::number(params.get(1))?,
+                    Self::number(params.first())?,
+                    config,
+                    &self.action(&params[3])?,
+                    &self.action(&params[2])?,
+                ))
+            }
+            "one-shot" => {
+                let timeout = Self::number(params.first())?;
+                match params.get(1) {
+                    Some(Sexp::List(list))
+                        if list.first().and_then(Sexp::atom) == Some("layer-while-held") =>
+                    {
+                        Ok(format!("osl({}, {})", self.layer(list.get(1))?, timeout))
+                    }
+                    Some(Sexp::Atom(key)) => match keycodes(key)?.as_slice() {
+                        [code] => Ok(format!("osm({}, {})", code, timeout)),
+                        _ => Err("`one-shot` only supports a modifier or a layer".to_string()),
+                    },
+                    _ => Err("`one-shot` only supports a modifier or a layer".to_string()),
+                }
+            }
+            "layer-switch" => Ok(format!("d({})", self.layer(params.first())?)),
+            "layer-while-held" => Ok(format!("l({})", self.layer(params.first())?)),
+            "multi" => {
+                let actions = params
+                    .iter()
+                    .map(|p| self.action(p))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(format!(
+                    "Action::MultipleActions(&[{}].as_slice())",
+                    actions.join(", ")
+                ))
+            }
+            "macro" => {
+                let mut events = Vec::new();
+                for param in params {
+                    let item = param
+                        .atom()
+                        .ok_or("macros only support keys, chords and delays")?;
+                    if let Ok(delay) = item.parse::<u16>() {
+                        events.push(format!("Delay({})", delay));
+                        continue;
+                    }
+                    // Modifiers of a chord are held while its key is tapped
+                    let codes = keycodes(item)?;
+                    let (key, modifiers) = codes.split_last().unwrap();
+                    events.extend(modifiers.iter().map(|m| format!("Press({})", m)));
+                    events.push(format!("Tap({})", key));
+                    events.extend(modifiers.iter().rev().map(|m| format!("Release({})", m)));
+                }
+                Ok(layout::sequence(&events))
+            }
+            "unicode" => {
+                let mut chars = params.first().and_then(Sexp::atom).unwrap_or("").chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Ok(format!("unicode({:?}, {:?})", c, c)),
+                    _ => Err("`unicode` takes a single character".to_string()),
+                }
+            }
+            _ => Err(format!("unsupported action `{}`", name)),
+        }
+    }
+}
+
+/// Parse a Kanata configuration into layers
+pub fn parse(source: &str) -> Result<Vec<Layer>, String> {
+    let forms = parse_sexps(source)?;
+    let mut context = Context {
+        aliases: HashMap::new(),
+        resolving: RefCell::new(Vec::new()),
+        layers: HashMap::new(),
+    };
+    let mut deflayers = Vec::new();
+    for form in &forms {
+        let list = match form {
+            Sexp::List(list) => list,
+            Sexp::Atom(a) => return Err(format!("unexpected `{}` at top level", a)),
+        };
+        match list.first().and_then(Sexp::atom) {
+            Some("defcfg") | Some("defsrc") => {}
+            Some("defalias") => {
+                for pair in list[1..].chunks(2) {
+                    match pair {
+                        [Sexp::Atom(name), action] => {
+                            context.aliases.insert(name, action);
+                        }
+                        _ => return Err("invalid `defalias`".to_string()),
+                    }
+                }
+            }
+            Some("deflayer") => {
+                let name = list
+                    .get(1)
+                    .and_then(Sexp::atom)
+                    .ok_or("`deflayer` without a name")?;
+                context.layers.insert(name, deflayers.len());
+                deflayers.push((name, &list[2..]));
+            }
+            Some(other) => return Err(format!("unsupported form `{}`", other)),
+            None => return Err("empty form".to_string()),
+        }
+    }
+    if deflayers.is_empty() {
+        return Err("the configuration has no `deflayer`".to_string());
+    }
+    let mut layers = Vec::new();
+    for (name, keys) in deflayers {
+        if keys.len() != KEYS {
+            return Err(format!(
+                "layer `{}` has {} keys, expected {}",
+                name,
+                keys.len(),
+                KEYS
+            ));
+        }
+        let keys = keys
+            .iter()
+            .map(|k| context.action(k))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("layer `{}`: {}", name, e))?;
+        layers.push(Layer {
+            name: name.to_string(),
+            keys,
+        });
+    }
+    Ok(layers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A configuration with the given aliases and a layer per item of
+    /// `layers`, each starting with the given keys, followed by `XX`
+    fn config(aliases: &str, layers: &[&str]) -> String {
+        let mut source = String::from("(defcfg)\n(defsrc a b c)\n");
+        source.push_str(&format!("(defalias {})\n", aliases));
+        for (i, keys) in layers.iter().enumerate() {
+            let count = parse_sexps(keys).unwrap().len();
+            let none = vec!["XX"; KEYS - count];
+            source.push_str(&format!("(deflayer l{} {} {})\n", i, keys, none.join(" ")));
+        }
+        source
+    }
+
+    /// Actions of the keys of a single layer, with the given aliases
+    fn keys(aliases: &str, keys: &str) -> Result<Vec<String>, String> {
+        let count = parse_sexps(keys).unwrap().len();
+        let mut layers = parse(&config(aliases, &[keys]))?;
+        Ok(layers.remove(0).keys.into_iter().take(count).collect())
+    }
+
+    #[test]
+    fn deflayer() {
+        let layers = parse(&config("", &["a 1", "_ (layer-switch l0)"])).unwrap();
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0].name, "l0");
+        assert_eq!(layers[1].name, "l1");
+        assert_eq!(layers[0].keys.len(), KEYS);
+        assert_eq!(layers[0].keys[..2], ["k(A)", "k(Kb1)"]);
+        assert_eq!(layers[0].keys[2], "Action::NoOp");
+        assert_eq!(layers[1].keys[..2], ["Action::Trans", "d(0)"]);
+    }
+
+    #[test]
+    fn defsrc_ignored() {
+        // `defsrc` lists 3 keys, the layers still need all 34
+        let source = "(defsrc a b c)\n(deflayer base a b c)";
+        assert_eq!(
+            parse(source).err().unwrap(),
+            "layer `base` has 3 keys, expected 34"
+        );
+    }
+
+    #[test]
+    fn keys_and_chords() {
+        assert_eq!(
+            keys("", "spc ; C-S-tab RA-e").unwrap(),
+            [
+                "k(Space)",
+                "k(SColon)",
+                "m(&[LCtrl, LShift, Tab].as_slice())",
+                "m(&[RAlt, E].as_slice())",
+            ]
+        );
+        assert_eq!(
+            keys("", "foo").err().unwrap(),
+            "layer `l0`: unknown key `foo`"
+        );
+    }
+
+    #[test]
+    fn comments() {
+        let source = config("", &[";; line comment\n a #| block\n comment |# b"]);
+        let layers = parse(&source).unwrap();
+        assert_eq!(layers[0].keys[..3], ["k(A)", "k(B)", "Action::NoOp"]);
+    }
+
+    #[test]
+    fn hold_taps() {
+        assert_eq!(
+            keys(
+                "",
+                "(tap-hold 0 200 a lsft) (tap-hold-release 150 180 b lctl)"
+            )
+            .unwrap(),
+            [
+                layout::hold_tap(200, 0, "Default", "k(LShift)", "k(A)"),
+                layout::hold_tap(180, 150, "PermissiveHold", "k(LCtrl)", "k(B)"),
+            ]
+        );
+        assert_eq!(
+            keys("", "(tap-hold-press 0 200 a)").err().unwrap(),
+            "layer `l0`: `tap-hold-press` expects 4 parameters"
+        );
+    }
+
+    #[test]
+    fn one_shots() {
+        let layers = parse(&config(
+            "",
+            &[
+                "(one-shot 500 lsft) (one-shot 800 (layer-while-held l1))",
+                "",
+            ],
+        ))
+        .unwrap();
+        assert_eq!(layers[0].keys[..2], ["osm(LShift, 500)", "osl(1, 800)"]);
+        assert_eq!(
+            keys("", "(one-shot 500 C-a)").err().unwrap(),
+            "layer `l0`: `one-shot` only supports a modifier or a layer"
+        );
+    }
+
+    #[test]
+    fn layers() {
+        let layers = parse(&config(
+            "",
+            &["(layer-while-held l1) (layer-switch l1)", ""],
+        ))
+        .unwrap();
+        assert_eq!(layers[0].keys[..2], ["l(1)", "d(1)"]);
+        assert_eq!(
+            keys("", "(layer-switch nav)").err().unwrap(),
+            "layer `l0`: unknown layer `nav`"
+        );
+    }
+
+    #[test]
+    fn multi_macro_and_unicode() {
+        assert_eq!(
+            keys("", "(multi lctl a) (macro a 10 S-b) (unicode €)").unwrap(),
+            [
+                "Action::MultipleActions(&[k(LCtrl), k(A)].as_slice())".to_string(),
+                layout::sequence(&[
+                    "Tap(A)".to_string(),
+                    "Delay(10)".to_string(),
+                    "Press(LShift)".to_string(),
+                    "Tap(B)".to_string(),
+                    "Release(LShift)".to_string(),
+                ]),
+                "unicode('€', '€')".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn aliases() {
+        assert_eq!(
+            keys("cpy C-c hr (tap-hold 0 200 @cpy lalt)", "@cpy @hr").unwrap(),
+            [
+                "m(&[LCtrl, C].as_slice())".to_string(),
+                layout::hold_tap(200, 0, "Default", "k(LAlt)", "m(&[LCtrl, C].as_slice())"),
+            ]
+        );
+        assert_eq!(
+            keys("", "@cpy").err().unwrap(),
+            "layer `l0`: unknown alias `@cpy`"
+        );
+    }
+
+    #[test]
+    fn alias_used_twice() {
+        // An alias is only a loop while it is being resolved
+        assert_eq!(
+            keys("x lsft two (multi @x @x)", "@two @x").unwrap(),
+            [
+                "Action::MultipleActions(&[k(LShift), k(LShift)].as_slice())",
+                "k(LShift)",
+            ]
+        );
+    }
+
+    #[test]
+    fn alias_referring_to_itself() {
+        assert_eq!(
+            keys("loop (multi a @loop)", "@loop").err().unwrap(),
+            "layer `l0`: alias `@loop` refers to itself"
+        );
+        assert_eq!(
+            keys("ping @pong pong (tap-hold 0 200 a @ping)", "@ping")
+                .err()
+                .unwrap(),
+            "layer `l0`: alias `@ping` refers to itself"
+        );
+    }
+
+    #[test]
+    fn unsupported() {
+        assert_eq!(
+            keys("", "(tap-dance 200 (a b))").err().unwrap(),
+            "layer `l0`: unsupported action `tap-dance`"
+        );
+        assert_eq!(
+            parse("(defvar x 1)").err().unwrap(),
+            "unsupported form `defvar`"
+        );
+        assert_eq!(parse("a").err().unwrap(), "unexpected `a` at top level");
+        assert_eq!(parse("()").err().unwrap(), "empty form");
+        assert_eq!(parse("(defalias a)").err().unwrap(), "invalid `defalias`");
+        assert_eq!(
+            parse("(defsrc a)").err().unwrap(),
+            "the configuration has no `deflayer`"
+        );
+        assert_eq!(parse("(deflayer").err().unwrap(), "unbalanced `(`");
+        assert_eq!(parse(")").err().unwrap(), "unbalanced `)`");
+    }
+
+    #[test]
+    fn example_config() {
+        let source = include_str!("../../keymaps/ferris.kbd");
+        let layers = parse(source).unwrap();
+        assert_eq!(layers.len(), 4);
+        assert_eq!(layers[0].name, "base");
+        assert_eq!(layers[2].keys[0], "k(Escape)");
+    }
+}
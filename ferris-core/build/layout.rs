@@ -0,0 +1,107 @@
+//! Rust code generation for the 34 keys of the Ferris
+//!
+//! Importers translate every binding into a keyberon `Action` expression and
+//! this module places them on the 10x4 matrix used by `KBLayout`.
+
+use std::fmt::Write;
+
+/// Number of keys on a Ferris/Sweep
+pub const KEYS: usize = 34;
+
+/// A layer as read from an external keymap
+pub struct Layer {
+    /// Name of the layer, only used as a comment
+    pub name: String,
+    /// The 34 actions, as Rust expressions, from top-left to bottom-right
+    pub keys: Vec<String>,
+}
+
+/// Position on the 10x4 matrix of the `index`-th key of a 34 keys layer
+///
+/// The first 30 keys fill the 3 main rows, the 4 thumb keys are on the
+/// columns 3 to 6 of the last row.
+fn position(index: usize) -> (usize, usize) {
+    if index < 30 {
+        (index / 10, index % 10)
+    } else {
+        (3, index - 30 + 3)
+    }
+}
+
+/// Write an action the way the `layout!` macro expects it
+fn cell(action: &str) -> String {
+    match action {
+        "t" | "Action::Trans" => "t".to_string(),
+        "n" | "Action::NoOp" => "n".to_string(),
+        _ => format!("{{{}}}", action),
+    }
+}
+
+/// Generate the `KBLayout` type and the `LAYERS` static
+pub fn emit(layers: &[Layer]) -> String {
+    let mut out = String::new();
+    writeln!(out, "/// Keyboard Layout type to mask the number of layers").unwrap();
+    writeln!(
+        out,
+        "pub type KBLayout = Layout<10, 4, {}, CustomAction>;",
+        layers.len()
+    )
+    .unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "#[rustfmt::skip]").unwrap();
+    writeln!(out, "/// Layout").unwrap();
+    writeln!(
+        out,
+        "pub static LAYERS: keyberon::layout::Layers<10, 4, {}, CustomAction> = keyberon::layout::layout! {{",
+        layers.len()
+    )
+    .unwrap();
+    for (i, layer) in layers.iter().enumerate() {
+        let mut rows = vec![vec!["n".to_string(); 10]; 4];
+        for (index, action) in layer.keys.iter().enumerate() {
+            let (row, col) = position(index);
+            rows[row][col] = cell(action);
+        }
+        let sep = if i == 0 { "    {" } else { "    } {" };
+        writeln!(out, "{} // {}: {}", sep, i, layer.name).unwrap();
+        for row in rows {
+            writeln!(out, "        [ {} ],", row.join(" ")).unwrap();
+        }
+    }
+    if !layers.is_empty() {
+        writeln!(out, "    }}").unwrap();
+    }
+    writeln!(out, "}};").unwrap();
+    out
+}
+
+/// Rust expression of a key, or of a chord when several keycodes are given
+pub fn keycodes(codes: &[&str]) -> String {
+    match codes {
+        [code] => format!("k({})", code),
+        _ => format!("m(&[{}].as_slice())", codes.join(", ")),
+    }
+}
+
+/// Rust expression of a sequence of `SequenceEvent`s, such as `Tap(A)`
+pub fn sequence(events: &[String]) -> String {
+    let events: Vec<String> = events
+        .iter()
+        .map(|event| format!("SequenceEvent::{}", event))
+        .collect();
+    format!("sequence(&[{}])", events.join(", "))
+}
+
+/// Rust expression of a `HoldTapAction`
+pub fn hold_tap(
+    timeout: u16,
+    tap_hold_interval: u16,
+    config: &str,
+    hold: &str,
+    tap: &str,
+) -> String {
+    format!(
+        "Action::HoldTap(&HoldTapAction {{ timeout: {}, tap_hold_interval: {}, config: HoldTapConfig::{}, hold: {}, tap: {} }})",
+        timeout, tap_hold_interval, config, hold, tap
+    )
+}
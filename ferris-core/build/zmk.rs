@@ -0,0 +1,931 @@
+//! Importer for ZMK `.keymap` devicetree files
+//!
+//! Only the `zmk,keymap` node is read. Each of its children is a layer whose
+//! `bindings` must list the 34 keys of a Ferris/Sweep. The supported
+//! behaviors are `&kp`, `&mt`, `&lt`, `&mo`, `&to`, `&sk`, `&sl`, `&trans`
+//! and `&none`.
+//! Simple `#define NAME value` lines can be used to name the layers.
+//!
+//! ZMK activates the highest layer held, while keyberon adds up the layers
+//! held. The `&mo` and `&lt` of a layer that is itself held are translated
+//! into the difference between the layer to activate and the one held, and
+//! so can only activate higher layers. Layers activated by `&to` or `&sl` are
+//! not held, so they cannot hold other layers.
+
+use crate::layout::{self, Layer, KEYS};
+use std::collections::HashMap;
+
+/// Timeout used for `&mt` and `&lt`, matching ZMK's default `tapping-term-ms`
+///
+/// Their flavors are ZMK's defaults too: hold-preferred for `&mt`, held as
+/// soon as another key is pressed, and tap-preferred for `&lt`, only held
+/// once the timeout expires.
+const TIMEOUT: u16 = 200;
+/// Timeout used for `&sk` and `&sl`, matching ZMK's default `release-after-ms`
+const ONE_SHOT_TIMEOUT: u16 = 1000;
+
+/// ZMK keycode names, their keyberon equivalent and whether they are shifted
+const KEYCODES: &[(&str, &str, bool)] = &[
+    ("A", "A", false),
+    ("B", "B", false),
+    ("C", "C", false),
+    ("D", "D", false),
+    ("E", "E", false),
+    ("F", "F", false),
+    ("G", "G", false),
+    ("H", "H", false),
+    ("I", "I", false),
+    ("J", "J", false),
+    ("K", "K", false),
+    ("L", "L", false),
+    ("M", "M", false),
+    ("N", "N", false),
+    ("O", "O", false),
+    ("P", "P", false),
+    ("Q", "Q", false),
+    ("R", "R", false),
+    ("S", "S", false),
+    ("T", "T", false),
+    ("U", "U", false),
+    ("V", "V", false),
+    ("W", "W", false),
+    ("X", "X", false),
+    ("Y", "Y", false),
+    ("Z", "Z", false),
+    ("N1", "Kb1", false),
+    ("N2", "Kb2", false),
+    ("N3", "Kb3", false),
+    ("N4", "Kb4", false),
+    ("N5", "Kb5", false),
+    ("N6", "Kb6", false),
+    ("N7", "Kb7", false),
+    ("N8", "Kb8", false),
+    ("N9", "Kb9", false),
+    ("N0", "Kb0", false),
+    ("NUMBER_1", "Kb1", false),
+    ("NUMBER_2", "Kb2", false),
+    ("NUMBER_3", "Kb3", false),
+    ("NUMBER_4", "Kb4", false),
+    ("NUMBER_5", "Kb5", false),
+    ("NUMBER_6", "Kb6", false),
+    ("NUMBER_7", "Kb7", false),
+    ("NUMBER_8", "Kb8", false),
+    ("NUMBER_9", "Kb9", false),
+    ("NUMBER_0", "Kb0", false),
+    ("RET", "Enter", false),
+    ("ENTER", "Enter", false),
+    ("RETURN", "Enter", false),
+    ("ESC", "Escape", false),
+    ("ESCAPE", "Escape", false),
+    ("BSPC", "BSpace", false),
+    ("BACKSPACE", "BSpace", false),
+    ("TAB", "Tab", false),
+    ("SPC", "Space", false),
+    ("SPACE", "Space", false),
+    ("MINUS", "Minus", false),
+    ("EQUAL", "Equal", false),
+    ("LBKT", "LBracket", false),
+    ("LEFT_BRACKET", "LBracket", false),
+    ("RBKT", "RBracket", false),
+    ("RIGHT_BRACKET", "RBracket", false),
+    ("BSLH", "Bslash", false),
+    ("BACKSLASH", "Bslash", false),
+    ("SEMI", "SColon", false),
+    ("SEMICOLON", "SColon", false),
+    ("SQT", "Quote", false),
+    ("APOS", "Quote", false),
+    ("APOSTROPHE", "Quote", false),
+    ("SINGLE_QUOTE", "Quote", false),
+    ("GRAVE", "Grave", false),
+    ("COMMA", "Comma", false),
+    ("DOT", "Dot", false),
+    ("PERIOD", "Dot", false),
+    ("FSLH", "Slash", false),
+    ("SLASH", "Slash", false),
+    ("CAPS", "CapsLock", false),
+    ("CAPSLOCK", "CapsLock", false),
+    ("CLCK", "CapsLock", false),
+    ("F1", "F1", false),
+    ("F2", "F2", false),
+    ("F3", "F3", false),
+    ("F4", "F4", false),
+    ("F5", "F5", false),
+    ("F6", "F6", false),
+    ("F7", "F7", false),
+    ("F8", "F8", false),
+    ("F9", "F9", false),
+    ("F10", "F10", false),
+    ("F11", "F11", false),
+    ("F12", "F12", false),
+    ("PSCRN", "PScreen", false),
+    ("PRINTSCREEN", "PScreen", false),
+    ("SLCK", "ScrollLock", false),
+    ("SCROLLLOCK", "ScrollLock", false),
+    ("PAUSE_BREAK", "Pause", false),
+    ("INS", "Insert", false),
+    ("INSERT", "Insert", false),
+    ("HOME", "Home", false),
+    ("END", "End", false),
+    ("PG_UP", "PgUp", false),
+    ("PAGE_UP", "PgUp", false),
+    ("PG_DN", "PgDown", false),
+    ("PAGE_DOWN", "PgDown", false),
+    ("DEL", "Delete", false),
+    ("DELETE", "Delete", false),
+    ("LEFT", "Left", false),
+    ("LARW", "Left", false),
+    ("RIGHT", "Right", false),
+    ("RARW", "Right", false),
+    ("UP", "Up", false),
+    ("UARW", "Up", false),
+    ("DOWN", "Down", false),
+    ("DARW", "Down", false),
+    ("K_APP", "Application", false),
+    ("K_APPLICATION", "Application", false),
+    ("LSHIFT", "LShift", false),
+    ("LSHFT", "LShift", false),
+    ("LEFT_SHIFT", "LShift", false),
+    ("RSHIFT", "RShift", false),
+    ("RSHFT", "RShift", false),
+    ("RIGHT_SHIFT", "RShift", false),
+    ("LCTRL", "LCtrl", false),
+    ("LCTL", "LCtrl", false),
+    ("LEFT_CONTROL", "LCtrl", false),
+    ("RCTRL", "RCtrl", false),
+    ("RCTL", "RCtrl", false),
+    ("RIGHT_CONTROL", "RCtrl", false),
+    ("LALT", "LAlt", false),
+    ("LEFT_ALT", "LAlt", false),
+    ("RALT", "RAlt", false),
+    ("RIGHT_ALT", "RAlt", false),
+    ("LGUI", "LGui", false),
+    ("LCMD", "LGui", false),
+    ("LWIN", "LGui", false),
+    ("LMETA", "LGui", false),
+    ("LEFT_GUI", "LGui", false),
+    ("RGUI", "RGui", false),
+    ("RCMD", "RGui", false),
+    ("RWIN", "RGui", false),
+    ("RMETA", "RGui", false),
+    ("RIGHT_GUI", "RGui", false),
+    ("C_VOL_UP", "VolUp", false),
+    ("C_VOLUME_UP", "VolUp", false),
+    ("C_VOL_DN", "VolDown", false),
+    ("C_VOLUME_DOWN", "VolDown", false),
+    ("C_MUTE", "Mute", false),
+    ("C_PP", "MediaPlayPause", false),
+    ("C_PLAY_PAUSE", "MediaPlayPause", false),
+    ("C_NEXT", "MediaNextSong", false),
+    ("C_PREV", "MediaPreviousSong", false),
+    ("C_PREVIOUS", "MediaPreviousSong", false),
+    ("EXCL", "Kb1", true),
+    ("EXCLAMATION", "Kb1", true),
+    ("AT", "Kb2", true),
+    ("AT_SIGN", "Kb2", true),
+    ("HASH", "Kb3", true),
+    ("POUND", "Kb3", true),
+    ("DLLR", "Kb4", true),
+    ("DOLLAR", "Kb4", true),
+    ("PRCNT", "Kb5", true),
+    ("PERCENT", "Kb5", true),
+    ("CARET", "Kb6", true),
+    ("AMPS", "Kb7", true),
+    ("AMPERSAND", "Kb7", true),
+    ("STAR", "Kb8", true),
+    ("ASTRK", "Kb8", true),
+    ("ASTERISK", "Kb8", true),
+    ("LPAR", "Kb9", true),
+    ("LEFT_PARENTHESIS", "Kb9", true),
+    ("RPAR", "Kb0", true),
+    ("RIGHT_PARENTHESIS", "Kb0", true),
+    ("UNDER", "Minus", true),
+    ("UNDERSCORE", "Minus", true),
+    ("PLUS", "Equal", true),
+    ("LBRC", "LBracket", true),
+    ("LEFT_BRACE", "LBracket", true),
+    ("RBRC", "RBracket", true),
+    ("RIGHT_BRACE", "RBracket", true),
+    ("PIPE", "Bslash", true),
+    ("COLON", "SColon", true),
+    ("DQT", "Quote", true),
+    ("DOUBLE_QUOTES", "Quote", true),
+    ("TILDE", "Grave", true),
+    ("LT", "Comma", true),
+    ("LESS_THAN", "Comma", true),
+    ("GT", "Dot", true),
+    ("GREATER_THAN", "Dot", true),
+    ("QMARK", "Slash", true),
+    ("QUESTION", "Slash", true),
+];
+
+/// Modifier functions such as `LS(A)` and the modifier they add
+const MODIFIER_FUNCTIONS: &[(&str, &str)] = &[
+    ("LS", "LShift"),
+    ("LC", "LCtrl"),
+    ("LA", "LAlt"),
+    ("LG", "LGui"),
+    ("RS", "RShift"),
+    ("RC", "RCtrl"),
+    ("RA", "RAlt"),
+    ("RG", "RGui"),
+];
+
+/// A devicetree node
+struct Node {
+    /// Name of the node
+    name: String,
+    /// Properties with their raw tokens
+    properties: Vec<(String, Vec<String>)>,
+    /// Child nodes
+    children: Vec<Node>,
+}
+
+impl Node {
+    /// Raw tokens of a property
+    fn property(&self, name: &str) -> Option<&[String]> {
+        self.properties
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_slice())
+    }
+
+    /// Find, depth first, the node that is compatible with `compatible`
+    fn find_compatible(&self, compatible: &str) -> Option<&Node> {
+        if let Some(value) = self.property("compatible") {
+            if value.iter().any(|v| v.trim_matches('"') == compatible) {
+                return Some(self);
+            }
+        }
+        self.children
+            .iter()
+            .find_map(|c| c.find_compatible(compatible))
+    }
+}
+
+/// Split the source into tokens, dropping comments and preprocessor lines
+///
+/// `#define` lines are recorded into `defines`.
+fn tokenize(source: &str, defines: &mut HashMap<String, String>) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut at_line_start = true;
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => at_line_start = true,
+            c if c.is_whitespace() => {}
+            '#' if at_line_start => {
+                let mut line = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    line.push(c);
+                    chars.next();
+                }
+                let mut words = line.split_whitespace();
+                if words.next() == Some("define") {
+                    if let (Some(name), Some(value)) = (words.next(), words.next()) {
+                        defines.insert(name.to_string(), value.to_string());
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    if c == '\n' {
+                        at_line_start = true;
+                    }
+                    previous = c;
+                }
+                continue;
+            }
+            '{' | '}' | '<' | '>' | ';' | '=' | ',' | '(' | ')' => {
+                at_line_start = false;
+                tokens.push(c.to_string());
+            }
+            '"' => {
+                at_line_start = false;
+                let mut string = String::from('"');
+                for c in chars.by_ref() {
+                    string.push(c);
+                    if c == '"' {
+                        break;
+                    }
+                }
+                tokens.push(string);
+            }
+            _ => {
+                at_line_start = false;
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "{}<>;=,()\"".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(word);
+            }
+        }
+    }
+    tokens
+}
+
+/// Parse the content of a node, after its opening brace
+fn parse_node(name: String, tokens: &[String], pos: &mut usize) -> Result<Node, String> {
+    let mut node = Node {
+        name,
+        properties: Vec::new(),
+        children: Vec::new(),
+    };
+    loop {
+        let token = tokens
+            .get(*pos)
+            .ok_or_else(|| format!("unterminated node `{}`", node.name))?;
+        *pos += 1;
+        if token == "}" {
+            if tokens.get(*pos).map(String::as_str) == Some(";") {
+                *pos += 1;
+            }
+            return Ok(node);
+        }
+        if token.ends_with(':') {
+            // Node label
+            continue;
+        }
+        match tokens.get(*pos).map(String::as_str) {
+            Some("{") => {
+                *pos += 1;
+                node.children.push(parse_node(token.clone(), tokens, pos)?);
+            }
+            Some("=") => {
+                *pos += 1;
+                let start = *pos;
+                while tokens.get(*pos).map(String::as_str) != Some(";") {
+                    if *pos >= tokens.len() {
+                        return Err(format!("unterminated property `{}`", token));
+                    }
+                    *pos += 1;
+                }
+                node.properties
+                    .push((token.clone(), tokens[start..*pos].to_vec()));
+                *pos += 1;
+            }
+            Some(";") => {
+                *pos += 1;
+                node.properties.push((token.clone(), Vec::new()));
+            }
+            _ => return Err(format!("unexpected token after `{}`", token)),
+        }
+    }
+}
+
+/// Parse the whole devicetree into a root node holding every top level node
+fn parse_tree(tokens: &[String]) -> Result<Node, String> {
+    let mut root = Node {
+        name: String::new(),
+        properties: Vec::new(),
+        children: Vec::new(),
+    };
+    let mut pos = 0;
+    while pos < tokens.len() {
+        let token = &tokens[pos];
+        pos += 1;
+        if token.ends_with(':') || token == ";" {
+            continue;
+        }
+        if tokens.get(pos).map(String::as_str) == Some("{") {
+            pos += 1;
+            root.children
+                .push(parse_node(token.clone(), tokens, &mut pos)?);
+        }
+    }
+    Ok(root)
+}
+
+/// Split the `bindings` tokens into behaviors with their parameters
+///
+/// Parameters using modifier functions, such as `LS(LC(A))`, are joined back
+/// into a single parameter.
+fn split_bindings(value: &[String]) -> Result<Vec<(String, Vec<String>)>, String> {
+    let mut bindings: Vec<(String, Vec<String>)> = Vec::new();
+    let mut depth = 0;
+    for token in value {
+        match token.as_str() {
+            "<" | ">" | "," if depth == 0 => {}
+            "(" => {
+                depth += 1;
+                append_to_last_param(&mut bindings, token)?;
+            }
+            ")" => {
+                depth -= 1;
+                append_to_last_param(&mut bindings, token)?;
+            }
+            _ if depth > 0 => append_to_last_param(&mut bindings, token)?,
+            _ if token.starts_with('&') => bindings.push((token.clone(), Vec::new())),
+            _ => bindings
+                .last_mut()
+                .ok_or_else(|| format!("parameter `{}` without behavior", token))?
+                .1
+                .push(token.clone()),
+        }
+    }
+    Ok(bindings)
+}
+
+/// Glue a token to the last parameter of the last binding
+fn append_to_last_param(bindings: &mut [(String, Vec<String>)], token: &str) -> Result<(), String> {
+    bindings
+        .last_mut()
+        .and_then(|(_, params)| params.last_mut())
+        .ok_or_else(|| format!("unexpected `{}` in bindings", token))?
+        .push_str(token);
+    Ok(())
+}
+
+/// Keyberon keycodes sent by a ZMK keycode, modifiers first
+fn keycode(param: &str) -> Result<Vec<&'static str>, String> {
+    if let Some(open) = param.find('(') {
+        let function = &param[..open];
+        let inner = param[open + 1..]
+            .strip_suffix(')')
+            .ok_or_else(|| format!("unbalanced parentheses in `{}`", param))?;
+        let modifier = MODIFIER_FUNCTIONS
+            .iter()
+            .find(|(f, _)| *f == function)
+            .ok_or_else(|| format!("unknown modifier function `{}`", function))?
+            .1;
+        let mut codes = vec![modifier];
+        codes.extend(keycode(inner)?);
+        return Ok(codes);
+    }
+    let (_, code, shifted) = KEYCODES
+        .iter()
+        .find(|(name, _, _)| *name == param)
+        .ok_or_else(|| format!("unknown keycode `{}`", param))?;
+    Ok(if *shifted {
+        vec!["LShift", code]
+    } else {
+        vec![code]
+    })
+}
+
+/// Layer index of a parameter, resolving `#define`d names
+fn layer(param: &str, defines: &HashMap<String, String>) -> Result<usize, String> {
+    let value = defines.get(param).map(String::as_str).unwrap_or(param);
+    value
+        .parse()
+        .map_err(|_| format!("invalid layer `{}`", param))
+}
+
+/// Layer to hold with `l(x)` to activate `target` from `current`, keyberon
+/// adding up the layers held, `defaults` being the layers activated by `&to`
+/// or `&sl`
+fn held_layer(target: usize, current: usize, defaults: &[usize]) -> Result<usize, String> {
+    if current == 0 {
+        return Ok(target);
+    }
+    if defaults.contains(&current) {
+        return Err(format!(
+            "layer {} is activated by `&to` or `&sl`, it cannot hold layer {}",
+            current, target
+        ));
+    }
+    target
+        .checked_sub(current)
+        .filter(|&offset| offset > 0)
+        .ok_or_else(|| {
+            format!(
+                "layer {} cannot be held from layer {}, only higher layers can",
+                target, current
+            )
+        })
+}
+
+/// Convert one ZMK binding of the layer `current` into a keyberon action
+/// expression, `defaults` being the layers activated by `&to` or `&sl`
+fn action(
+    behavior: &str,
+    params: &[String],
+    defines: &HashMap<String, String>,
+    current: usize,
+    defaults: &[usize],
+) -> Result<String, String> {
+    let expected = match behavior {
+        "&trans" | "&none" => 0,
+        "&kp" | "&mo" | "&to" | "&sk" | "&sl" => 1,
+        "&mt" | "&lt" => 2,
+        _ => return Err(format!("unsupported behavior `{}`", behavior)),
+    };
+    if params.len() != expected {
+        return Err(format!(
+            "`{}` expects {} parameter(s), got {}",
+            behavior,
+            expected,
+            params.len()
+        ));
+    }
+    Ok(match behavior {
+        "&trans" => "t".to_string(),
+        "&none" => "n".to_string(),
+        "&kp" => layout::keycodes(&keycode(&params[0])?),
+        "&mo" => format!(
+            "l({})",
+            held_layer(layer(&params[0], defines)?, current, defaults)?
+        ),
+        "&to" => format!("d({})", layer(&params[0], defines)?),
+        "&sk" => match keycode(&params[0])?.as_slice() {
+            [code] => format!("osm({}, {})", code, ONE_SHOT_TIMEOUT),
+            _ => return Err("`&sk` only supports a single modifier".to_string()),
+        },
+        "&sl" => format!("osl({}, {})", layer(&params[0], defines)?, ONE_SHOT_TIMEOUT),
+        "&mt" => layout::hold_tap(
+            TIMEOUT,
+            0,
+            "HoldOnOtherKeyPress",
+            &layout::keycodes(&keycode(&params[0])?),
+            &layout::keycodes(&keycode(&params[1])?),
+        ),
+        _ => layout::hold_tap(
+            TIMEOUT,
+            0,
+            "Default",
+            &format!(
+                "l({})",
+                held_layer(layer(&params[0], defines)?, current, defaults)?
+            ),
+            &layout::keycodes(&keycode(&params[1])?),
+        ),
+    })
+}
+
+/// Translate a `&trans` of the layer `current` at `index`, `base` being the
+/// bindings of the layer 0
+///
+/// keyberon resolves it to the binding of the layer 0. When that binding
+/// holds a higher layer, it is translated for `current`, like a `&mo` or
+/// `&lt` would be. When it holds `current` itself, it is the key holding the
+/// layer, and when it holds a lower layer, ZMK keeps `current` as the highest
+/// layer held, so only its tap is kept.
+fn transparent(
+    index: usize,
+    base: &[(String, Vec<String>)],
+    defines: &HashMap<String, String>,
+    current: usize,
+    defaults: &[usize],
+) -> Result<String, String> {
+    let (behavior, params) = &base[index];
+    if current == 0 || !matches!(behavior.as_str(), "&mo" | "&lt") {
+        return Ok("t".to_string());
+    }
+    let target = layer(&params[0], defines)?;
+    if target > current {
+        return action(behavior, params, defines, current, defaults);
+    }
+    if target == current {
+        return Ok("t".to_string());
+    }
+    Ok(match params.get(1) {
+        Some(tap) => layout::keycodes(&keycode(tap)?),
+        None => "n".to_string(),
+    })
+}
+
+/// Parse a ZMK `.keymap` file into layers
+pub fn parse(source: &str) -> Result<Vec<Layer>, String> {
+    let mut defines = HashMap::new();
+    let tokens = tokenize(source, &mut defines);
+    let root = parse_tree(&tokens)?;
+    let keymap = root
+        .find_compatible("zmk,keymap")
+        .ok_or("no node with `compatible = \"zmk,keymap\"`")?;
+    let mut nodes = Vec::new();
+    let mut defaults = Vec::new();
+    let mut max_layer = 0;
+    for node in &keymap.children {
+        let bindings = node
+            .property("bindings")
+            .ok_or_else(|| format!("layer `{}` has no bindings", node.name))?;
+        let bindings = split_bindings(bindings)?;
+        if bindings.len() != KEYS {
+            return Err(format!(
+                "layer `{}` has {} bindings, expected {}",
+                node.name,
+                bindings.len(),
+                KEYS
+            ));
+        }
+        for (behavior, params) in &bindings {
+            let param = match (behavior.as_str(), params.first()) {
+                ("&mo" | "&to" | "&lt" | "&sl", Some(param)) => param,
+                _ => continue,
+            };
+            let target =
+                layer(param, &defines).map_err(|e| format!("layer `{}`: {}", node.name, e))?;
+            max_layer = max_layer.max(target);
+            if matches!(behavior.as_str(), "&to" | "&sl") && target != 0 {
+                defaults.push(target);
+            }
+        }
+        nodes.push((node, bindings));
+    }
+    let mut layers = Vec::new();
+    for (current, (node, bindings)) in nodes.iter().enumerate() {
+        let mut keys = Vec::with_capacity(KEYS);
+        for (index, (behavior, params)) in bindings.iter().enumerate() {
+            let key = match behavior.as_str() {
+                "&trans" if params.is_empty() => {
+                    transparent(index, &nodes[0].1, &defines, current, &defaults)
+                }
+                _ => action(behavior, params, &defines, current, &defaults),
+            };
+            keys.push(key.map_err(|e| format!("layer `{}`: {}", node.name, e))?);
+        }
+        let name = node
+            .property("display-name")
+            .or_else(|| node.property("label"))
+            .and_then(|v| v.first())
+            .map(|v| v.trim_matches('"').to_string())
+            .unwrap_or_else(|| node.name.clone());
+        layers.push(Layer { name, keys });
+    }
+    if layers.is_empty() {
+        return Err("the keymap has no layer".to_string());
+    }
+    if max_layer >= layers.len() {
+        return Err(format!(
+            "layer {} is used but the keymap only has {} layers",
+            max_layer,
+            layers.len()
+        ));
+    }
+    Ok(layers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Write;
+
+    /// A keymap with a layer per item of `layers`, each starting with the
+    /// given bindings, followed by `&none`
+    fn keymap(layers: &[&str]) -> String {
+        let mut source = String::from("#define NUM 2\n/ {\n keymap {\n");
+        source.push_str("  compatible = \"zmk,keymap\";\n");
+        for (i, bindings) in layers.iter().enumerate() {
+            let none = vec!["&none"; KEYS - bindings.matches('&').count()];
+            writeln!(
+                source,
+                "  layer_{} {{ bindings = <{} {}>; }};",
+                i,
+                bindings,
+                none.join(" ")
+            )
+            .unwrap();
+        }
+        source.push_str(" };\n};\n");
+        source
+    }
+
+    /// Actions of the bindings given to `keymap`, layer by layer
+    fn import(layers: &[&str]) -> Result<Vec<Vec<String>>, String> {
+        let imported = parse(&keymap(layers))?;
+        Ok(imported
+            .into_iter()
+            .zip(layers)
+            .map(|(layer, bindings)| {
+                let count = bindings.matches('&').count();
+                layer.keys.into_iter().take(count).collect()
+            })
+            .collect())
+    }
+
+    /// Actions of the bindings of a single layer
+    fn keys(bindings: &str) -> Vec<String> {
+        import(&[bindings]).unwrap().remove(0)
+    }
+
+    #[test]
+    fn key_press() {
+        assert_eq!(
+            keys("&kp A &kp N1 &kp RET &kp EXCL &kp LS(LC(A))"),
+            [
+                "k(A)",
+                "k(Kb1)",
+                "k(Enter)",
+                "m(&[LShift, Kb1].as_slice())",
+                "m(&[LShift, LCtrl, A].as_slice())",
+            ]
+        );
+    }
+
+    #[test]
+    fn mod_tap() {
+        assert_eq!(
+            keys("&mt LSHFT A"),
+            [layout::hold_tap(
+                TIMEOUT,
+                0,
+                "HoldOnOtherKeyPress",
+                "k(LShift)",
+                "k(A)"
+            )]
+        );
+    }
+
+    #[test]
+    fn layer_tap() {
+        assert_eq!(
+            import(&["&lt 1 TAB", ""]).unwrap()[0],
+            [layout::hold_tap(TIMEOUT, 0, "Default", "l(1)", "k(Tab)")]
+        );
+    }
+
+    #[test]
+    fn momentary_layer() {
+        assert_eq!(import(&["&mo NUM", "", ""]).unwrap()[0], ["l(2)"]);
+    }
+
+    #[test]
+    fn to_layer() {
+        assert_eq!(import(&["&to 1", "&to 0"]).unwrap(), [["d(1)"], ["d(0)"]]);
+    }
+
+    #[test]
+    fn sticky_key_and_layer() {
+        assert_eq!(
+            import(&["&sk LSHFT &sl 1", ""]).unwrap()[0],
+            ["osm(LShift, 1000)", "osl(1, 1000)"]
+        );
+    }
+
+    #[test]
+    fn transparent_and_none() {
+        assert_eq!(import(&["", "&trans &none"]).unwrap()[1], ["t", "n"]);
+    }
+
+    #[test]
+    fn comments_and_labels() {
+        let source = keymap(&["&kp A // A\n/* B */ &kp B"]).replace(
+            "bindings",
+            "label = \"Base\";\n  display-name = \"Letters\";\n  bindings",
+        );
+        let layers = parse(&source).unwrap();
+        assert_eq!(layers[0].name, "Letters");
+        assert_eq!(layers[0].keys[..2], ["k(A)", "k(B)"]);
+    }
+
+    #[test]
+    fn layer_held_from_a_held_layer() {
+        // ZMK activates NUM, keyberon adds 1 to the SYM layer held
+        assert_eq!(
+            import(&["&mo 1", "&mo NUM &lt NUM A", ""]).unwrap()[1],
+            [
+                "l(1)".to_string(),
+                layout::hold_tap(TIMEOUT, 0, "Default", "l(1)", "k(A)")
+            ]
+        );
+    }
+
+    #[test]
+    fn transparent_over_a_layer() {
+        let layers = import(&[
+            "&lt 1 TAB &lt 2 RET &mo 1 &mo 2",
+            "&trans &trans &trans &trans",
+            "&trans &trans &trans &trans",
+        ])
+        .unwrap();
+        // The key holding the layer, or a higher one, held by the difference
+        assert_eq!(
+            layers[1],
+            [
+                "t".to_string(),
+                layout::hold_tap(TIMEOUT, 0, "Default", "l(1)", "k(Enter)"),
+                "t".to_string(),
+                "l(1)".to_string(),
+            ]
+        );
+        // A lower layer, only tapped
+        assert_eq!(layers[2], ["k(Tab)", "t", "n", "t"]);
+    }
+
+    #[test]
+    fn lower_layer_held_from_a_held_layer() {
+        assert_eq!(
+            import(&["&mo 2", "", "&mo 1"]),
+            Err(
+                "layer `layer_2`: layer 1 cannot be held from layer 2, only higher layers can"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn layer_held_from_a_default_layer() {
+        let error =
+            "layer `layer_1`: layer 1 is activated by `&to` or `&sl`, it cannot hold layer 2";
+        assert_eq!(import(&["&to 1", "&mo 2", ""]), Err(error.to_string()));
+        assert_eq!(import(&["&sl 1", "&mo 2", ""]), Err(error.to_string()));
+        assert_eq!(
+            import(&["&sl 1 &mo 2", "&none &trans", ""]),
+            Err(error.to_string())
+        );
+    }
+
+    #[test]
+    fn layer_beyond_count() {
+        assert_eq!(
+            import(&["&mo 1"]),
+            Err("layer 1 is used but the keymap only has 1 layers".to_string())
+        );
+    }
+
+    #[test]
+    fn unsupported_behavior() {
+        assert_eq!(
+            import(&["&bt BT_CLR"]),
+            Err("layer `layer_0`: unsupported behavior `&bt`".to_string())
+        );
+    }
+
+    #[test]
+    fn wrong_parameter_count() {
+        assert_eq!(
+            import(&["&mt A"]),
+            Err("layer `layer_0`: `&mt` expects 2 parameter(s), got 1".to_string())
+        );
+    }
+
+    #[test]
+    fn unknown_keycode() {
+        assert_eq!(
+            import(&["&kp FOO"]),
+            Err("layer `layer_0`: unknown keycode `FOO`".to_string())
+        );
+        assert_eq!(
+            import(&["&kp XX(A)"]),
+            Err("layer `layer_0`: unknown modifier function `XX`".to_string())
+        );
+    }
+
+    #[test]
+    fn sticky_chord() {
+        assert_eq!(
+            import(&["&sk LS(LCTRL)"]),
+            Err("layer `layer_0`: `&sk` only supports a single modifier".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_layer() {
+        assert_eq!(
+            import(&["&mo SYM"]),
+            Err("layer `layer_0`: invalid layer `SYM`".to_string())
+        );
+    }
+
+    #[test]
+    fn wrong_binding_count() {
+        let source = keymap(&["&kp A"]).replace("&none>", ">");
+        assert_eq!(
+            parse(&source).err(),
+            Some("layer `layer_0` has 33 bindings, expected 34".to_string())
+        );
+    }
+
+    #[test]
+    fn no_keymap() {
+        let source = keymap(&["&kp A"]).replace("zmk,keymap", "zmk,combos");
+        assert_eq!(
+            parse(&source).err(),
+            Some("no node with `compatible = \"zmk,keymap\"`".to_string())
+        );
+        let source = keymap(&[]);
+        assert_eq!(
+            parse(&source).err(),
+            Some("the keymap has no layer".to_string())
+        );
+    }
+
+    #[test]
+    fn example_keymap() {
+        let source = include_str!("../../keymaps/ferris.keymap");
+        let layers = parse(source).unwrap();
+        assert_eq!(layers.len(), 4);
+        // `&mo NUM` on the symbols and navigation layers
+        assert_eq!(layers[1].keys[30], "l(2)");
+        assert_eq!(layers[2].keys[33], "l(1)");
+    }
+}
@@ -0,0 +1,130 @@
+//! Actions specific to this firmware
+
+use crate::oneshot::{OneShot, OneShotTarget};
+use crate::sequence::{Sequence, SequenceEvent};
+use crate::unicode::UnicodeMode;
+use keyberon::key_code::KeyCode;
+
+/// Custom actions, used in the keymaps as `Action::Custom`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CustomAction {
+    /// Jump to the bootloader, on release, to flash a new firmware
+    Bootloader,
+    /// Play a sequence, on press
+    Sequence(Sequence),
+    /// One-shot modifier or layer
+    OneShot(OneShot),
+    /// Leader key, to type a sequence of the leader dictionary
+    Leader,
+    /// Toggle Caps Word
+    CapsWord,
+    /// Toggle Num Word on a layer
+    NumWord(usize),
+    /// Keep the layer held after its release, or release the locked layer
+    LayerLock,
+    /// Type a Unicode character, the second one when Shift is held
+    Unicode(char, char),
+    /// Change the input method of the host for Unicode characters
+    UnicodeMode(UnicodeMode),
+    /// Type a character, held while the key is held, with the layout of the
+    /// host
+    Char(char),
+    /// Toggle auto-shift
+    AutoShift,
+    /// Send the last key again, while held
+    Repeat,
+    /// Send the counterpart of the last key, while held
+    AltRepeat,
+    /// Mirror the keys across the split, while held
+    SwapHands,
+    /// Toggle the mirroring of the keys across the split
+    SwapHandsToggle,
+    /// Start recording a dynamic macro in a slot, or stop recording
+    MacroRecord(usize),
+    /// Play the dynamic macro of a slot, or stop recording
+    MacroPlay(usize),
+}
+
+/// Action type used by the keymaps
+pub type Action = keyberon::action::Action<CustomAction>;
+
+/// Leader key, to type a sequence of the leader dictionary
+pub const LEADER: Action = Action::Custom(CustomAction::Leader);
+
+/// Toggle Caps Word, shifting letters until the end of the word
+pub const CAPS_WORD: Action = Action::Custom(CustomAction::CapsWord);
+
+/// Toggle auto-shift, sending keys held long enough shifted
+pub const AUTO_SHIFT: Action = Action::Custom(CustomAction::AutoShift);
+
+/// Send the last key again, with its modifiers
+pub const REPEAT: Action = Action::Custom(CustomAction::Repeat);
+
+/// Send the counterpart of the last key, such as Down after Up
+pub const ALT_REPEAT: Action = Action::Custom(CustomAction::AltRepeat);
+
+/// Type the keys of the other half, while held
+pub const SWAP_HANDS: Action = Action::Custom(CustomAction::SwapHands);
+
+/// Toggle typing the keys of the other half
+pub const SWAP_HANDS_TOGGLE: Action = Action::Custom(CustomAction::SwapHandsToggle);
+
+/// Keep the layer held after its release, or release the locked layer
+pub const LAYER_LOCK: Action = Action::Custom(CustomAction::LayerLock);
+
+/// Toggle Num Word on `layer`, kept while typing a number
+pub const fn num_word(layer: usize) -> Action {
+    Action::Custom(CustomAction::NumWord(layer))
+}
+
+/// Start recording the dynamic macro of `slot`, or stop recording
+pub const fn record_macro(slot: usize) -> Action {
+    Action::Custom(CustomAction::MacroRecord(slot))
+}
+
+/// Play the dynamic macro of `slot`, or stop recording
+pub const fn play_macro(slot: usize) -> Action {
+    Action::Custom(CustomAction::MacroPlay(slot))
+}
+
+/// Action playing the steps of `events` in order
+pub const fn sequence(events: &'static [SequenceEvent]) -> Action {
+    Action::Custom(CustomAction::Sequence(Sequence::Events(events)))
+}
+
+/// Action typing `text`, with the layout of the host
+pub const fn string(text: &'static str) -> Action {
+    Action::Custom(CustomAction::Sequence(Sequence::Text(text)))
+}
+
+/// One-shot modifier, cancelled `timeout` milliseconds after its release
+pub const fn osm(kc: KeyCode, timeout: u16) -> Action {
+    Action::Custom(CustomAction::OneShot(OneShot {
+        target: OneShotTarget::Modifier(kc),
+        timeout,
+    }))
+}
+
+/// One-shot layer, cancelled `timeout` milliseconds after its release
+pub const fn osl(layer: usize, timeout: u16) -> Action {
+    Action::Custom(CustomAction::OneShot(OneShot {
+        target: OneShotTarget::Layer(layer),
+        timeout,
+    }))
+}
+
+/// Type the Unicode character `lower`, or `upper` when Shift is held, with
+/// the input method of the host
+pub const fn unicode(lower: char, upper: char) -> Action {
+    Action::Custom(CustomAction::Unicode(lower, upper))
+}
+
+/// Change the input method of the host for Unicode characters
+pub const fn unicode_mode(mode: UnicodeMode) -> Action {
+    Action::Custom(CustomAction::UnicodeMode(mode))
+}
+
+/// Type `c`, with the layout of the host, held while the key is held
+pub const fn ch(c: char) -> Action {
+    Action::Custom(CustomAction::Char(c))
+}
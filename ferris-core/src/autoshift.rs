@@ -0,0 +1,249 @@
+//! Auto-shift: keys held long enough are sent shifted
+//!
+//! When auto-shift is on, the press of a letter, digit or symbol key is held
+//! back. Released before the timeout, the key is tapped as usual. Held until
+//! the timeout, it is sent with Shift until its release. Pressing another key
+//! sends the waiting key unshifted, so fast typing is not disturbed.
+//!
+//! Only keys whose action is a plain keycode are shifted: hold-taps and
+//! other actions are left alone, as are keys pressed while a modifier is
+//! held, so that shortcuts are not shifted.
+
+use crate::action::Action;
+use heapless::{Deque, Vec};
+use keyberon::key_code::KeyCode::{self, *};
+use keyberon::layout::Event;
+
+/// Maximum number of keys held shifted at once
+const HELD: usize = 4;
+/// Maximum number of events waiting for the layout
+const OUTPUT: usize = 16;
+
+/// Whether `kc` is a letter, digit or symbol key
+fn is_shiftable(kc: KeyCode) -> bool {
+    (A <= kc && kc <= Kb0) || (Minus <= kc && kc <= Slash)
+}
+
+/// A key held back until it is known whether it is shifted
+struct Pending {
+    /// Position of the key
+    key: (u8, u8),
+    /// Keycode of its action
+    kc: KeyCode,
+    /// Milliseconds since its press
+    elapsed: u16,
+}
+
+/// State of auto-shift
+pub struct AutoShift {
+    /// Whether auto-shift is on
+    enabled: bool,
+    /// Milliseconds to hold a key to send it shifted
+    timeout: u16,
+    /// Key held back
+    pending: Option<Pending>,
+    /// Keys held shifted, with their keycode
+    shifted: Vec<((u8, u8), KeyCode), HELD>,
+    /// Events for the layout
+    output: Deque<Event, OUTPUT>,
+}
+
+impl AutoShift {
+    /// Create a new auto-shift state, on if `enabled`, shifting keys held
+    /// for `timeout` milliseconds
+    pub fn new(enabled: bool, timeout: u16) -> Self {
+        Self {
+            enabled,
+            timeout,
+            pending: None,
+            shifted: Vec::new(),
+            output: Deque::new(),
+        }
+    }
+
+    /// Turn auto-shift off if it is on, and on otherwise
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.flush();
+    }
+
+    /// Send the press of the key held back to the layout
+    fn flush(&mut self) {
+        if let Some(pending) = self.pending.take() {
+            let (i, j) = pending.key;
+            self.output.push_back(Event::Press(i, j)).ok();
+        }
+    }
+
+    /// Handle a key event, `action` being the action of the key and
+    /// `modified` whether a modifier is held
+    pub fn event(&mut self, event: Event, action: &Action, modified: bool) {
+        let key = event.coord();
+        if event.is_release() {
+            if let Some(index) = self.shifted.iter().position(|(k, _)| *k == key) {
+                self.shifted.swap_remove(index);
+                return;
+            }
+            if self.pending.as_ref().map(|p| p.key) == Some(key) {
+                self.flush();
+            }
+            self.output.push_back(event).ok();
+            return;
+        }
+        self.flush();
+        match action {
+            Action::KeyCode(kc) if self.enabled && !modified && is_shiftable(*kc) => {
+                self.pending = Some(Pending {
+                    key,
+                    kc: *kc,
+                    elapsed: 0,
+                });
+            }
+            _ => {
+                self.output.push_back(event).ok();
+            }
+        }
+    }
+
+    /// Advance the timeout of the key held back by one millisecond
+    ///
+    /// Returns whether the key was shifted.
+    pub fn tick(&mut self) -> bool {
+        let Some(pending) = &mut self.pending else {
+            return false;
+        };
+        pending.elapsed += 1;
+        if pending.elapsed < self.timeout {
+            return false;
+        }
+        let (key, kc) = (pending.key, pending.kc);
+        if self.shifted.push((key, kc)).is_err() {
+            self.flush();
+            return false;
+        }
+        self.pending = None;
+        true
+    }
+
+    /// Next event for the layout
+    pub fn pop(&mut self) -> Option<Event> {
+        self.output.pop_front()
+    }
+
+    /// Keys pressed for the keys held shifted
+    pub fn keycodes(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.shifted.iter().flat_map(|&(_, kc)| [LShift, kc])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keyberon::action::{k, l};
+
+    /// Milliseconds to hold a key to send it shifted
+    const TIMEOUT: u16 = 10;
+    /// Action of A
+    const KEY_A: Action = k(A);
+    /// Action holding a layer
+    const LAYER: Action = l(1);
+
+    /// Events sent to the layout so far
+    fn output(auto_shift: &mut AutoShift) -> Vec<Event, OUTPUT> {
+        core::iter::from_fn(|| auto_shift.pop()).collect()
+    }
+
+    /// Tick `auto_shift` for `ms` milliseconds
+    fn wait(auto_shift: &mut AutoShift, ms: u16) {
+        for _ in 0..ms {
+            auto_shift.tick();
+        }
+    }
+
+    /// Keys pressed for the keys held shifted
+    fn shifted(auto_shift: &AutoShift) -> Vec<KeyCode, 4> {
+        auto_shift.keycodes().collect()
+    }
+
+    #[test]
+    fn tap() {
+        let mut auto_shift = AutoShift::new(true, TIMEOUT);
+        auto_shift.event(Event::Press(0, 0), &KEY_A, false);
+        wait(&mut auto_shift, TIMEOUT - 1);
+        assert_eq!(output(&mut auto_shift), []);
+        auto_shift.event(Event::Release(0, 0), &KEY_A, false);
+        assert_eq!(
+            output(&mut auto_shift),
+            [Event::Press(0, 0), Event::Release(0, 0)]
+        );
+        assert_eq!(shifted(&auto_shift), []);
+    }
+
+    #[test]
+    fn hold() {
+        let mut auto_shift = AutoShift::new(true, TIMEOUT);
+        auto_shift.event(Event::Press(0, 0), &KEY_A, false);
+        wait(&mut auto_shift, TIMEOUT - 1);
+        assert!(auto_shift.tick());
+        assert_eq!(shifted(&auto_shift), [LShift, A]);
+        wait(&mut auto_shift, 100);
+        assert_eq!(shifted(&auto_shift), [LShift, A]);
+        // The layout never sees the key
+        auto_shift.event(Event::Release(0, 0), &KEY_A, false);
+        assert_eq!(output(&mut auto_shift), []);
+        assert_eq!(shifted(&auto_shift), []);
+    }
+
+    #[test]
+    fn another_key_pressed() {
+        let mut auto_shift = AutoShift::new(true, TIMEOUT);
+        auto_shift.event(Event::Press(0, 0), &KEY_A, false);
+        wait(&mut auto_shift, 5);
+        auto_shift.event(Event::Press(0, 1), &KEY_A, false);
+        assert_eq!(output(&mut auto_shift), [Event::Press(0, 0)]);
+        wait(&mut auto_shift, TIMEOUT);
+        // Only the last key is shifted
+        assert_eq!(shifted(&auto_shift), [LShift, A]);
+        auto_shift.event(Event::Release(0, 0), &KEY_A, false);
+        assert_eq!(output(&mut auto_shift), [Event::Release(0, 0)]);
+    }
+
+    #[test]
+    fn modifier_held() {
+        let mut auto_shift = AutoShift::new(true, TIMEOUT);
+        auto_shift.event(Event::Press(0, 0), &KEY_A, true);
+        assert_eq!(output(&mut auto_shift), [Event::Press(0, 0)]);
+        wait(&mut auto_shift, 100);
+        assert_eq!(shifted(&auto_shift), []);
+    }
+
+    #[test]
+    fn other_actions() {
+        let mut auto_shift = AutoShift::new(true, TIMEOUT);
+        auto_shift.event(Event::Press(3, 3), &LAYER, false);
+        auto_shift.event(Event::Press(3, 4), &k(Space), false);
+        assert_eq!(
+            output(&mut auto_shift),
+            [Event::Press(3, 3), Event::Press(3, 4)]
+        );
+        wait(&mut auto_shift, 100);
+        assert_eq!(shifted(&auto_shift), []);
+    }
+
+    #[test]
+    fn toggle() {
+        let mut auto_shift = AutoShift::new(false, TIMEOUT);
+        auto_shift.event(Event::Press(0, 0), &KEY_A, false);
+        assert_eq!(output(&mut auto_shift), [Event::Press(0, 0)]);
+        auto_shift.event(Event::Release(0, 0), &KEY_A, false);
+        output(&mut auto_shift);
+        auto_shift.toggle();
+        auto_shift.event(Event::Press(0, 0), &KEY_A, false);
+        assert_eq!(output(&mut auto_shift), []);
+        // Turning it off sends the key held back
+        auto_shift.toggle();
+        assert_eq!(output(&mut auto_shift), [Event::Press(0, 0)]);
+        wait(&mut auto_shift, 100);
+        assert_eq!(shifted(&auto_shift), []);
+    }
+}
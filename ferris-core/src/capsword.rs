@@ -0,0 +1,184 @@
+//! Caps Word: shift letters until the end of the word
+//!
+//! While Caps Word is on, Shift is added to the report while the last key
+//! pressed is a letter, so a `-` pressed while rolling off a letter is not
+//! shifted. Digits, `-`, `_`, Backspace and Delete keep it on, any other key
+//! turns it off, as does the timeout without any key pressed. Modifiers are
+//! ignored.
+
+use heapless::Vec;
+use keyberon::key_code::KeyCode::{self, *};
+
+/// Maximum number of keys tracked at once
+const KEYS: usize = 16;
+
+/// Whether `kc` types a letter
+fn is_letter(kc: KeyCode) -> bool {
+    A <= kc && kc <= Z
+}
+
+/// Whether `kc` keeps Caps Word on
+fn is_word(kc: KeyCode) -> bool {
+    is_letter(kc) || (Kb1 <= kc && kc <= Kb0) || matches!(kc, Minus | BSpace | Delete)
+}
+
+/// State of Caps Word
+pub struct CapsWord {
+    /// Milliseconds without any key pressed before Caps Word turns off
+    timeout: u16,
+    /// Whether Caps Word is on
+    active: bool,
+    /// Milliseconds since the last key pressed
+    elapsed: u16,
+    /// Keys of the layout on the last tick
+    pressed: Vec<KeyCode, KEYS>,
+    /// Last key pressed that is not a modifier, while it is held
+    last: Option<KeyCode>,
+}
+
+impl CapsWord {
+    /// Create a new Caps Word state, turned off
+    pub fn new(timeout: u16) -> Self {
+        Self {
+            timeout,
+            active: false,
+            elapsed: 0,
+            pressed: Vec::new(),
+            last: None,
+        }
+    }
+
+    /// Turn Caps Word on, or off if it was on
+    pub fn toggle(&mut self) {
+        self.active = !self.active;
+        self.elapsed = 0;
+    }
+
+    /// Whether Caps Word is on
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Turn Caps Word off on keys that are not part of a word
+    pub fn tick(&mut self, keycodes: impl Iterator<Item = KeyCode>) {
+        let keycodes: Vec<KeyCode, KEYS> = keycodes.collect();
+        for &kc in keycodes
+            .iter()
+            .filter(|kc| !kc.is_modifier() && !self.pressed.contains(kc))
+        {
+            self.last = Some(kc);
+            if self.active {
+                self.elapsed = 0;
+                self.active &= is_word(kc);
+            }
+        }
+        if self.active {
+            self.elapsed += 1;
+            self.active &= self.elapsed <= self.timeout;
+        }
+        self.last = self.last.filter(|kc| keycodes.contains(kc));
+        self.pressed = keycodes;
+    }
+
+    /// Modifiers to add to the report
+    pub fn modifiers(&self) -> impl Iterator<Item = KeyCode> {
+        let shift = self.active && self.last.is_some_and(is_letter);
+        shift.then_some(LShift).into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Milliseconds without any key pressed before Caps Word turns off
+    const TIMEOUT: u16 = 10;
+
+    /// Tap the keys of `keycodes`, all at once, returning the modifiers
+    /// added to them
+    fn tap(caps_word: &mut CapsWord, keycodes: &[KeyCode]) -> Vec<KeyCode, 1> {
+        caps_word.tick(keycodes.iter().copied());
+        let modifiers = caps_word.modifiers().collect();
+        caps_word.tick([].into_iter());
+        modifiers
+    }
+
+    /// Caps Word, turned on
+    fn caps_word() -> CapsWord {
+        let mut caps_word = CapsWord::new(TIMEOUT);
+        caps_word.toggle();
+        caps_word
+    }
+
+    #[test]
+    fn letters() {
+        let mut caps_word = caps_word();
+        assert_eq!(tap(&mut caps_word, &[A]), [LShift]);
+        assert_eq!(tap(&mut caps_word, &[B]), [LShift]);
+        caps_word.toggle();
+        assert_eq!(tap(&mut caps_word, &[C]), []);
+    }
+
+    #[test]
+    fn ends_on_space() {
+        let mut caps_word = caps_word();
+        tap(&mut caps_word, &[A]);
+        assert_eq!(tap(&mut caps_word, &[Space]), []);
+        assert!(!caps_word.is_active());
+        assert_eq!(tap(&mut caps_word, &[B]), []);
+    }
+
+    #[test]
+    fn ends_on_punctuation() {
+        for kc in [Dot, Comma, SColon, Quote, Slash, Enter, Escape] {
+            let mut caps_word = caps_word();
+            tap(&mut caps_word, &[A]);
+            tap(&mut caps_word, &[kc]);
+            assert!(!caps_word.is_active());
+        }
+    }
+
+    #[test]
+    fn continues_on_digits_and_dashes() {
+        let mut caps_word = caps_word();
+        // `_` is typed with Shift, which is ignored
+        for keycodes in [&[Kb1][..], &[Minus], &[LShift, Minus], &[Kb0]] {
+            assert_eq!(tap(&mut caps_word, keycodes), []);
+            assert!(caps_word.is_active());
+            assert_eq!(tap(&mut caps_word, &[A]), [LShift]);
+        }
+    }
+
+    #[test]
+    fn backspace() {
+        let mut caps_word = caps_word();
+        tap(&mut caps_word, &[A]);
+        assert_eq!(tap(&mut caps_word, &[BSpace]), []);
+        assert_eq!(tap(&mut caps_word, &[Delete]), []);
+        assert!(caps_word.is_active());
+        assert_eq!(tap(&mut caps_word, &[B]), [LShift]);
+    }
+
+    #[test]
+    fn rolling_off_a_letter() {
+        let mut caps_word = caps_word();
+        caps_word.tick([A].into_iter());
+        caps_word.tick([A, Minus].into_iter());
+        // Only the last key pressed is shifted
+        assert_eq!(caps_word.modifiers().next(), None);
+        caps_word.tick([Minus].into_iter());
+        assert_eq!(caps_word.modifiers().next(), None);
+    }
+
+    #[test]
+    fn timeout() {
+        let mut caps_word = caps_word();
+        tap(&mut caps_word, &[A]);
+        for _ in 0..TIMEOUT - 2 {
+            caps_word.tick([].into_iter());
+        }
+        assert!(caps_word.is_active());
+        caps_word.tick([].into_iter());
+        assert!(!caps_word.is_active());
+    }
+}
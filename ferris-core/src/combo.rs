@@ -0,0 +1,372 @@
+//! Combos: keys pressed together acting as another key
+//!
+//! When all the keys of a combo are pressed within the combo timeout, the
+//! layout sees a press of the combo's output position instead, and its
+//! release when the first key of the combo is released. The output is
+//! usually one of the positions of the matrix that are not wired, `(3, 0)`
+//! to `(3, 2)` and `(3, 7)` to `(3, 9)`, so each layer can give the combo a
+//! different action.
+//!
+//! Presses of keys part of a combo are held back until the combo is
+//! complete, another key is pressed or released, or the timeout expires.
+//! They then reach the layout in order, so hold-taps such as home row mods
+//! still work, their own timeout starting once the combo is ruled out.
+
+use heapless::{Deque, Vec};
+use keyberon::layout::Event;
+
+/// Maximum number of keys held back at once
+const PENDING: usize = 8;
+/// Maximum number of combos active at once
+const ACTIVE: usize = 4;
+/// Maximum number of events waiting for the layout
+const OUTPUT: usize = 16;
+
+/// Keys pressed together acting as another key
+pub struct Combo {
+    /// Positions of the keys to press, as `(row, column)`
+    pub keys: &'static [(u8, u8)],
+    /// Position pressed instead
+    pub output: (u8, u8),
+    /// Layers where the combo is enabled, all of them if empty
+    pub layers: &'static [usize],
+}
+
+impl Combo {
+    /// Whether the combo is enabled on `layer`
+    fn is_enabled(&self, layer: usize) -> bool {
+        self.layers.is_empty() || self.layers.contains(&layer)
+    }
+}
+
+/// A combo that was triggered, until all its keys are released
+struct Active {
+    /// The combo
+    combo: &'static Combo,
+    /// Keys of the combo still pressed
+    pressed: Vec<(u8, u8), PENDING>,
+}
+
+/// Detection of the combos in the key events
+pub struct Combos {
+    /// Combos of the keymap
+    combos: &'static [Combo],
+    /// Milliseconds to press all the keys of a combo
+    timeout: u16,
+    /// Presses held back since they may be part of a combo
+    pending: Vec<(u8, u8), PENDING>,
+    /// Layer when the first pending key was pressed
+    layer: usize,
+    /// Milliseconds since the first pending key was pressed
+    elapsed: u16,
+    /// Combos triggered whose keys are still pressed
+    active: Vec<Active, ACTIVE>,
+    /// Events for the layout
+    output: Deque<Event, OUTPUT>,
+}
+
+impl Combos {
+    /// Create the detection of `combos`, whose keys must be pressed within
+    /// `timeout` milliseconds
+    pub fn new(combos: &'static [Combo], timeout: u16) -> Self {
+        Self {
+            combos,
+            timeout,
+            pending: Vec::new(),
+            layer: 0,
+            elapsed: 0,
+            active: Vec::new(),
+            output: Deque::new(),
+        }
+    }
+
+    /// Combos enabled on the layer of the pending keys
+    fn enabled(&self) -> impl Iterator<Item = &'static Combo> + '_ {
+        self.combos.iter().filter(|c| c.is_enabled(self.layer))
+    }
+
+    /// Whether the pending keys are all keys of `combo`
+    fn is_started(&self, combo: &Combo) -> bool {
+        self.pending.iter().all(|key| combo.keys.contains(key))
+    }
+
+    /// Whether the pending keys are exactly the keys of `combo`
+    fn is_complete(&self, combo: &Combo) -> bool {
+        combo.keys.len() == self.pending.len() && self.is_started(combo)
+    }
+
+    /// Send the pending presses to the layout
+    fn flush(&mut self) {
+        for &(i, j) in &self.pending {
+            self.output.push_back(Event::Press(i, j)).ok();
+        }
+        self.pending.clear();
+    }
+
+    /// Press the output of `combo` instead of its pending keys, or send
+    /// them as they are if too many combos are already active
+    fn trigger(&mut self, combo: &'static Combo) {
+        let active = Active {
+            combo,
+            pressed: self.pending.clone(),
+        };
+        if self.active.push(active).is_err() {
+            self.flush();
+            return;
+        }
+        let (i, j) = combo.output;
+        self.output.push_back(Event::Press(i, j)).ok();
+        self.pending.clear();
+    }
+
+    /// Trigger the combo completed by the pending keys, if no longer combo
+    /// can still be completed, or if `force`d
+    fn try_trigger(&mut self, force: bool) {
+        let complete = self.enabled().find(|c| self.is_complete(c));
+        let longer = self
+            .enabled()
+            .any(|c| c.keys.len() > self.pending.len() && self.is_started(c));
+        if let Some(combo) = complete.filter(|_| force || !longer) {
+            self.trigger(combo);
+        }
+    }
+
+    /// Release `key` from the active combo it is part of, returning whether
+    /// there was one
+    fn release(&mut self, key: (u8, u8)) -> bool {
+        let Some(index) = self.active.iter().position(|a| a.pressed.contains(&key)) else {
+            return false;
+        };
+        let active = &mut self.active[index];
+        // The output is released with the first key of the combo
+        if active.pressed.len() == active.combo.keys.len() {
+            let (i, j) = active.combo.output;
+            self.output.push_back(Event::Release(i, j)).ok();
+        }
+        active.pressed.retain(|k| *k != key);
+        if active.pressed.is_empty() {
+            self.active.swap_remove(index);
+        }
+        true
+    }
+
+    /// Handle a key event, `layer` being the current layer
+    pub fn event(&mut self, event: Event, layer: usize) {
+        let key = event.coord();
+        if self.active.iter().any(|a| a.pressed.contains(&key)) {
+            if event.is_release() {
+                self.release(key);
+            }
+            return;
+        }
+        if event.is_press() {
+            if self.pending.is_empty() {
+                self.layer = layer;
+                self.elapsed = 0;
+            }
+            if self.pending.push(key).is_ok() {
+                if self.enabled().any(|c| self.is_started(c)) {
+                    self.try_trigger(false);
+                    return;
+                }
+                // The key cannot be part of a combo with the pending keys
+                self.pending.pop();
+            }
+            self.try_trigger(true);
+            self.flush();
+            if self
+                .combos
+                .iter()
+                .any(|c| c.is_enabled(layer) && c.keys.contains(&key))
+            {
+                self.layer = layer;
+                self.elapsed = 0;
+                self.pending.push(key).ok();
+                return;
+            }
+        } else {
+            self.try_trigger(true);
+            self.flush();
+            // The release may have just triggered a combo with the key
+            if self.release(key) {
+                return;
+            }
+        }
+        self.output.push_back(event).ok();
+    }
+
+    /// Advance the timeout of the pending keys by one millisecond
+    pub fn tick(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        self.elapsed += 1;
+        if self.elapsed >= self.timeout {
+            self.try_trigger(true);
+            self.flush();
+        }
+    }
+
+    /// Next event for the layout
+    pub fn pop(&mut self) -> Option<Event> {
+        self.output.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Combos of the tests: `(1, 1)` and `(1, 2)` for `(3, 0)`, the same and
+    /// `(1, 3)` for `(3, 1)`, and `(1, 4)` and `(1, 5)`, one on each half,
+    /// for `(3, 9)` on the layer 0 only
+    static COMBOS: [Combo; 3] = [
+        Combo {
+            keys: &[(1, 1), (1, 2)],
+            output: (3, 0),
+            layers: &[],
+        },
+        Combo {
+            keys: &[(1, 1), (1, 2), (1, 3)],
+            output: (3, 1),
+            layers: &[],
+        },
+        Combo {
+            keys: &[(1, 4), (1, 5)],
+            output: (3, 9),
+            layers: &[0],
+        },
+    ];
+
+    /// Events sent to the layout so far
+    fn output(combos: &mut Combos) -> Vec<Event, OUTPUT> {
+        core::iter::from_fn(|| combos.pop()).collect()
+    }
+
+    /// Tick `combos` for `ms` milliseconds
+    fn wait(combos: &mut Combos, ms: u16) {
+        for _ in 0..ms {
+            combos.tick();
+        }
+    }
+
+    #[test]
+    fn across_halves() {
+        let mut combos = Combos::new(&COMBOS, 10);
+        combos.event(Event::Press(1, 5), 0);
+        combos.event(Event::Press(1, 4), 0);
+        assert_eq!(output(&mut combos), [Event::Press(3, 9)]);
+        combos.event(Event::Release(1, 4), 0);
+        assert_eq!(output(&mut combos), [Event::Release(3, 9)]);
+        combos.event(Event::Release(1, 5), 0);
+        assert_eq!(output(&mut combos), []);
+        assert!(combos.active.is_empty());
+    }
+
+    #[test]
+    fn disabled_layer() {
+        let mut combos = Combos::new(&COMBOS, 10);
+        combos.event(Event::Press(1, 4), 1);
+        combos.event(Event::Press(1, 5), 1);
+        assert_eq!(
+            output(&mut combos),
+            [Event::Press(1, 4), Event::Press(1, 5)]
+        );
+    }
+
+    #[test]
+    fn longer_combo() {
+        let mut combos = Combos::new(&COMBOS, 10);
+        combos.event(Event::Press(1, 1), 0);
+        combos.event(Event::Press(1, 2), 0);
+        // The longer combo can still be completed
+        assert_eq!(output(&mut combos), []);
+        combos.event(Event::Press(1, 3), 0);
+        assert_eq!(output(&mut combos), [Event::Press(3, 1)]);
+    }
+
+    #[test]
+    fn shorter_combo_on_timeout() {
+        let mut combos = Combos::new(&COMBOS, 10);
+        combos.event(Event::Press(1, 1), 0);
+        combos.event(Event::Press(1, 2), 0);
+        wait(&mut combos, 9);
+        assert_eq!(output(&mut combos), []);
+        wait(&mut combos, 1);
+        assert_eq!(output(&mut combos), [Event::Press(3, 0)]);
+    }
+
+    #[test]
+    fn shorter_combo_on_another_key() {
+        let mut combos = Combos::new(&COMBOS, 10);
+        combos.event(Event::Press(1, 1), 0);
+        combos.event(Event::Press(1, 2), 0);
+        combos.event(Event::Press(0, 0), 0);
+        assert_eq!(
+            output(&mut combos),
+            [Event::Press(3, 0), Event::Press(0, 0)]
+        );
+    }
+
+    #[test]
+    fn release_while_pending() {
+        let mut combos = Combos::new(&COMBOS, 10);
+        combos.event(Event::Press(1, 1), 0);
+        combos.event(Event::Press(1, 2), 0);
+        combos.event(Event::Release(1, 1), 0);
+        assert_eq!(
+            output(&mut combos),
+            [Event::Press(3, 0), Event::Release(3, 0)]
+        );
+        combos.event(Event::Release(1, 2), 0);
+        assert_eq!(output(&mut combos), []);
+        assert!(combos.active.is_empty());
+        // The key released is no longer part of the combo
+        combos.event(Event::Press(1, 1), 0);
+        wait(&mut combos, 10);
+        assert_eq!(output(&mut combos), [Event::Press(1, 1)]);
+    }
+
+    #[test]
+    fn release_of_an_incomplete_combo() {
+        let mut combos = Combos::new(&COMBOS, 10);
+        combos.event(Event::Press(1, 1), 0);
+        combos.event(Event::Release(1, 1), 0);
+        assert_eq!(
+            output(&mut combos),
+            [Event::Press(1, 1), Event::Release(1, 1)]
+        );
+    }
+
+    #[test]
+    fn hold_tap_held() {
+        let mut combos = Combos::new(&COMBOS, 10);
+        // A home row mod part of a combo, held alone
+        combos.event(Event::Press(1, 1), 0);
+        wait(&mut combos, 10);
+        assert_eq!(output(&mut combos), [Event::Press(1, 1)]);
+        // Its hold-tap timeout starts now, and sees the next key in order
+        combos.event(Event::Press(0, 0), 0);
+        combos.event(Event::Release(0, 0), 0);
+        combos.event(Event::Release(1, 1), 0);
+        assert_eq!(
+            output(&mut combos),
+            [
+                Event::Press(0, 0),
+                Event::Release(0, 0),
+                Event::Release(1, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn hold_tap_with_another_key() {
+        let mut combos = Combos::new(&COMBOS, 10);
+        combos.event(Event::Press(1, 1), 0);
+        combos.event(Event::Press(0, 0), 0);
+        assert_eq!(
+            output(&mut combos),
+            [Event::Press(1, 1), Event::Press(0, 0)]
+        );
+    }
+}
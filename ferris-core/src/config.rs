@@ -0,0 +1,101 @@
+//! Features of a keymap beyond its layers
+
+use crate::combo::Combo;
+use crate::host::HostLayout;
+use crate::keyoverride::KeyOverride;
+use crate::leader::LeaderSequence;
+use crate::longhold::LongHold;
+use crate::prefix::PrefixLayer;
+use crate::repeat::AltRepeat;
+use crate::tapdance::TapDance;
+use crate::trilayer::TriLayer;
+use crate::unicode::UnicodeMode;
+use keyberon::key_code::KeyCode;
+
+/// Features of a keymap beyond its layers, exported as `CONFIG` by every
+/// keymap
+///
+/// Keymaps only set the fields they need, the others coming from
+/// [`Config::DEFAULT`].
+pub struct Config {
+    /// Layers sending a prefix before each key
+    pub prefix_layers: &'static [PrefixLayer],
+    /// Keys pressed together acting as another key
+    pub combos: &'static [Combo],
+    /// Milliseconds to press all the keys of a combo
+    pub combo_timeout: u16,
+    /// Keys doing something different depending on how many times they are
+    /// tapped
+    pub tap_dances: &'static [TapDance],
+    /// Keys doing something else when held long enough
+    pub long_holds: &'static [LongHold],
+    /// Sequences that can be typed after the leader key
+    pub leader: &'static [LeaderSequence],
+    /// Milliseconds to type each key after the leader key
+    pub leader_timeout: u16,
+    /// Milliseconds without any key pressed before Caps Word turns off
+    pub caps_word_timeout: u16,
+    /// Keys, besides digits, keeping Num Word on
+    pub num_word_keys: &'static [KeyCode],
+    /// Keys releasing the layer locked by the layer lock key, besides the
+    /// layer lock key itself
+    pub layer_lock_exits: &'static [KeyCode],
+    /// Input method of the host for Unicode characters, until changed by a
+    /// key
+    pub unicode_mode: UnicodeMode,
+    /// Layout of the host, to type characters
+    pub host_layout: HostLayout,
+    /// Whether auto-shift is on when the keyboard starts
+    pub auto_shift: bool,
+    /// Milliseconds to hold a key to send it shifted with auto-shift
+    pub auto_shift_timeout: u16,
+    /// Keys sending something else while modifiers are held
+    pub key_overrides: &'static [KeyOverride],
+    /// Layers activated when some layers are held together
+    pub tri_layers: &'static [TriLayer],
+    /// Counterparts of the keys for the alternate repeat key, on top of
+    /// [`DEFAULT_ALT_REPEATS`](crate::repeat::DEFAULT_ALT_REPEATS)
+    pub alt_repeats: &'static [AltRepeat],
+    /// Column typed instead of each column of the thumb row with swap-hands
+    pub swap_hands_thumbs: [u8; 10],
+    /// Whether the dynamic macros are saved to flash once recorded, and
+    /// restored when the keyboard starts
+    pub save_macros: bool,
+}
+
+impl Config {
+    /// Configuration with every feature disabled
+    pub const DEFAULT: Self = Self {
+        prefix_layers: &[],
+        combos: &[],
+        combo_timeout: 50,
+        tap_dances: &[],
+        long_holds: &[],
+        leader: &[],
+        leader_timeout: 1000,
+        caps_word_timeout: 5000,
+        num_word_keys: &[
+            KeyCode::Dot,
+            KeyCode::Comma,
+            KeyCode::Minus,
+            KeyCode::Equal,
+            KeyCode::Slash,
+            KeyCode::KpDot,
+            KeyCode::KpMinus,
+            KeyCode::KpPlus,
+            KeyCode::KpAsterisk,
+            KeyCode::KpSlash,
+            KeyCode::KpEqual,
+        ],
+        layer_lock_exits: &[KeyCode::Escape],
+        unicode_mode: UnicodeMode::Linux,
+        host_layout: HostLayout::Us,
+        auto_shift: false,
+        auto_shift_timeout: 175,
+        key_overrides: &[],
+        tri_layers: &[],
+        alt_repeats: &[],
+        swap_hands_thumbs: [9, 8, 7, 6, 5, 4, 3, 2, 1, 0],
+        save_macros: false,
+    };
+}
@@ -0,0 +1,390 @@
+//! Dynamic macros: keystrokes recorded at runtime and played back
+//!
+//! A record key starts recording into one of the slots, and any record or
+//! play key stops it. The keycodes sent while recording are stored, whatever
+//! the layer or the action that sent them, with their modifiers, up to
+//! `LEN` presses and releases per slot. A play key sends them back in order,
+//! one press or release per tick, and releases what is left pressed at the
+//! end.
+//!
+//! The slots can be saved as bytes, for the firmware to keep them in flash.
+//! Only the keycodes of `SAVED` fit in the saved bytes: the others, such as
+//! F13 to F24 or the international keys, are played as long as the keyboard
+//! runs but are missing from the macros restored when it starts again.
+
+use crate::sequence::SequenceEvent::{self, Press, Release};
+use heapless::Vec;
+use keyberon::key_code::KeyCode::{self, *};
+
+/// Number of slots
+pub const SLOTS: usize = 2;
+/// Maximum number of presses and releases in a slot
+const LEN: usize = 64;
+/// Maximum number of keys tracked at once
+const KEYS: usize = 16;
+/// Marker of saved macros
+const MAGIC: [u8; 2] = *b"DM";
+/// Number of bytes of saved macros: the marker, then for each slot its length
+/// and two bytes per event
+pub const STORAGE: usize = MAGIC.len() + SLOTS * (1 + 2 * LEN);
+
+/// Keycodes kept when macros are saved, the others being dropped
+const SAVED: &[KeyCode] = &[
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Kb1,
+    Kb2,
+    Kb3,
+    Kb4,
+    Kb5,
+    Kb6,
+    Kb7,
+    Kb8,
+    Kb9,
+    Kb0,
+    Enter,
+    Escape,
+    BSpace,
+    Tab,
+    Space,
+    Minus,
+    Equal,
+    LBracket,
+    RBracket,
+    Bslash,
+    NonUsHash,
+    SColon,
+    Quote,
+    Grave,
+    Comma,
+    Dot,
+    Slash,
+    CapsLock,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    PScreen,
+    ScrollLock,
+    Pause,
+    Insert,
+    Home,
+    PgUp,
+    Delete,
+    End,
+    PgDown,
+    Right,
+    Left,
+    Down,
+    Up,
+    NumLock,
+    KpSlash,
+    KpAsterisk,
+    KpMinus,
+    KpPlus,
+    KpEnter,
+    Kp1,
+    Kp2,
+    Kp3,
+    Kp4,
+    Kp5,
+    Kp6,
+    Kp7,
+    Kp8,
+    Kp9,
+    Kp0,
+    KpDot,
+    KpEqual,
+    NonUsBslash,
+    Application,
+    Mute,
+    VolUp,
+    VolDown,
+    LCtrl,
+    LShift,
+    LAlt,
+    LGui,
+    RCtrl,
+    RShift,
+    RAlt,
+    RGui,
+    MediaPlayPause,
+    MediaPreviousSong,
+    MediaNextSong,
+    MediaVolUp,
+    MediaVolDown,
+    MediaMute,
+];
+
+/// Keycode saved as `value`, if kept when saving
+fn keycode(value: u8) -> Option<KeyCode> {
+    SAVED.iter().copied().find(|&kc| kc as u8 == value)
+}
+
+/// State of the dynamic macros
+pub struct DynamicMacros {
+    /// Recorded presses and releases, per slot
+    slots: [Vec<SequenceEvent, LEN>; SLOTS],
+    /// Slot being recorded, if any
+    recording: Option<usize>,
+    /// Slot being played, with the position of its next event, if any
+    playing: Option<(usize, usize)>,
+    /// Keys pressed by the playback
+    sent: Vec<KeyCode, KEYS>,
+    /// Keys sent on the last tick
+    pressed: Vec<KeyCode, KEYS>,
+    /// Keys pressed since the recording started
+    recorded: Vec<KeyCode, KEYS>,
+}
+
+impl DynamicMacros {
+    /// Create a new state, with empty slots
+    pub fn new() -> Self {
+        Self {
+            slots: Default::default(),
+            recording: None,
+            playing: None,
+            sent: Vec::new(),
+            pressed: Vec::new(),
+            recorded: Vec::new(),
+        }
+    }
+
+    /// Stop recording
+    ///
+    /// Returns whether a slot was being recorded.
+    fn stop(&mut self) -> bool {
+        self.recorded.clear();
+        self.recording.take().is_some()
+    }
+
+    /// Handle the press of the record key of `slot`: start recording it, or
+    /// stop recording if a slot is being recorded
+    ///
+    /// Returns whether a recording ended.
+    pub fn record(&mut self, slot: usize) -> bool {
+        if self.stop() {
+            return true;
+        }
+        if let Some(events) = self.slots.get_mut(slot) {
+            events.clear();
+            self.playing = None;
+            self.sent.clear();
+            self.recording = Some(slot);
+        }
+        false
+    }
+
+    /// Handle the press of the play key of `slot`: play it, or stop
+    /// recording if a slot is being recorded
+    ///
+    /// Returns whether a recording ended.
+    pub fn play(&mut self, slot: usize) -> bool {
+        if self.stop() {
+            return true;
+        }
+        if slot < SLOTS {
+            self.sent.clear();
+            self.playing = Some((slot, 0));
+        }
+        false
+    }
+
+    /// Record the keys pressed and released among `keycodes`, the keys
+    /// currently sent with their modifiers, then play the next event
+    ///
+    /// Returns whether a recording ended, the slot being full.
+    pub fn tick(&mut self, keycodes: impl Iterator<Item = KeyCode>) -> bool {
+        let keycodes: Vec<KeyCode, KEYS> = keycodes.collect();
+        let mut ended = false;
+        if let Some(slot) = self.recording {
+            let released = self
+                .pressed
+                .iter()
+                .filter(|kc| !keycodes.contains(kc) && self.recorded.contains(kc))
+                .map(|&kc| Release(kc));
+            let pressed = keycodes
+                .iter()
+                .filter(|kc| !self.pressed.contains(kc))
+                .map(|&kc| Press(kc));
+            let events: Vec<SequenceEvent, { 2 * KEYS }> = released.chain(pressed).collect();
+            for event in events {
+                if self.slots[slot].push(event).is_err() {
+                    ended = self.stop();
+                    break;
+                }
+                if let Press(kc) = event {
+                    self.recorded.push(kc).ok();
+                }
+            }
+        }
+        self.pressed = keycodes;
+        self.play_next();
+        ended
+    }
+
+    /// Play the next event of the slot being played, if any
+    fn play_next(&mut self) {
+        let Some((slot, position)) = self.playing else {
+            return;
+        };
+        match self.slots[slot].get(position) {
+            Some(&Press(kc)) => {
+                if !self.sent.contains(&kc) {
+                    self.sent.push(kc).ok();
+                }
+            }
+            Some(&Release(kc)) => self.sent.retain(|&k| k != kc),
+            Some(_) => {}
+            None => {
+                self.sent.clear();
+                self.playing = None;
+                return;
+            }
+        }
+        self.playing = Some((slot, position + 1));
+    }
+
+    /// Keys pressed by the playback
+    pub fn keycodes(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.sent.iter().copied()
+    }
+
+    /// The slots, as bytes to save
+    ///
+    /// The presses and releases of keycodes missing from `SAVED` are dropped.
+    pub fn save(&self) -> [u8; STORAGE] {
+        let mut bytes = [0; STORAGE];
+        bytes[..MAGIC.len()].copy_from_slice(&MAGIC);
+        let mut chunks = bytes[MAGIC.len()..].chunks_mut(1 + 2 * LEN);
+        for (events, chunk) in self.slots.iter().zip(&mut chunks) {
+            let saved = events.iter().filter_map(|&event| match event {
+                Press(kc) if SAVED.contains(&kc) => Some([0, kc as u8]),
+                Release(kc) if SAVED.contains(&kc) => Some([1, kc as u8]),
+                _ => None,
+            });
+            let mut len = 0;
+            for (event, pair) in saved.zip(chunk[1..].chunks_mut(2)) {
+                pair.copy_from_slice(&event);
+                len += 1;
+            }
+            chunk[0] = len;
+        }
+        bytes
+    }
+
+    /// Restore the slots from `bytes`, saved by `save`
+    ///
+    /// Nothing is restored if `bytes` does not hold saved macros.
+    pub fn load(&mut self, bytes: &[u8]) {
+        if bytes.len() < STORAGE || bytes[..MAGIC.len()] != MAGIC {
+            return;
+        }
+        let chunks = bytes[MAGIC.len()..STORAGE].chunks(1 + 2 * LEN);
+        for (events, chunk) in self.slots.iter_mut().zip(chunks) {
+            let len = usize::from(chunk[0]).min(LEN);
+            *events = chunk[1..1 + 2 * len]
+                .chunks(2)
+                .filter_map(|pair| match (pair[0], keycode(pair[1])) {
+                    (0, Some(kc)) => Some(Press(kc)),
+                    (1, Some(kc)) => Some(Release(kc)),
+                    _ => None,
+                })
+                .collect();
+        }
+    }
+}
+
+impl Default for DynamicMacros {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Record the keys sent on each tick into `slot`
+    fn record(macros: &mut DynamicMacros, slot: usize, ticks: &[&[KeyCode]]) {
+        macros.record(slot);
+        for keycodes in ticks {
+            macros.tick(keycodes.iter().copied());
+        }
+        assert!(macros.record(slot));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut macros = DynamicMacros::new();
+        record(&mut macros, 0, &[&[LShift], &[LShift, A], &[], &[Kb1], &[]]);
+        record(&mut macros, 1, &[&[Up], &[], &[MediaMute], &[]]);
+        let mut loaded = DynamicMacros::new();
+        loaded.load(&macros.save());
+        assert_eq!(loaded.slots, macros.slots);
+        assert_eq!(
+            loaded.slots[0],
+            [
+                Press(LShift),
+                Press(A),
+                Release(LShift),
+                Release(A),
+                Press(Kb1),
+                Release(Kb1)
+            ]
+        );
+    }
+
+    #[test]
+    fn unsaved_keycodes() {
+        let mut macros = DynamicMacros::new();
+        record(&mut macros, 0, &[&[F13], &[F13, A], &[]]);
+        assert_eq!(macros.slots[0].len(), 4);
+        let mut loaded = DynamicMacros::new();
+        loaded.load(&macros.save());
+        assert_eq!(loaded.slots[0], [Press(A), Release(A)]);
+    }
+
+    #[test]
+    fn nothing_saved() {
+        let mut macros = DynamicMacros::new();
+        record(&mut macros, 0, &[&[A], &[]]);
+        // An erased page
+        macros.load(&[0xff; STORAGE]);
+        assert_eq!(macros.slots[0], [Press(A), Release(A)]);
+        macros.load(&[]);
+        assert_eq!(macros.slots[0], [Press(A), Release(A)]);
+    }
+}
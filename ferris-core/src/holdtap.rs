@@ -0,0 +1,532 @@
+//! Hold-tap decisions friendly to home row mods
+//!
+//! keyberon decides between the tap and the hold of a hold-tap key with its
+//! `HoldTapConfig`: `Default` waits for the timeout, `HoldOnOtherKeyPress`
+//! holds as soon as another key is pressed, and `PermissiveHold` holds when
+//! another key is pressed and released while the hold-tap is held.
+//!
+//! The functions of this module are `HoldTapConfig::Custom` decisions adding
+//! "require prior idle" to these: a hold-tap pressed less than `IDLE`
+//! milliseconds after the previous key press is part of a typing streak, and
+//! is tapped right away. Pressing a hold-tap while another one is held does
+//! not count as typing, so modifiers can still be combined.
+//!
+//! `bilateral` is a decision for home row mods on both halves: the hold-tap
+//! is only held when the next key is on the other half, or is a thumb key,
+//! and is tapped when it is on the same half, so same hand rolls are typed.
+//!
+//! ```ignore
+//! ht!(k(LShift), k(A), 250, HoldTapConfig::Custom(permissive_hold::<150>))
+//! ht!(l(5), k(S), 200, HoldTapConfig::Custom(bilateral::<0>))
+//! ```
+//!
+//! As keyberon only gives the events that came after the hold-tap to the
+//! decision, the last press of each key is remembered in `History`, fed by
+//! the keyboard with every event sent to the layout. The hold-tap deciding is
+//! the last one pressed before the keys pressed in these events.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+use heapless::Vec;
+use keyberon::layout::{Event, StackedIter, WaitingAction};
+
+/// Number of rows of the matrix
+const ROWS: usize = 4;
+/// Number of columns of the matrix
+const COLS: usize = 10;
+/// Maximum number of events considered by a decision
+const STACKED: usize = 16;
+/// Age of the presses too old to matter
+const NEVER: u16 = u16::MAX;
+/// First column of the right half
+const RIGHT_HALF: u8 = 5;
+/// Row of the thumb keys
+const THUMB_ROW: u8 = 3;
+
+/// The last press of a key sent to the layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Press {
+    /// Position of the key
+    key: (u8, u8),
+    /// Milliseconds since the press
+    age: u16,
+    /// Whether the key is still held
+    held: bool,
+    /// Whether the action of the key is a hold-tap
+    hold_tap: bool,
+}
+
+impl Press {
+    /// Pack the press in 32 bits: the age, then the flags
+    fn pack(self) -> u32 {
+        u32::from(self.age) << 16 | u32::from(self.hold_tap) << 1 | u32::from(self.held)
+    }
+
+    /// Unpack the press of the key at `key` packed by `pack`
+    fn unpack(key: (u8, u8), packed: u32) -> Self {
+        Self {
+            key,
+            age: (packed >> 16) as u16,
+            held: packed & 1 != 0,
+            hold_tap: packed & 1 << 1 != 0,
+        }
+    }
+}
+
+/// Last press of each key, by position, packed by `Press::pack`
+///
+/// keyberon calls the decisions as plain functions, so the presses are kept
+/// in a static, fed by the keyboard through the associated functions.
+pub struct History([[AtomicU32; COLS]; ROWS]);
+
+/// Recent key presses of the keyboard
+static PRESSES: History = History::new();
+
+/// Lock of the history, held by each test using it
+#[cfg(test)]
+static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+impl History {
+    /// An empty history
+    const fn new() -> Self {
+        Self([const { [const { AtomicU32::new(u32::MAX) }; COLS] }; ROWS])
+    }
+
+    /// Lock the history for the current test, as tests run in parallel
+    #[cfg(test)]
+    pub(crate) fn lock() -> std::sync::MutexGuard<'static, ()> {
+        LOCK.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Where the press of the key at `key` is stored, if on the matrix
+    fn slot(key: (u8, u8)) -> Option<&'static AtomicU32> {
+        PRESSES.0.get(usize::from(key.0))?.get(usize::from(key.1))
+    }
+
+    /// The last press of the key at `key`, if not too old
+    fn get(key: (u8, u8)) -> Option<Press> {
+        let press = Press::unpack(key, Self::slot(key)?.load(Ordering::Relaxed));
+        (press.age != NEVER).then_some(press)
+    }
+
+    /// Store `press` as the last one of its key
+    fn set(press: Press) {
+        if let Some(slot) = Self::slot(press.key) {
+            slot.store(press.pack(), Ordering::Relaxed);
+        }
+    }
+
+    /// The last presses of all the keys, if not too old
+    fn presses() -> impl Iterator<Item = Press> {
+        (0..ROWS as u8)
+            .flat_map(|i| (0..COLS as u8).map(move |j| (i, j)))
+            .filter_map(Self::get)
+    }
+
+    /// Remember the press of the key at `key`, `hold_tap` telling whether
+    /// its action is a hold-tap
+    pub fn press(key: (u8, u8), hold_tap: bool) {
+        Self::set(Press {
+            key,
+            age: 0,
+            held: true,
+            hold_tap,
+        });
+    }
+
+    /// Remember the release of the key at `key`
+    pub fn release(key: (u8, u8)) {
+        if let Some(press) = Self::get(key) {
+            Self::set(Press {
+                held: false,
+                ..press
+            });
+        }
+    }
+
+    /// Age the remembered presses by one millisecond
+    pub fn tick() {
+        for press in Self::presses() {
+            Self::set(Press {
+                age: press.age + 1,
+                ..press
+            });
+        }
+    }
+
+    /// Forget every press, such as when a new keyboard starts
+    pub fn reset() {
+        for press in PRESSES.0.iter().flatten() {
+            press.store(u32::MAX, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Events stacked after the hold-tap
+fn events(stacked: StackedIter) -> Vec<Event, STACKED> {
+    stacked.map(|s| s.event).take(STACKED).collect()
+}
+
+/// Whether the key at `key` is pressed in `events`
+fn is_stacked(events: &[Event], key: (u8, u8)) -> bool {
+    events.iter().any(|e| e.is_press() && e.coord() == key)
+}
+
+/// The press of the hold-tap deciding on `events`: the last hold-tap pressed
+/// before the keys pressed in `events`, still held or released in `events`
+fn hold_tap(events: &[Event]) -> Option<Press> {
+    let released = |key| events.iter().any(|e| e.is_release() && e.coord() == key);
+    History::presses()
+        .filter(|p| p.hold_tap && !is_stacked(events, p.key))
+        .filter(|p| p.held || released(p.key))
+        .min_by_key(|p| p.age)
+}
+
+/// Whether the hold-tap deciding on `events` was pressed less than `idle`
+/// milliseconds after the previous key press, no other hold-tap being held
+fn is_typing(events: &[Event], idle: u16) -> bool {
+    let Some(hold_tap) = hold_tap(events) else {
+        return false;
+    };
+    let previous = History::presses()
+        .filter(|p| p.key != hold_tap.key && !is_stacked(events, p.key))
+        .filter(|p| p.age >= hold_tap.age)
+        .min_by_key(|p| p.age);
+    let Some(previous) = previous else {
+        return false;
+    };
+    !(previous.hold_tap && previous.held) && previous.age - hold_tap.age < idle
+}
+
+/// Whether a key pressed after the hold-tap is also released in `events`
+fn is_pressed_and_released(events: &[Event]) -> bool {
+    events.iter().enumerate().any(|(i, e)| {
+        e.is_press()
+            && events[i..]
+                .iter()
+                .any(|r| r.is_release() && r.coord() == e.coord())
+    })
+}
+
+/// `HoldTapConfig::Default`, tapping while typing
+pub fn prior_idle<const IDLE: u16>(stacked: StackedIter) -> Option<WaitingAction> {
+    let events = events(stacked);
+    is_typing(&events, IDLE).then_some(WaitingAction::Tap)
+}
+
+/// `HoldTapConfig::PermissiveHold`, tapping while typing
+pub fn permissive_hold<const IDLE: u16>(stacked: StackedIter) -> Option<WaitingAction> {
+    let events = events(stacked);
+    if is_typing(&events, IDLE) {
+        Some(WaitingAction::Tap)
+    } else {
+        is_pressed_and_released(&events).then_some(WaitingAction::Hold)
+    }
+}
+
+/// `HoldTapConfig::HoldOnOtherKeyPress`, tapping while typing
+pub fn hold_on_other_key_press<const IDLE: u16>(stacked: StackedIter) -> Option<WaitingAction> {
+    let events = events(stacked);
+    if is_typing(&events, IDLE) {
+        Some(WaitingAction::Tap)
+    } else {
+        events
+            .iter()
+            .any(|e| e.is_press())
+            .then_some(WaitingAction::Hold)
+    }
+}
+
+/// Whether the keys at `a` and `b` are on the same half, thumb keys being on
+/// neither
+fn same_half(a: (u8, u8), b: (u8, u8)) -> bool {
+    a.0 != THUMB_ROW && b.0 != THUMB_ROW && (a.1 < RIGHT_HALF) == (b.1 < RIGHT_HALF)
+}
+
+/// Bilateral combinations, tapping while typing: held when the next key is
+/// on the other half or is a thumb key, tapped when it is on the same half
+pub fn bilateral<const IDLE: u16>(stacked: StackedIter) -> Option<WaitingAction> {
+    let events = events(stacked);
+    if is_typing(&events, IDLE) {
+        return Some(WaitingAction::Tap);
+    }
+    let hold_tap = hold_tap(&events)?;
+    let next = events.iter().find(|e| e.is_press())?;
+    if same_half(hold_tap.key, next.coord()) {
+        Some(WaitingAction::Tap)
+    } else {
+        Some(WaitingAction::Hold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::{Action, CustomAction};
+    use keyberon::action::{k, HoldTapAction, HoldTapConfig};
+    use keyberon::key_code::KeyCode::{self, *};
+    use keyberon::layout::{Layers, Layout};
+    use std::boxed::Box;
+    use std::sync::MutexGuard;
+
+    /// Milliseconds after the previous press for a hold-tap to be tapped
+    const IDLE: u16 = 150;
+    /// Hold-tap on the left half, Shift or A
+    const HOLD_TAP: (u8, u8) = (1, 0);
+    /// Another hold-tap on the right half, Control or `;`
+    const OTHER_HOLD_TAP: (u8, u8) = (1, 9);
+    /// Key on the left half, S
+    const SAME_HALF: (u8, u8) = (1, 1);
+    /// Key on the right half, L
+    const OTHER_HALF: (u8, u8) = (1, 8);
+    /// Thumb key on the left half, Space
+    const THUMB: (u8, u8) = (3, 4);
+
+    /// A layout whose hold-taps decide with `decision`, with the presses
+    /// sent to the history like the keyboard does, while holding its lock
+    struct Keys(
+        Layout<10, 4, 1, CustomAction>,
+        #[allow(dead_code)] MutexGuard<'static, ()>,
+    );
+
+    impl Keys {
+        /// Create the layout, with an empty history
+        fn new(decision: fn(StackedIter) -> Option<WaitingAction>) -> Self {
+            let hold_tap = |hold, tap| {
+                Action::HoldTap(Box::leak(Box::new(HoldTapAction {
+                    timeout: 200,
+                    tap_hold_interval: 0,
+                    config: HoldTapConfig::Custom(decision),
+                    hold: k(hold),
+                    tap: k(tap),
+                })))
+            };
+            let mut layers: Layers<10, 4, 1, CustomAction> = [[[Action::NoOp; 10]; 4]; 1];
+            layers[0][1][0] = hold_tap(LShift, A);
+            layers[0][1][9] = hold_tap(RCtrl, SColon);
+            layers[0][1][1] = k(S);
+            layers[0][1][8] = k(L);
+            layers[0][3][4] = k(Space);
+            let lock = History::lock();
+            History::reset();
+            Self(Layout::new(Box::leak(Box::new(layers))), lock)
+        }
+
+        /// Press the key at `key`
+        fn press(&mut self, key: (u8, u8)) {
+            History::press(key, key == HOLD_TAP || key == OTHER_HOLD_TAP);
+            self.0.event(Event::Press(key.0, key.1));
+        }
+
+        /// Release the key at `key`
+        fn release(&mut self, key: (u8, u8)) {
+            History::release(key);
+            self.0.event(Event::Release(key.0, key.1));
+        }
+
+        /// Tap the key at `key`
+        fn tap(&mut self, key: (u8, u8)) {
+            self.press(key);
+            self.wait(1);
+            self.release(key);
+        }
+
+        /// Let `ms` milliseconds pass
+        fn wait(&mut self, ms: u16) {
+            for _ in 0..ms {
+                History::tick();
+                self.0.tick();
+            }
+        }
+
+        /// Keys pressed, after up to `ms` milliseconds until there is one
+        fn decided(&mut self, ms: u16) -> heapless::Vec<KeyCode, 8> {
+            for _ in 0..ms {
+                self.wait(1);
+                if self.0.keycodes().next().is_some() {
+                    break;
+                }
+            }
+            self.0.keycodes().collect()
+        }
+    }
+
+    /// Whether the hold-tap deciding with `decision`, pressed after idling
+    /// and followed by the press of `next`, and its release if `release`, is
+    /// held within a few milliseconds, tapped, or still undecided
+    fn decide(
+        decision: fn(StackedIter) -> Option<WaitingAction>,
+        next: (u8, u8),
+        release: bool,
+    ) -> Option<WaitingAction> {
+        let mut keys = Keys::new(decision);
+        keys.wait(1000);
+        keys.press(HOLD_TAP);
+        keys.wait(5);
+        keys.press(next);
+        keys.wait(5);
+        if release {
+            keys.release(next);
+        }
+        let decided = keys.decided(5);
+        if decided.contains(&LShift) {
+            Some(WaitingAction::Hold)
+        } else if decided.contains(&A) {
+            Some(WaitingAction::Tap)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the hold-tap deciding with `decision` is tapped right away
+    /// when pressed during a typing streak
+    fn is_tapped_while_typing(decision: fn(StackedIter) -> Option<WaitingAction>) -> bool {
+        let mut keys = Keys::new(decision);
+        keys.wait(1000);
+        keys.tap(OTHER_HALF);
+        keys.wait(50);
+        keys.press(HOLD_TAP);
+        keys.decided(2).as_slice() == [A]
+    }
+
+    /// Whether the hold-tap deciding with `decision` is not tapped right
+    /// away, but held, when pressed quickly while another hold-tap is held
+    fn is_combined(decision: fn(StackedIter) -> Option<WaitingAction>) -> bool {
+        let mut keys = Keys::new(decision);
+        keys.wait(1000);
+        keys.press(OTHER_HOLD_TAP);
+        keys.wait(50);
+        keys.press(HOLD_TAP);
+        keys.wait(2);
+        let tapped = keys.0.keycodes().any(|kc| kc == A);
+        keys.press(SAME_HALF);
+        keys.release(SAME_HALF);
+        keys.wait(300);
+        !tapped && keys.0.keycodes().any(|kc| kc == LShift)
+    }
+
+    #[test]
+    fn prior_idle_hold() {
+        let decision = prior_idle::<IDLE>;
+        // Other keys do not decide, the hold-tap waits for its timeout
+        assert_eq!(decide(decision, SAME_HALF, true), None);
+        assert_eq!(decide(decision, OTHER_HALF, true), None);
+        assert_eq!(decide(decision, THUMB, false), None);
+        let mut keys = Keys::new(decision);
+        keys.wait(1000);
+        keys.press(HOLD_TAP);
+        assert!(keys.decided(250).contains(&LShift));
+    }
+
+    #[test]
+    fn prior_idle_typing() {
+        assert!(is_tapped_while_typing(prior_idle::<IDLE>));
+        assert!(is_combined(prior_idle::<IDLE>));
+    }
+
+    #[test]
+    fn permissive_hold_decisions() {
+        let decision = permissive_hold::<IDLE>;
+        assert_eq!(decide(decision, SAME_HALF, true), Some(WaitingAction::Hold));
+        assert_eq!(
+            decide(decision, OTHER_HALF, true),
+            Some(WaitingAction::Hold)
+        );
+        assert_eq!(decide(decision, THUMB, true), Some(WaitingAction::Hold));
+        // A key pressed but not released yet does not decide
+        assert_eq!(decide(decision, SAME_HALF, false), None);
+        assert_eq!(decide(decision, THUMB, false), None);
+    }
+
+    #[test]
+    fn permissive_hold_typing() {
+        assert!(is_tapped_while_typing(permissive_hold::<IDLE>));
+        assert!(is_combined(permissive_hold::<IDLE>));
+    }
+
+    #[test]
+    fn hold_on_other_key_press_decisions() {
+        let decision = hold_on_other_key_press::<IDLE>;
+        assert_eq!(
+            decide(decision, SAME_HALF, false),
+            Some(WaitingAction::Hold)
+        );
+        assert_eq!(
+            decide(decision, OTHER_HALF, false),
+            Some(WaitingAction::Hold)
+        );
+        assert_eq!(decide(decision, THUMB, false), Some(WaitingAction::Hold));
+    }
+
+    #[test]
+    fn hold_on_other_key_press_typing() {
+        assert!(is_tapped_while_typing(hold_on_other_key_press::<IDLE>));
+        assert!(is_combined(hold_on_other_key_press::<IDLE>));
+    }
+
+    #[test]
+    fn bilateral_decisions() {
+        let decision = bilateral::<IDLE>;
+        assert_eq!(decide(decision, SAME_HALF, false), Some(WaitingAction::Tap));
+        assert_eq!(
+            decide(decision, OTHER_HALF, false),
+            Some(WaitingAction::Hold)
+        );
+        assert_eq!(decide(decision, THUMB, false), Some(WaitingAction::Hold));
+    }
+
+    #[test]
+    fn bilateral_typing() {
+        assert!(is_tapped_while_typing(bilateral::<IDLE>));
+        // Without prior idle, the key pressed after the hold-tap decides
+        let mut keys = Keys::new(bilateral::<0>);
+        keys.tap(OTHER_HALF);
+        keys.press(HOLD_TAP);
+        keys.wait(5);
+        keys.press(OTHER_HALF);
+        assert!(keys.decided(5).contains(&LShift));
+    }
+
+    #[test]
+    fn combined_then_typing() {
+        // A hold-tap held as a modifier does not hide the one deciding
+        let mut keys = Keys::new(bilateral::<IDLE>);
+        keys.wait(1000);
+        keys.press(OTHER_HOLD_TAP);
+        keys.wait(300);
+        keys.tap(SAME_HALF);
+        keys.wait(50);
+        keys.press(HOLD_TAP);
+        keys.wait(2);
+        assert!(keys.0.keycodes().eq([RCtrl, A]));
+    }
+
+    #[test]
+    fn history() {
+        let _lock = History::lock();
+        History::reset();
+        assert_eq!(History::presses().next(), None);
+        History::press((0, 1), false);
+        History::tick();
+        History::press((1, 2), true);
+        History::release((0, 1));
+        let expected = Press {
+            key: (0, 1),
+            age: 1,
+            held: false,
+            hold_tap: false,
+        };
+        assert_eq!(History::get((0, 1)), Some(expected));
+        let expected = Press {
+            key: (1, 2),
+            age: 0,
+            held: true,
+            hold_tap: true,
+        };
+        assert_eq!(History::get((1, 2)), Some(expected));
+        // Keys outside of the matrix are not remembered
+        History::press((4, 0), false);
+        assert_eq!(History::get((4, 0)), None);
+        History::reset();
+        assert_eq!(History::presses().next(), None);
+    }
+}
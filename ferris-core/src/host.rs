@@ -0,0 +1,635 @@
+//! Keyboard layout of the host, to type characters
+//!
+//! The keycodes sent over USB are positions on a US keyboard, which the host
+//! turns into characters with its own layout. To type a given character, such
+//! as with `string` or `ch`, the firmware looks up the keystroke producing it
+//! on the layout of the host set in the keymap configuration.
+//!
+//! The tables cover the characters typed with a single key, possibly with
+//! Shift or AltGr, as on Linux: dead keys, and the characters needing them,
+//! are not supported.
+
+use heapless::Vec;
+use keyberon::key_code::KeyCode::{self, *};
+
+/// Maximum number of characters held at once
+const HELD: usize = 8;
+
+/// Keyboard layout of the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostLayout {
+    /// US QWERTY
+    Us,
+    /// US Dvorak
+    Dvorak,
+    /// French AZERTY
+    Azerty,
+    /// French Bépo
+    Bepo,
+}
+
+/// Key and modifiers typing a character
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keystroke {
+    /// Key to press
+    pub key: KeyCode,
+    /// Whether Shift is held with the key
+    pub shift: bool,
+    /// Whether AltGr is held with the key
+    pub altgr: bool,
+}
+
+impl Keystroke {
+    /// Keystroke of `key` alone
+    const fn key(key: KeyCode) -> Self {
+        Self {
+            key,
+            shift: false,
+            altgr: false,
+        }
+    }
+
+    /// Keystroke of `key` with Shift
+    const fn shift(key: KeyCode) -> Self {
+        Self {
+            key,
+            shift: true,
+            altgr: false,
+        }
+    }
+
+    /// Keystroke of `key` with AltGr
+    const fn altgr(key: KeyCode) -> Self {
+        Self {
+            key,
+            shift: false,
+            altgr: true,
+        }
+    }
+
+    /// Keys to press, modifiers first
+    pub fn keycodes(self) -> impl Iterator<Item = KeyCode> {
+        [
+            self.shift.then_some(LShift),
+            self.altgr.then_some(RAlt),
+            Some(self.key),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+/// Keystroke of a character on a US layout
+fn us(c: char) -> Option<Keystroke> {
+    let key = match c {
+        'a' => A,
+        'b' => B,
+        'c' => C,
+        'd' => D,
+        'e' => E,
+        'f' => F,
+        'g' => G,
+        'h' => H,
+        'i' => I,
+        'j' => J,
+        'k' => K,
+        'l' => L,
+        'm' => M,
+        'n' => N,
+        'o' => O,
+        'p' => P,
+        'q' => Q,
+        'r' => R,
+        's' => S,
+        't' => T,
+        'u' => U,
+        'v' => V,
+        'w' => W,
+        'x' => X,
+        'y' => Y,
+        'z' => Z,
+        '1' => Kb1,
+        '2' => Kb2,
+        '3' => Kb3,
+        '4' => Kb4,
+        '5' => Kb5,
+        '6' => Kb6,
+        '7' => Kb7,
+        '8' => Kb8,
+        '9' => Kb9,
+        '0' => Kb0,
+        '-' => Minus,
+        '=' => Equal,
+        '[' => LBracket,
+        ']' => RBracket,
+        '\\' => Bslash,
+        ';' => SColon,
+        '\'' => Quote,
+        '`' => Grave,
+        ',' => Comma,
+        '.' => Dot,
+        '/' => Slash,
+        '!' => return Some(Keystroke::shift(Kb1)),
+        '@' => return Some(Keystroke::shift(Kb2)),
+        '#' => return Some(Keystroke::shift(Kb3)),
+        '$' => return Some(Keystroke::shift(Kb4)),
+        '%' => return Some(Keystroke::shift(Kb5)),
+        '^' => return Some(Keystroke::shift(Kb6)),
+        '&' => return Some(Keystroke::shift(Kb7)),
+        '*' => return Some(Keystroke::shift(Kb8)),
+        '(' => return Some(Keystroke::shift(Kb9)),
+        ')' => return Some(Keystroke::shift(Kb0)),
+        '_' => return Some(Keystroke::shift(Minus)),
+        '+' => return Some(Keystroke::shift(Equal)),
+        '{' => return Some(Keystroke::shift(LBracket)),
+        '}' => return Some(Keystroke::shift(RBracket)),
+        '|' => return Some(Keystroke::shift(Bslash)),
+        ':' => return Some(Keystroke::shift(SColon)),
+        '"' => return Some(Keystroke::shift(Quote)),
+        '~' => return Some(Keystroke::shift(Grave)),
+        '<' => return Some(Keystroke::shift(Comma)),
+        '>' => return Some(Keystroke::shift(Dot)),
+        '?' => return Some(Keystroke::shift(Slash)),
+        _ => return None,
+    };
+    Some(Keystroke::key(key))
+}
+
+/// Keystroke of a character on a US Dvorak layout
+fn dvorak(c: char) -> Option<Keystroke> {
+    let key = match c {
+        'a' => A,
+        'b' => N,
+        'c' => I,
+        'd' => H,
+        'e' => D,
+        'f' => Y,
+        'g' => U,
+        'h' => J,
+        'i' => G,
+        'j' => C,
+        'k' => V,
+        'l' => P,
+        'm' => M,
+        'n' => L,
+        'o' => S,
+        'p' => R,
+        'q' => X,
+        'r' => O,
+        's' => SColon,
+        't' => K,
+        'u' => F,
+        'v' => Dot,
+        'w' => Comma,
+        'x' => B,
+        'y' => T,
+        'z' => Slash,
+        '[' => Minus,
+        ']' => Equal,
+        '\'' => Q,
+        ',' => W,
+        '.' => E,
+        '/' => LBracket,
+        '=' => RBracket,
+        '-' => Quote,
+        ';' => Z,
+        '{' => return Some(Keystroke::shift(Minus)),
+        '}' => return Some(Keystroke::shift(Equal)),
+        '"' => return Some(Keystroke::shift(Q)),
+        '<' => return Some(Keystroke::shift(W)),
+        '>' => return Some(Keystroke::shift(E)),
+        '?' => return Some(Keystroke::shift(LBracket)),
+        '+' => return Some(Keystroke::shift(RBracket)),
+        '_' => return Some(Keystroke::shift(Quote)),
+        ':' => return Some(Keystroke::shift(Z)),
+        // Digits, `\`, `` ` `` and the shifted digits are as on US
+        _ => return us(c),
+    };
+    Some(Keystroke::key(key))
+}
+
+/// Keystroke of a character on a French AZERTY layout
+fn azerty(c: char) -> Option<Keystroke> {
+    let key = match c {
+        'a' => Q,
+        'b' => B,
+        'c' => C,
+        'd' => D,
+        'e' => E,
+        'f' => F,
+        'g' => G,
+        'h' => H,
+        'i' => I,
+        'j' => J,
+        'k' => K,
+        'l' => L,
+        'm' => SColon,
+        'n' => N,
+        'o' => O,
+        'p' => P,
+        'q' => A,
+        'r' => R,
+        's' => S,
+        't' => T,
+        'u' => U,
+        'v' => V,
+        'w' => Z,
+        'x' => X,
+        'y' => Y,
+        'z' => W,
+        '&' => Kb1,
+        'é' => Kb2,
+        '"' => Kb3,
+        '\'' => Kb4,
+        '(' => Kb5,
+        '-' => Kb6,
+        'è' => Kb7,
+        '_' => Kb8,
+        'ç' => Kb9,
+        'à' => Kb0,
+        ')' => Minus,
+        '=' => Equal,
+        '$' => RBracket,
+        'ù' => Quote,
+        '*' => NonUsHash,
+        '<' => NonUsBslash,
+        ',' => M,
+        ';' => Comma,
+        ':' => Dot,
+        '!' => Slash,
+        '²' => Grave,
+        '1' => return Some(Keystroke::shift(Kb1)),
+        '2' => return Some(Keystroke::shift(Kb2)),
+        '3' => return Some(Keystroke::shift(Kb3)),
+        '4' => return Some(Keystroke::shift(Kb4)),
+        '5' => return Some(Keystroke::shift(Kb5)),
+        '6' => return Some(Keystroke::shift(Kb6)),
+        '7' => return Some(Keystroke::shift(Kb7)),
+        '8' => return Some(Keystroke::shift(Kb8)),
+        '9' => return Some(Keystroke::shift(Kb9)),
+        '0' => return Some(Keystroke::shift(Kb0)),
+        '°' => return Some(Keystroke::shift(Minus)),
+        '+' => return Some(Keystroke::shift(Equal)),
+        '£' => return Some(Keystroke::shift(RBracket)),
+        '%' => return Some(Keystroke::shift(Quote)),
+        'µ' => return Some(Keystroke::shift(NonUsHash)),
+        '>' => return Some(Keystroke::shift(NonUsBslash)),
+        '?' => return Some(Keystroke::shift(M)),
+        '.' => return Some(Keystroke::shift(Comma)),
+        '/' => return Some(Keystroke::shift(Dot)),
+        '§' => return Some(Keystroke::shift(Slash)),
+        '~' => return Some(Keystroke::altgr(Kb2)),
+        '#' => return Some(Keystroke::altgr(Kb3)),
+        '{' => return Some(Keystroke::altgr(Kb4)),
+        '[' => return Some(Keystroke::altgr(Kb5)),
+        '|' => return Some(Keystroke::altgr(Kb6)),
+        '`' => return Some(Keystroke::altgr(Kb7)),
+        '\\' => return Some(Keystroke::altgr(Kb8)),
+        '^' => return Some(Keystroke::altgr(Kb9)),
+        '@' => return Some(Keystroke::altgr(Kb0)),
+        ']' => return Some(Keystroke::altgr(Minus)),
+        '}' => return Some(Keystroke::altgr(Equal)),
+        '€' => return Some(Keystroke::altgr(E)),
+        '¤' => return Some(Keystroke::altgr(RBracket)),
+        _ => return None,
+    };
+    Some(Keystroke::key(key))
+}
+
+/// Keystroke of a character on a French Bépo layout
+fn bepo(c: char) -> Option<Keystroke> {
+    let key = match c {
+        'a' => A,
+        'b' => Q,
+        'c' => H,
+        'd' => I,
+        'e' => F,
+        'f' => Slash,
+        'g' => Comma,
+        'h' => Dot,
+        'i' => D,
+        'j' => P,
+        'k' => B,
+        'l' => O,
+        'm' => Quote,
+        'n' => SColon,
+        'o' => R,
+        'p' => E,
+        'q' => M,
+        'r' => L,
+        's' => K,
+        't' => J,
+        'u' => S,
+        'v' => U,
+        'w' => RBracket,
+        'x' => C,
+        'y' => X,
+        'z' => LBracket,
+        'é' => W,
+        'è' => T,
+        'ç' => NonUsHash,
+        'ê' => NonUsBslash,
+        'à' => Z,
+        '$' => Grave,
+        '"' => Kb1,
+        '«' => Kb2,
+        '»' => Kb3,
+        '(' => Kb4,
+        ')' => Kb5,
+        '@' => Kb6,
+        '+' => Kb7,
+        '-' => Kb8,
+        '/' => Kb9,
+        '*' => Kb0,
+        '=' => Minus,
+        '%' => Equal,
+        ',' => G,
+        '.' => V,
+        '\'' => N,
+        '#' => return Some(Keystroke::shift(Grave)),
+        '1' => return Some(Keystroke::shift(Kb1)),
+        '2' => return Some(Keystroke::shift(Kb2)),
+        '3' => return Some(Keystroke::shift(Kb3)),
+        '4' => return Some(Keystroke::shift(Kb4)),
+        '5' => return Some(Keystroke::shift(Kb5)),
+        '6' => return Some(Keystroke::shift(Kb6)),
+        '7' => return Some(Keystroke::shift(Kb7)),
+        '8' => return Some(Keystroke::shift(Kb8)),
+        '9' => return Some(Keystroke::shift(Kb9)),
+        '0' => return Some(Keystroke::shift(Kb0)),
+        '°' => return Some(Keystroke::shift(Minus)),
+        '`' => return Some(Keystroke::shift(Equal)),
+        '!' => return Some(Keystroke::shift(Y)),
+        ';' => return Some(Keystroke::shift(G)),
+        ':' => return Some(Keystroke::shift(V)),
+        '?' => return Some(Keystroke::shift(N)),
+        'É' => return Some(Keystroke::shift(W)),
+        'È' => return Some(Keystroke::shift(T)),
+        'Ç' => return Some(Keystroke::shift(NonUsHash)),
+        'Ê' => return Some(Keystroke::shift(NonUsBslash)),
+        'À' => return Some(Keystroke::shift(Z)),
+        '<' => return Some(Keystroke::altgr(Kb2)),
+        '>' => return Some(Keystroke::altgr(Kb3)),
+        '[' => return Some(Keystroke::altgr(Kb4)),
+        ']' => return Some(Keystroke::altgr(Kb5)),
+        '^' => return Some(Keystroke::altgr(Kb6)),
+        '|' => return Some(Keystroke::altgr(Q)),
+        '&' => return Some(Keystroke::altgr(E)),
+        'œ' => return Some(Keystroke::altgr(R)),
+        'æ' => return Some(Keystroke::altgr(A)),
+        'ù' => return Some(Keystroke::altgr(S)),
+        '€' => return Some(Keystroke::altgr(F)),
+        '\\' => return Some(Keystroke::altgr(Z)),
+        '{' => return Some(Keystroke::altgr(X)),
+        '}' => return Some(Keystroke::altgr(C)),
+        '~' => return Some(Keystroke::altgr(B)),
+        '_' => return Some(Keystroke::altgr(Space)),
+        _ => return None,
+    };
+    Some(Keystroke::key(key))
+}
+
+impl HostLayout {
+    /// Keystroke typing `c` on this layout
+    ///
+    /// Uppercase ASCII letters are typed as their lowercase letter with Shift.
+    pub fn keystroke(self, c: char) -> Option<Keystroke> {
+        let table = match self {
+            Self::Us => us,
+            Self::Dvorak => dvorak,
+            Self::Azerty => azerty,
+            Self::Bepo => bepo,
+        };
+        match c {
+            '\n' => Some(Keystroke::key(Enter)),
+            '\t' => Some(Keystroke::key(Tab)),
+            ' ' => Some(Keystroke::key(Space)),
+            c if c.is_ascii_uppercase() => {
+                let keystroke = table(c.to_ascii_lowercase())?;
+                Some(Keystroke {
+                    shift: true,
+                    ..keystroke
+                })
+            }
+            c => table(c),
+        }
+    }
+}
+
+/// Characters held by `ch` keys
+pub struct Chars {
+    /// Layout of the host
+    layout: HostLayout,
+    /// Characters held, with the keystroke typing them
+    held: Vec<(char, Keystroke), HELD>,
+}
+
+impl Chars {
+    /// Create a new state, without any character held, typing them on
+    /// `layout`
+    pub fn new(layout: HostLayout) -> Self {
+        Self {
+            layout,
+            held: Vec::new(),
+        }
+    }
+
+    /// Handle the press of a key typing `c`
+    ///
+    /// Characters missing from the layout of the host are ignored.
+    pub fn press(&mut self, c: char) {
+        if let Some(keystroke) = self.layout.keystroke(c) {
+            self.held.push((c, keystroke)).ok();
+        }
+    }
+
+    /// Handle the release of a key typing `c`
+    pub fn release(&mut self, c: char) {
+        if let Some(index) = self.held.iter().position(|&(h, _)| h == c) {
+            self.held.remove(index);
+        }
+    }
+
+    /// Keys pressed to type the characters held
+    pub fn keycodes(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.held
+            .iter()
+            .flat_map(|&(_, keystroke)| keystroke.keycodes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keys of an ANSI keyboard, row by row
+    const ANSI: [&[KeyCode]; 5] = [
+        &[
+            Grave, Kb1, Kb2, Kb3, Kb4, Kb5, Kb6, Kb7, Kb8, Kb9, Kb0, Minus, Equal,
+        ],
+        &[Q, W, E, R, T, Y, U, I, O, P, LBracket, RBracket, Bslash],
+        &[A, S, D, F, G, H, J, K, L, SColon, Quote],
+        &[Z, X, C, V, B, N, M, Comma, Dot, Slash],
+        &[Space],
+    ];
+
+    /// Keys of an ISO keyboard, row by row
+    const ISO: [&[KeyCode]; 5] = [
+        &[
+            Grave, Kb1, Kb2, Kb3, Kb4, Kb5, Kb6, Kb7, Kb8, Kb9, Kb0, Minus, Equal,
+        ],
+        &[Q, W, E, R, T, Y, U, I, O, P, LBracket, RBracket],
+        &[A, S, D, F, G, H, J, K, L, SColon, Quote, NonUsHash],
+        &[NonUsBslash, Z, X, C, V, B, N, M, Comma, Dot, Slash],
+        &[Space],
+    ];
+
+    /// A layout as the host sees it: the characters of each row of `keys`
+    /// alone, with Shift and with AltGr, `·` standing for dead keys and keys
+    /// typing nothing
+    struct Host {
+        /// Keys of the keyboard, row by row
+        keys: [&'static [KeyCode]; 5],
+        /// Characters of the rows, alone, with Shift and with AltGr
+        levels: [[&'static str; 5]; 3],
+    }
+
+    /// US QWERTY, as typed by the host
+    const US: Host = Host {
+        keys: ANSI,
+        levels: [
+            [
+                "`1234567890-=",
+                "qwertyuiop[]\\",
+                "asdfghjkl;'",
+                "zxcvbnm,./",
+                " ",
+            ],
+            [
+                "~!@#$%^&*()_+",
+                "QWERTYUIOP{}|",
+                "ASDFGHJKL:\"",
+                "ZXCVBNM<>?",
+                " ",
+            ],
+            ["", "", "", "", ""],
+        ],
+    };
+
+    /// US Dvorak, as typed by the host
+    const DVORAK: Host = Host {
+        keys: ANSI,
+        levels: [
+            [
+                "`1234567890[]",
+                "',.pyfgcrl/=\\",
+                "aoeuidhtns-",
+                ";qjkxbmwvz",
+                " ",
+            ],
+            [
+                "~!@#$%^&*(){}",
+                "\"<>PYFGCRL?+|",
+                "AOEUIDHTNS_",
+                ":QJKXBMWVZ",
+                " ",
+            ],
+            ["", "", "", "", ""],
+        ],
+    };
+
+    /// French AZERTY, as typed by the host
+    const AZERTY: Host = Host {
+        keys: ISO,
+        levels: [
+            [
+                "²&é\"'(-è_çà)=",
+                "azertyuiop·$",
+                "qsdfghjklmù*",
+                "<wxcvbn,;:!",
+                " ",
+            ],
+            [
+                "·1234567890°+",
+                "AZERTYUIOP·£",
+                "QSDFGHJKLM%µ",
+                ">WXCVBN?./§",
+                " ",
+            ],
+            ["··~#{[|`\\^@]}", "··€········¤", "", "", ""],
+        ],
+    };
+
+    /// French Bépo, as typed by the host
+    const BEPO: Host = Host {
+        keys: ISO,
+        levels: [
+            [
+                "$\"«»()@+-/*=%",
+                "bépoè·vdljzw",
+                "auie,ctsrnmç",
+                "êàyx.k'qghf",
+                " ",
+            ],
+            [
+                "#1234567890°`",
+                "BÉPOÈ!VDLJZW",
+                "AUIE;CTSRNMÇ",
+                "ÊÀYX:K?QGHF",
+                " ",
+            ],
+            ["··<>[]^", "|·&œ", "æù·€", "/\\{}·~", "_"],
+        ],
+    };
+
+    /// Character typed by the host for `keystroke`
+    fn typed(host: &Host, keystroke: Keystroke) -> Option<char> {
+        let level = match (keystroke.shift, keystroke.altgr) {
+            (false, false) => 0,
+            (true, false) => 1,
+            (false, true) => 2,
+            (true, true) => return None,
+        };
+        host.keys
+            .iter()
+            .zip(host.levels[level])
+            .find_map(|(keys, chars)| {
+                let index = keys.iter().position(|&k| k == keystroke.key)?;
+                chars.chars().nth(index).filter(|&c| c != '·')
+            })
+    }
+
+    /// Check that every printable ASCII character is typed by the host as
+    /// itself
+    fn round_trip(layout: HostLayout, host: &Host) {
+        for c in ' '..='~' {
+            let keystroke = layout.keystroke(c);
+            assert_eq!(
+                keystroke.and_then(|k| typed(host, k)),
+                Some(c),
+                "{:?}",
+                keystroke
+            );
+        }
+    }
+
+    #[test]
+    fn us() {
+        round_trip(HostLayout::Us, &US);
+    }
+
+    #[test]
+    fn dvorak() {
+        round_trip(HostLayout::Dvorak, &DVORAK);
+    }
+
+    #[test]
+    fn azerty() {
+        round_trip(HostLayout::Azerty, &AZERTY);
+    }
+
+    #[test]
+    fn bepo() {
+        round_trip(HostLayout::Bepo, &BEPO);
+    }
+}
@@ -0,0 +1,713 @@
+//! The keyboard logic, from key events to HID reports
+
+use crate::action::{Action, CustomAction};
+use crate::autoshift::AutoShift;
+use crate::capsword::CapsWord;
+use crate::combo::Combos;
+use crate::dynamicmacro::{self, DynamicMacros};
+use crate::holdtap::History;
+use crate::host::Chars;
+use crate::keyoverride;
+use crate::layerlock::LayerLock;
+use crate::leader::Leader;
+use crate::longhold::LongHolds;
+use crate::numword::NumWord;
+use crate::oneshot::OneShots;
+use crate::prefix::Prefixer;
+use crate::repeat::Repeat;
+use crate::sequence::{Sequence, Sequencer};
+use crate::swaphands::SwapHands;
+use crate::tapdance::TapDances;
+use crate::trilayer::TriLayers;
+use crate::{KBLayout, CONFIG, LAYERS};
+use heapless::Vec;
+use keyberon::key_code::{KbHidReport, KeyCode};
+use keyberon::layout::{CustomEvent, Event, Layout};
+
+/// Requests to the hardware, emitted by custom actions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Jump to the bootloader
+    Bootloader,
+    /// Save the dynamic macros, from `Keyboard::save_macros`, to flash
+    SaveMacros,
+}
+
+/// Maximum number of keys tracked at once
+const KEYS: usize = 16;
+
+/// State of the keyboard
+pub struct Keyboard {
+    /// Layout of the keyboard
+    layout: KBLayout,
+    /// Detection of the combos
+    combos: Combos,
+    /// Detection of the tap dances
+    tap_dances: TapDances,
+    /// Detection of the long holds
+    long_holds: LongHolds,
+    /// Auto-shift
+    auto_shift: AutoShift,
+    /// Player of the sequences
+    sequencer: Sequencer,
+    /// Characters held by `ch` keys
+    chars: Chars,
+    /// Prefixes of the layers
+    prefixer: Prefixer,
+    /// One-shot modifiers and layers
+    one_shots: OneShots,
+    /// Leader key
+    leader: Leader,
+    /// Caps Word
+    caps_word: CapsWord,
+    /// Num Word
+    num_word: NumWord,
+    /// Layer lock
+    layer_lock: LayerLock,
+    /// Tri-layers
+    tri_layers: TriLayers,
+    /// Repeat keys
+    repeat: Repeat,
+    /// Swap-hands
+    swap_hands: SwapHands,
+    /// Dynamic macros
+    dynamic_macros: DynamicMacros,
+    /// Number of keys held
+    held: usize,
+    /// Default layer, when no layer is activated by a held key or a
+    /// one-shot action
+    default_layer: usize,
+}
+
+impl Keyboard {
+    /// Create a new keyboard using the keymap's layers
+    pub fn new() -> Self {
+        History::reset();
+        Self {
+            layout: Layout::new(&LAYERS),
+            combos: Combos::new(CONFIG.combos, CONFIG.combo_timeout),
+            tap_dances: TapDances::new(CONFIG.tap_dances),
+            long_holds: LongHolds::new(CONFIG.long_holds),
+            auto_shift: AutoShift::new(CONFIG.auto_shift, CONFIG.auto_shift_timeout),
+            sequencer: Sequencer::new(CONFIG.host_layout, CONFIG.unicode_mode),
+            chars: Chars::new(CONFIG.host_layout),
+            prefixer: Prefixer::new(CONFIG.prefix_layers),
+            one_shots: OneShots::new(),
+            leader: Leader::new(CONFIG.leader, CONFIG.leader_timeout),
+            caps_word: CapsWord::new(CONFIG.caps_word_timeout),
+            num_word: NumWord::new(CONFIG.num_word_keys),
+            layer_lock: LayerLock::new(CONFIG.layer_lock_exits),
+            tri_layers: TriLayers::new(CONFIG.tri_layers),
+            repeat: Repeat::new(CONFIG.alt_repeats),
+            swap_hands: SwapHands::new(CONFIG.swap_hands_thumbs),
+            dynamic_macros: DynamicMacros::new(),
+            held: 0,
+            default_layer: 0,
+        }
+    }
+
+    /// Handle a key event
+    pub fn event(&mut self, event: Event) {
+        let event = self.swap_hands.event(event);
+        self.combos.event(event, self.layout.current_layer());
+        self.route_events();
+    }
+
+    /// Send the events coming out of the combo detection to the tap dance
+    /// detection, then to the long hold detection, then to auto-shift, then
+    /// to the layout
+    fn route_events(&mut self) {
+        while let Some(event) = self.combos.pop() {
+            self.tap_dances.event(event, self.layout.current_layer());
+        }
+        while let Some(event) = self.tap_dances.pop() {
+            self.long_holds.event(event, self.layout.current_layer());
+        }
+        while let Some(event) = self.long_holds.pop() {
+            let (i, j) = event.coord();
+            let modified = self.keycodes().any(|kc| kc.is_modifier());
+            self.auto_shift.event(event, self.action(i, j), modified);
+        }
+        while let Some(event) = self.auto_shift.pop() {
+            self.route(event);
+        }
+    }
+
+    /// Action of the key at `(i, j)` on the current layer
+    ///
+    /// keyberon adds up the layers held, which can go past the last layer:
+    /// like in keyberon, keys do nothing there.
+    fn action(&self, i: u8, j: u8) -> &'static Action {
+        let (i, j) = (usize::from(i), usize::from(j));
+        let action = |layer: usize| {
+            LAYERS
+                .get(layer)
+                .and_then(|layer| layer.get(i))
+                .and_then(|row| row.get(j))
+                .unwrap_or(&Action::NoOp)
+        };
+        match action(self.layout.current_layer()) {
+            Action::Trans => action(self.default_layer),
+            action => action,
+        }
+    }
+
+    /// Send an event to the layout
+    fn route(&mut self, event: Event) {
+        let (i, j) = event.coord();
+        let action = self.action(i, j);
+        if event.is_press() {
+            self.held += 1;
+            self.one_shots.key_pressed();
+            History::press((i, j), matches!(action, Action::HoldTap(_)));
+        } else {
+            self.held = self.held.saturating_sub(1);
+            History::release((i, j));
+        }
+        if self.tri_layers.event(event, action) {
+            self.layout.event(event)
+        }
+    }
+
+    /// Advance the state of the keyboard by one millisecond
+    ///
+    /// Returns what the hardware has to do, if anything.
+    pub fn tick(&mut self) -> Option<Command> {
+        History::tick();
+        self.combos.tick();
+        self.tap_dances.tick();
+        self.long_holds.tick();
+        if self.auto_shift.tick() {
+            self.one_shots.key_pressed();
+        }
+        self.route_events();
+        let event = match self.layout.tick() {
+            CustomEvent::Press(&action) => Some((action, true)),
+            CustomEvent::Release(&action) => Some((action, false)),
+            CustomEvent::NoEvent => None,
+        };
+        self.tri_layers.tick(&mut self.layout, self.default_layer);
+        if self.held == 0 && !self.one_shots.has_layer() {
+            self.default_layer = self.layout.current_layer();
+        }
+        let mut command = None;
+        let mut recorded = false;
+        match event {
+            Some((CustomAction::Sequence(sequence), true)) => self.sequencer.push(sequence),
+            Some((CustomAction::OneShot(one_shot), true)) => {
+                self.one_shots
+                    .press(one_shot, &mut self.layout, self.default_layer)
+            }
+            Some((CustomAction::OneShot(one_shot), false)) => {
+                self.one_shots
+                    .release(one_shot, &mut self.layout, self.default_layer)
+            }
+            Some((CustomAction::Leader, true)) => self.leader.start(),
+            Some((CustomAction::CapsWord, true)) => self.caps_word.toggle(),
+            Some((CustomAction::NumWord(layer), true)) => {
+                self.num_word
+                    .toggle(layer, &mut self.layout, self.default_layer)
+            }
+            Some((CustomAction::LayerLock, true)) => {
+                self.layer_lock.toggle(&mut self.layout, self.default_layer)
+            }
+            Some((CustomAction::Unicode(lower, upper), true)) => {
+                let c = if self.is_shifted() { upper } else { lower };
+                self.sequencer.push(Sequence::Unicode(c))
+            }
+            Some((CustomAction::UnicodeMode(mode), true)) => self.sequencer.set_unicode_mode(mode),
+            Some((CustomAction::Char(c), true)) => self.chars.press(c),
+            Some((CustomAction::Char(c), false)) => self.chars.release(c),
+            Some((CustomAction::AutoShift, true)) => self.auto_shift.toggle(),
+            Some((CustomAction::Repeat, true)) => self.repeat.press(false),
+            Some((CustomAction::AltRepeat, true)) => self.repeat.press(true),
+            Some((CustomAction::Repeat | CustomAction::AltRepeat, false)) => self.repeat.release(),
+            Some((CustomAction::SwapHands, true)) => self.swap_hands.press(),
+            Some((CustomAction::SwapHands, false)) => self.swap_hands.release(),
+            Some((CustomAction::SwapHandsToggle, true)) => self.swap_hands.toggle(),
+            Some((CustomAction::MacroRecord(slot), true)) => {
+                recorded = self.dynamic_macros.record(slot)
+            }
+            Some((CustomAction::MacroPlay(slot), true)) => {
+                recorded = self.dynamic_macros.play(slot)
+            }
+            Some((CustomAction::Bootloader, false)) => command = Some(Command::Bootloader),
+            _ => {}
+        }
+        let keycodes: Vec<KeyCode, KEYS> = self.keycodes().collect();
+        let one_shot_pressed = matches!(event, Some((CustomAction::OneShot(_), true)));
+        self.one_shots.tick(
+            one_shot_pressed,
+            keycodes.iter().copied(),
+            &mut self.layout,
+            self.default_layer,
+        );
+        self.leader
+            .tick(keycodes.iter().copied(), &mut self.sequencer);
+        self.caps_word.tick(keycodes.iter().copied());
+        self.repeat.tick(
+            keycodes
+                .iter()
+                .copied()
+                .chain(self.one_shots.modifiers())
+                .chain(self.caps_word.modifiers()),
+        );
+        self.num_word
+            .tick(keycodes.iter().copied(), &mut self.layout);
+        self.layer_lock
+            .tick(keycodes.iter().copied(), &mut self.layout);
+        self.prefixer.tick(
+            self.layout.current_layer(),
+            keycodes.iter().copied(),
+            &mut self.sequencer,
+        );
+        recorded |= self.dynamic_macros.tick(
+            keycodes
+                .iter()
+                .copied()
+                .chain(self.one_shots.modifiers())
+                .chain(self.caps_word.modifiers())
+                .chain(self.sequencer.keycodes()),
+        );
+        self.sequencer.tick();
+        if recorded && CONFIG.save_macros {
+            command = Some(Command::SaveMacros);
+        }
+        command
+    }
+
+    /// Keys pressed by the layout, the `ch` keys, auto-shift, the repeat keys
+    /// and the dynamic macros
+    fn keycodes(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.layout
+            .keycodes()
+            .chain(self.chars.keycodes())
+            .chain(self.auto_shift.keycodes())
+            .chain(self.repeat.keycodes())
+            .chain(self.dynamic_macros.keycodes())
+    }
+
+    /// The dynamic macros, as bytes to save to flash
+    pub fn save_macros(&self) -> [u8; dynamicmacro::STORAGE] {
+        self.dynamic_macros.save()
+    }
+
+    /// Restore the dynamic macros saved to flash, if `save_macros` is set in
+    /// the keymap configuration
+    pub fn load_macros(&mut self, bytes: &[u8]) {
+        if CONFIG.save_macros {
+            self.dynamic_macros.load(bytes);
+        }
+    }
+
+    /// Whether Shift is held, by the layout or a one-shot modifier, or Caps
+    /// Word is on
+    fn is_shifted(&self) -> bool {
+        self.caps_word.is_active()
+            || self
+                .keycodes()
+                .chain(self.one_shots.modifiers())
+                .any(|kc| kc == KeyCode::LShift || kc == KeyCode::RShift)
+    }
+
+    /// HID report of the keys currently pressed, by the layout, the `ch` keys,
+    /// auto-shift, the repeat keys, the dynamic macros and the sequences, with
+    /// the one-shot modifiers and Shift for Caps Word
+    ///
+    /// Keys waiting for the prefix of their layer are not reported yet, and
+    /// keys typed after the leader key are not reported. Key overrides are
+    /// applied, and modifiers are left out while a Unicode character is
+    /// typed.
+    pub fn report(&self) -> KbHidReport {
+        let masked = self.sequencer.masks_modifiers();
+        let mut keycodes: Vec<KeyCode, KEYS> = self
+            .keycodes()
+            .filter(|&kc| self.prefixer.is_sent(kc) && self.leader.is_sent(kc))
+            .chain(self.one_shots.modifiers())
+            .chain(self.caps_word.modifiers())
+            .filter(|kc| !(masked && kc.is_modifier()))
+            .collect();
+        keyoverride::apply(
+            CONFIG.key_overrides,
+            self.layout.current_layer(),
+            &mut keycodes,
+        );
+        keycodes
+            .into_iter()
+            .chain(self.sequencer.keycodes())
+            .collect()
+    }
+}
+
+impl Default for Keyboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+#[cfg(any(feature = "keymap_borisfaure", feature = "keymap_zmk"))]
+mod tests {
+    use super::*;
+    use keyberon::action::{k, l};
+    use keyberon::key_code::KeyCode::*;
+    use std::boxed::Box;
+
+    /// Milliseconds to hold a hold-tap for its hold
+    const HOLD: u16 = 250;
+
+    /// Let `ms` milliseconds pass
+    fn wait(kb: &mut Keyboard, ms: u16) {
+        for _ in 0..ms {
+            kb.tick();
+        }
+    }
+
+    /// Press the key at `key`
+    fn press(kb: &mut Keyboard, key: (u8, u8)) {
+        kb.event(Event::Press(key.0, key.1));
+        wait(kb, 1);
+    }
+
+    /// Release the key at `key`
+    fn release(kb: &mut Keyboard, key: (u8, u8)) {
+        kb.event(Event::Release(key.0, key.1));
+        wait(kb, 1);
+    }
+
+    /// Hold the key at `key` long enough for the hold of a hold-tap
+    fn hold(kb: &mut Keyboard, key: (u8, u8)) {
+        press(kb, key);
+        wait(kb, HOLD);
+    }
+
+    /// Bytes of the report of `keys`
+    fn report(keys: &[KeyCode]) -> Vec<u8, 8> {
+        let report: KbHidReport = keys.iter().copied().collect();
+        Vec::from_slice(report.as_bytes()).unwrap()
+    }
+
+    /// Bytes of the current report of `kb`
+    fn sent(kb: &Keyboard) -> Vec<u8, 8> {
+        Vec::from_slice(kb.report().as_bytes()).unwrap()
+    }
+
+    /// Tap the key at `key`, returning the first report with a key pressed
+    fn tap(kb: &mut Keyboard, key: (u8, u8)) -> Vec<u8, 8> {
+        let before = sent(kb);
+        let mut first = None;
+        kb.event(Event::Press(key.0, key.1));
+        for ms in 0..40 {
+            if ms == 20 {
+                kb.event(Event::Release(key.0, key.1));
+            }
+            kb.tick();
+            if first.is_none() && sent(kb) != before {
+                first = Some(sent(kb));
+            }
+        }
+        first.unwrap_or(before)
+    }
+
+    #[test]
+    fn layers_adding_up_past_the_count() {
+        // (3, 3) holds the last layer, where (3, 4) holds it again
+        let last = LAYERS.len() - 1;
+        let mut layers = LAYERS;
+        layers[0][0][0] = k(A);
+        layers[0][3][3] = l(last);
+        layers[last][3][4] = l(last);
+        let _history = History::lock();
+        let mut kb = Keyboard::new();
+        kb.layout = Layout::new(Box::leak(Box::new(layers)));
+        press(&mut kb, (3, 3));
+        press(&mut kb, (3, 4));
+        assert_eq!(kb.layout.current_layer(), 2 * last);
+        assert_eq!(tap(&mut kb, (0, 0)), report(&[]));
+        release(&mut kb, (3, 4));
+        release(&mut kb, (3, 3));
+        assert_eq!(tap(&mut kb, (0, 0)), report(&[A]));
+    }
+
+    #[cfg(feature = "keymap_borisfaure")]
+    mod borisfaure {
+        use super::*;
+
+        /// HT_W_W on BASE, W when tapped, 4 on NUMBERS
+        const Q_4: (u8, u8) = (0, 1);
+        /// HT_C_A: Left Control when held, or A
+        const HT_C_A: (u8, u8) = (1, 0);
+        /// HT_3_N: NUMBERS when held, or N
+        const HT_3_N: (u8, u8) = (2, 5);
+        /// NUM on RAISE, LOCK on NUMBERS
+        const NUM_LOCK: (u8, u8) = (2, 4);
+        /// HT_1_TAB: LOWER when held, or Tab
+        const LOWER: (u8, u8) = (3, 3);
+        /// Space
+        const SPACE: (u8, u8) = (3, 4);
+        /// HT_2_ENT: RAISE when held, or Enter
+        const RAISE: (u8, u8) = (3, 6);
+        /// HT_S_ESC: Left Shift when held, or Escape
+        const HT_S_ESC: (u8, u8) = (2, 0);
+        /// Tap dance on RAISE: BASE, or the gaming layer on a double tap
+        const BASE_GAME: (u8, u8) = (0, 0);
+        /// Gaming layer
+        const GAMING: usize = 6;
+        /// HT_4_T: MISC when held, Shift-T when held long, or T
+        const HT_4_T: (u8, u8) = (0, 4);
+        /// VolUp on MISC
+        const VOL_UP: (u8, u8) = (1, 1);
+        /// Milliseconds to hold a key for its long hold
+        const LONG_HOLD: u16 = 550;
+
+        /// Release the key at `key`, returning the first report with a key
+        /// pressed
+        fn release_sent(kb: &mut Keyboard, key: (u8, u8)) -> Vec<u8, 8> {
+            let before = sent(kb);
+            kb.event(Event::Release(key.0, key.1));
+            (0..20)
+                .find_map(|_| {
+                    kb.tick();
+                    (sent(kb) != before).then(|| sent(kb))
+                })
+                .unwrap_or(before)
+        }
+
+        /// Turn Num Word on from RAISE
+        fn num_word(kb: &mut Keyboard) {
+            hold(kb, RAISE);
+            tap(kb, NUM_LOCK);
+            release(kb, RAISE);
+        }
+
+        /// Lock the NUMBERS layer
+        fn lock_numbers(kb: &mut Keyboard) {
+            hold(kb, HT_3_N);
+            tap(kb, NUM_LOCK);
+            release(kb, HT_3_N);
+        }
+
+        /// Hold LOWER and RAISE for ADJUST
+        fn hold_adjust(kb: &mut Keyboard) {
+            press(kb, LOWER);
+            press(kb, RAISE);
+            wait(kb, HOLD);
+        }
+
+        /// Release LOWER and RAISE
+        fn release_adjust(kb: &mut Keyboard) {
+            release(kb, RAISE);
+            release(kb, LOWER);
+        }
+
+        #[test]
+        fn plain_tap() {
+            let _history = History::lock();
+            let mut kb = Keyboard::new();
+            press(&mut kb, (0, 0));
+            assert_eq!(sent(&kb), report(&[Q]));
+            release(&mut kb, (0, 0));
+            assert_eq!(sent(&kb), report(&[]));
+        }
+
+        #[test]
+        fn gaming_layer() {
+            let _history = History::lock();
+            let mut kb = Keyboard::new();
+            hold(&mut kb, RAISE);
+            tap(&mut kb, BASE_GAME);
+            tap(&mut kb, BASE_GAME);
+            release(&mut kb, RAISE);
+            assert_eq!(kb.layout.current_layer(), GAMING);
+            assert_eq!(tap(&mut kb, HT_S_ESC), report(&[Z]));
+            hold(&mut kb, RAISE);
+            tap(&mut kb, BASE_GAME);
+            wait(&mut kb, 200);
+            release(&mut kb, RAISE);
+            assert_eq!(kb.layout.current_layer(), 0);
+            // Escape is not delayed by a tap dance
+            assert_eq!(tap(&mut kb, HT_S_ESC), report(&[Escape]));
+        }
+
+        #[test]
+        fn long_hold() {
+            let _history = History::lock();
+            let mut kb = Keyboard::new();
+            press(&mut kb, HT_4_T);
+            wait(&mut kb, LONG_HOLD);
+            assert_eq!(release_sent(&mut kb, HT_4_T), report(&[LShift, T]));
+        }
+
+        #[test]
+        fn long_hold_of_a_layer() {
+            // MISC is held longer than the long hold, then used
+            let _history = History::lock();
+            let mut kb = Keyboard::new();
+            press(&mut kb, HT_4_T);
+            wait(&mut kb, LONG_HOLD);
+            assert_eq!(tap(&mut kb, VOL_UP), report(&[VolUp]));
+            assert_eq!(release_sent(&mut kb, HT_4_T), report(&[]));
+        }
+
+        #[test]
+        fn hold_tap() {
+            let _history = History::lock();
+            let mut kb = Keyboard::new();
+            assert_eq!(tap(&mut kb, HT_C_A), report(&[A]));
+            hold(&mut kb, HT_C_A);
+            assert_eq!(sent(&kb), report(&[LCtrl]));
+            release(&mut kb, HT_C_A);
+            assert_eq!(sent(&kb), report(&[]));
+        }
+
+        #[test]
+        fn layer() {
+            let _history = History::lock();
+            let mut kb = Keyboard::new();
+            hold(&mut kb, HT_3_N);
+            assert_eq!(tap(&mut kb, Q_4), report(&[Kb4]));
+            release(&mut kb, HT_3_N);
+            assert_eq!(tap(&mut kb, Q_4), report(&[W]));
+        }
+
+        #[test]
+        fn num_word_until_space() {
+            let _history = History::lock();
+            let mut kb = Keyboard::new();
+            num_word(&mut kb);
+            assert_eq!(tap(&mut kb, Q_4), report(&[Kb4]));
+            assert_eq!(tap(&mut kb, Q_4), report(&[Kb4]));
+            assert_eq!(tap(&mut kb, SPACE), report(&[Space]));
+            assert_eq!(tap(&mut kb, Q_4), report(&[W]));
+        }
+
+        #[test]
+        fn layer_lock() {
+            let _history = History::lock();
+            let mut kb = Keyboard::new();
+            lock_numbers(&mut kb);
+            assert_eq!(tap(&mut kb, Q_4), report(&[Kb4]));
+            assert_eq!(tap(&mut kb, SPACE), report(&[Space]));
+            assert_eq!(tap(&mut kb, Q_4), report(&[Kb4]));
+            tap(&mut kb, NUM_LOCK);
+            assert_eq!(tap(&mut kb, Q_4), report(&[W]));
+        }
+
+        #[test]
+        fn tri_layer() {
+            let _history = History::lock();
+            let mut kb = Keyboard::new();
+            hold_adjust(&mut kb);
+            assert_eq!(kb.layout.current_layer(), 7);
+            release(&mut kb, RAISE);
+            assert_eq!(kb.layout.current_layer(), 1);
+            release(&mut kb, LOWER);
+            assert_eq!(kb.layout.current_layer(), 0);
+            assert_eq!(tap(&mut kb, Q_4), report(&[W]));
+        }
+
+        #[test]
+        fn tri_layer_one_after_the_other() {
+            let _history = History::lock();
+            let mut kb = Keyboard::new();
+            hold(&mut kb, LOWER);
+            assert_eq!(kb.layout.current_layer(), 1);
+            hold(&mut kb, RAISE);
+            assert_eq!(kb.layout.current_layer(), 7);
+            release_adjust(&mut kb);
+            assert_eq!(kb.layout.current_layer(), 0);
+        }
+
+        #[test]
+        fn num_word_in_locked_layer() {
+            let _history = History::lock();
+            let mut kb = Keyboard::new();
+            lock_numbers(&mut kb);
+            num_word(&mut kb);
+            assert_eq!(tap(&mut kb, Q_4), report(&[Kb4]));
+            // Num Word ends on Space, back to the layer still locked
+            assert_eq!(tap(&mut kb, SPACE), report(&[Space]));
+            assert_eq!(tap(&mut kb, Q_4), report(&[Kb4]));
+            tap(&mut kb, NUM_LOCK);
+            assert_eq!(tap(&mut kb, Q_4), report(&[W]));
+        }
+
+        #[test]
+        fn tri_layer_during_num_word() {
+            let _history = History::lock();
+            let mut kb = Keyboard::new();
+            num_word(&mut kb);
+            hold_adjust(&mut kb);
+            assert_eq!(kb.layout.current_layer(), 7);
+            release_adjust(&mut kb);
+            // Back to Num Word, not to BASE
+            assert_eq!(tap(&mut kb, Q_4), report(&[Kb4]));
+            assert_eq!(tap(&mut kb, SPACE), report(&[Space]));
+            assert_eq!(tap(&mut kb, Q_4), report(&[W]));
+        }
+    }
+
+    #[cfg(feature = "keymap_zmk")]
+    mod zmk {
+        use super::*;
+
+        /// Q on the base layer, 1 on the numbers layer
+        const Q_1: (u8, u8) = (0, 0);
+        /// `&sk LSHFT` on the navigation layer
+        const ONE_SHOT_SHIFT: (u8, u8) = (1, 0);
+        /// `&sl NUM` on the navigation layer
+        const ONE_SHOT_NUM: (u8, u8) = (1, 4);
+        /// `&lt NAV TAB`, `&mo NUM` on the symbols layer
+        const NAV: (u8, u8) = (3, 3);
+        /// `&lt SYM RET`
+        const SYM: (u8, u8) = (3, 6);
+
+        /// Tap the key at `key` on the navigation layer
+        fn tap_nav(kb: &mut Keyboard, key: (u8, u8)) {
+            hold(kb, NAV);
+            tap(kb, key);
+            release(kb, NAV);
+        }
+
+        #[test]
+        fn layer_held_from_a_held_layer() {
+            let _history = History::lock();
+            let mut kb = Keyboard::new();
+            hold(&mut kb, SYM);
+            press(&mut kb, NAV);
+            assert_eq!(tap(&mut kb, Q_1), report(&[Kb1]));
+            release(&mut kb, NAV);
+            release(&mut kb, SYM);
+            assert_eq!(tap(&mut kb, Q_1), report(&[Q]));
+        }
+
+        #[test]
+        fn one_shot_modifier() {
+            let _history = History::lock();
+            let mut kb = Keyboard::new();
+            tap_nav(&mut kb, ONE_SHOT_SHIFT);
+            assert_eq!(sent(&kb), report(&[LShift]));
+            assert_eq!(tap(&mut kb, Q_1), report(&[LShift, Q]));
+            assert_eq!(tap(&mut kb, Q_1), report(&[Q]));
+        }
+
+        #[test]
+        fn one_shot_layer() {
+            let _history = History::lock();
+            let mut kb = Keyboard::new();
+            tap_nav(&mut kb, ONE_SHOT_NUM);
+            assert_eq!(tap(&mut kb, Q_1), report(&[Kb1]));
+            assert_eq!(tap(&mut kb, Q_1), report(&[Q]));
+        }
+
+        #[test]
+        fn one_shot_modifier_and_layer() {
+            let _history = History::lock();
+            let mut kb = Keyboard::new();
+            tap_nav(&mut kb, ONE_SHOT_SHIFT);
+            tap_nav(&mut kb, ONE_SHOT_NUM);
+            assert_eq!(tap(&mut kb, Q_1), report(&[LShift, Kb1]));
+            assert_eq!(tap(&mut kb, Q_1), report(&[Q]));
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use crate::action::{Action, CustomAction, LEADER};
+use crate::combo::Combo;
+use crate::config::Config;
+use crate::holdtap::bilateral;
+use crate::leader::LeaderSequence;
+use crate::sequence::Sequence;
+use crate::trilayer::TriLayer;
+use keyberon::action::{d, k, l, m, HoldTapAction, HoldTapConfig};
+use keyberon::key_code::KeyCode::*;
+use keyberon::layout::Layout;
+
+/// Keyboard Layout type to mask the number of layers
+pub type KBLayout = Layout<10, 4, 8, CustomAction>;
+
+/// Timeout to consider a key as held
+const TIMEOUT: u16 = 200;
+/// Disable tap_hold_interval
+const TAP_HOLD_INTERVAL: u16 = 0;
+/// Home row keys are only held when the next key is on the other half or is
+/// a thumb key, so same hand rolls are typed
+const HRM: HoldTapConfig = HoldTapConfig::Custom(bilateral::<0>);
+
+/// Helper to create a HoldTapAction, with `TIMEOUT` and
+/// `HoldTapConfig::Default` unless a timeout and a config are given
+macro_rules! ht {
+    ($h:expr, $t:expr) => {
+        ht!($h, $t, TIMEOUT, HoldTapConfig::Default)
+    };
+    ($h:expr, $t:expr, $timeout:expr, $config:expr) => {
+        Action::HoldTap(&HoldTapAction {
+            timeout: $timeout,
+            tap_hold_interval: TAP_HOLD_INTERVAL,
+            config: $config,
+            hold: $h,
+            tap: $t,
+        })
+    };
+}
+
+/// HoldTap(KeyCode(LShift), KeyCode(A))
+const LSA: Action = ht!(k(LShift), k(A), TIMEOUT, HRM);
+/// HoldTap(Layer(5), KeyCode(S))
+const S5: Action = ht!(l(5), k(S), TIMEOUT, HRM);
+/// HoldTap(Layer(1), KeyCode(D))
+const D1: Action = ht!(l(1), k(D), TIMEOUT, HRM);
+/// HoldTap(Layer(3), KeyCode(F))
+const F3: Action = ht!(l(3), k(F), TIMEOUT, HRM);
+/// HoldTap(Layer(4), KeyCode(J))
+const J4: Action = ht!(l(4), k(J), TIMEOUT, HRM);
+/// HoldTap(Layer(2), KeyCode(K))
+const K2: Action = ht!(l(2), k(K), TIMEOUT, HRM);
+/// HoldTap(Layer(6), KeyCode(L))
+const L6: Action = ht!(l(6), k(L), TIMEOUT, HRM);
+#[allow(non_upper_case_globals)]
+/// HoldTap(Layer(7), KeyCode(Space))
+const Sp7: Action = ht!(l(7), k(Space));
+
+#[allow(non_upper_case_globals)]
+/// HoldTap(KeyCode(LShift), KeyCode(SColon))
+const LSSc: Action = ht!(k(LShift), k(SColon), TIMEOUT, HRM);
+/// HoldTap(KeyCode(LCtrl), KeyCode(X))
+const LCX: Action = ht!(k(LCtrl), k(X));
+/// HoldTap(KeyCode(LAlt), KeyCode(C)),
+const LAC: Action = ht!(k(LAlt), k(C));
+#[allow(non_upper_case_globals)]
+/// HoldTap(KeyCode(LAlt), KeyCode(Comma))
+const LACm: Action = ht!(k(LAlt), k(Comma));
+#[allow(non_upper_case_globals)]
+/// HoldTap(KeyCode(LCtrl), KeyCode(Dot))
+const LCDot: Action = ht!(k(LCtrl), k(Dot));
+/// MultipleKeyCodes([LCtrl, LAlt])
+const CA: Action = m(&[LCtrl, LAlt].as_slice());
+/// MultipleKeyCodes([LCtrl, LAlt, LShift])
+const CAS: Action = m(&[LCtrl, LAlt, LShift].as_slice());
+/// HoldTap(KeyCode(RCtrl), KeyCode(Dot))
+const RCD: Action = ht!(k(RCtrl), k(Dot));
+#[allow(non_upper_case_globals)]
+/// HoldTap(KeyCode(RAlt), KeyCode(Comma))
+const RACm: Action = ht!(k(RAlt), k(Comma));
+
+/// DefaultLayer(0)
+const DL0: Action = d(0);
+/// DefaultLayer(1)
+const DL1: Action = d(1);
+
+/// Features beyond the layers
+pub static CONFIG: Config = Config {
+    combos: &[
+        // S + D: Escape
+        Combo {
+            keys: &[(1, 1), (1, 2)],
+            output: (3, 0),
+            layers: &[0],
+        },
+        // B + N: Enter
+        Combo {
+            keys: &[(2, 4), (2, 5)],
+            output: (3, 9),
+            layers: &[0],
+        },
+    ],
+    leader: &[
+        LeaderSequence {
+            keys: &[G],
+            sequence: Sequence::Text("git "),
+        },
+        LeaderSequence {
+            keys: &[G, S],
+            sequence: Sequence::Text("git status\n"),
+        },
+        LeaderSequence {
+            keys: &[G, D],
+            sequence: Sequence::Text("git diff\n"),
+        },
+    ],
+    // Layer 7 is always accessible: holding it on top of another layer held
+    // activates it, instead of adding up past the last layer
+    tri_layers: &[
+        TriLayer {
+            layers: &[1, 7],
+            layer: 7,
+        },
+        TriLayer {
+            layers: &[2, 7],
+            layer: 7,
+        },
+        TriLayer {
+            layers: &[3, 7],
+            layer: 7,
+        },
+        TriLayer {
+            layers: &[4, 7],
+            layer: 7,
+        },
+        TriLayer {
+            layers: &[5, 7],
+            layer: 7,
+        },
+        TriLayer {
+            layers: &[6, 7],
+            layer: 7,
+        },
+    ],
+    ..Config::DEFAULT
+};
+
+#[rustfmt::skip]
+/// Layout
+pub static LAYERS: keyberon::layout::Layers<10, 4, 8, CustomAction> = keyberon::layout::layout! {
+    { // 0: Base Layer, (3, 0) and (3, 9) are pressed by combos
+        [ Q     W     E     R    T      Y     U    I      O        P    ],
+        [{LSA} {S5}  {D1}  {F3}  G      H    {J4} {K2}   {L6}     {LSSc}],
+        [ Z    {LCX} {LAC}  V    B      N     M   {LACm} {LCDot}   /    ],
+        [Escape n     n     0   BSpace {Sp7}  1    n      n      Enter  ],
+    } { // 1: Mouse TODO: mouse support
+        // Set with d(1): (3, 5) holds layer 7 itself, for d(0), since
+        // transparent keys do nothing on the default layer
+        [t t t t t    t     n n n t],
+        [t n n n t    t     n n n n],
+        [t t t t t    t     n n n t],
+        [n n n t t  {Sp7}   t n n n],
+    } { // 2: Navigation
+        [ t    t   PgUp    t    t        t  t   t  t    t   ],
+        [Left Up   Down   Right t        t LGui n {CA} {CAS}],
+        [ t   Home PgDown End   t        t  t   t  t    t   ],
+        [ n   n     n      t    t        t  t   n  n    n   ],
+    } { // 3: Right Symbols
+        [t t t t t     t '_'   |   '\'' t],
+        [^ * & n t     #  ~    /   '"'  $],
+        [t t t t t     t  -   '\\'  .   t],
+        [n n n n t     t  n    n    n   n],
+    } { // 4: Left Symbols
+        [ t    :    <    >            ;     t   t         t t t],
+        ['{'  '}'  '('  ')'           @     t   n         = + %],
+        [ t    !   '['  ']'           t     t   t         t t t],
+        [ n    n    n   MediaVolDown  t     t  MediaVolUp n n n],
+    } { // 5: Function keys
+        [t t  t   t t      t F7 F8 F9 F10],
+        [t n {CA} n t      t F4 F5 F6 F11],
+        [t t  t   t t      t F1 F2 F3 F12],
+        [n n  n   t t      t t  n  n  n  ],
+    } { // 6: Numbers
+        [/ 7 8 9 +     t t t t t],
+        [0 1 2 3 -     t n n n t],
+        [* 4 5 6 =     t t t t t],
+        [n n n t t     t t n n n],
+    } { // 7: Always accessible
+        [t t : Escape  t     t     t    t        t       Delete],
+        [t % / Enter   t    {DL1} LGui  n     {LEADER}    t    ],
+        [t t t  !      t    {DL0}  t   {RACm}  {RCD}      n    ],
+        [n n n  t     Tab    n     t    n        n        n    ],
+    }
+};
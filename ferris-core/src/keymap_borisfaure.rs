@@ -0,0 +1,237 @@
+use crate::action::{
+    num_word, play_macro, record_macro, unicode, unicode_mode, Action, CustomAction, AUTO_SHIFT,
+    CAPS_WORD, LAYER_LOCK, SWAP_HANDS_TOGGLE,
+};
+use crate::config::Config;
+use crate::keyoverride::KeyOverride;
+use crate::longhold::LongHold;
+use crate::prefix::PrefixLayer;
+use crate::sequence::SequenceEvent::{Press, Release, Tap};
+use crate::tapdance::TapDance;
+use crate::trilayer::TriLayer;
+use crate::unicode::UnicodeMode;
+use keyberon::action::{d, k, l, m, HoldTapAction, HoldTapConfig};
+use keyberon::key_code::KeyCode::*;
+use keyberon::layout::Layout;
+
+/// Keyboard Layout type to mask the number of layers
+pub type KBLayout = Layout<10, 4, 8, CustomAction>;
+
+/// Timeout to consider a key as held
+const TIMEOUT: u16 = 200;
+/// Timeout to consider a key as held long, after its hold
+const LONG_TIMEOUT: u16 = 500;
+/// Timeout to tap a tap dance key again
+const TAP_DANCE_TIMEOUT: u16 = 200;
+/// Disable tap_hold_interval
+const TAP_HOLD_INTERVAL: u16 = 0;
+
+/// Helper to create a HoldTapAction, with `TIMEOUT` and
+/// `HoldTapConfig::Default` unless a timeout and a config are given
+macro_rules! ht {
+    ($h:expr, $t:expr) => {
+        ht!($h, $t, TIMEOUT, HoldTapConfig::Default)
+    };
+    ($h:expr, $t:expr, $timeout:expr, $config:expr) => {
+        Action::HoldTap(&HoldTapAction {
+            timeout: $timeout,
+            tap_hold_interval: TAP_HOLD_INTERVAL,
+            config: $config,
+            hold: $h,
+            tap: $t,
+        })
+    };
+}
+
+/// Win when held, or W
+const HT_W_W: Action = ht!(k(LGui), k(W));
+/// Win when held, or O
+const HT_W_O: Action = ht!(k(RGui), k(O));
+/// Left Control when held, or A
+const HT_C_A: Action = ht!(k(LCtrl), k(A));
+/// Right Control when held, or SemiColon
+const HT_C_SC: Action = ht!(k(RCtrl), k(SColon));
+/// Left Shift when held, or Escape
+const HT_S_ESC: Action = ht!(k(LShift), k(Escape));
+/// Right Shift when held, or Slash
+const HT_S_SL: Action = ht!(k(RShift), k(Slash));
+/// Left Alt when held, or X
+const HT_A_X: Action = ht!(k(LAlt), k(X));
+/// Left Alt when held, or .
+const HT_A_DOT: Action = ht!(k(LAlt), k(Dot));
+
+/// Layer 1 (lower) when held, or Tab
+const HT_1_TAB: Action = ht!(l(1), k(Tab));
+
+/// Layer 2 (raise) when held, or Enter
+const HT_2_ENT: Action = ht!(l(2), k(Enter));
+
+/// Layer 3 (numbers/Fx) when held, or B
+const HT_3_B: Action = ht!(l(3), k(B));
+/// Layer 3 (numbers/Fx) when held, or N
+const HT_3_N: Action = ht!(l(3), k(N));
+
+/// Num Word on layer 3 (numbers/Fx), kept while typing a number
+const NUM: Action = num_word(3);
+/// Keep the layer held, such as the numbers, after its release
+const LOCK: Action = LAYER_LOCK;
+
+/// Layer 4 (misc) when held, or T
+const HT_4_T: Action = ht!(l(4), k(T));
+/// Layer 4 (misc) when held, or Y
+const HT_4_Y: Action = ht!(l(4), k(Y));
+
+/// Shift-T, on the long hold of T, at (3, 0)
+const S_T: Action = m(&[LShift, T].as_slice());
+/// Shift-Y, on the long hold of Y, at (3, 9)
+const S_Y: Action = m(&[LShift, Y].as_slice());
+
+/// Layer 5 (tmux, Ctrl-B before each key) when held, or F
+const HT_5_F: Action = ht!(l(5), k(F));
+
+/// é, or É with Shift
+const E_AC: Action = unicode('é', 'É');
+/// è, or È with Shift
+const E_GR: Action = unicode('è', 'È');
+/// ê, or Ê with Shift
+const E_CI: Action = unicode('ê', 'Ê');
+/// ë, or Ë with Shift
+const E_TR: Action = unicode('ë', 'Ë');
+/// à, or À with Shift
+const A_GR: Action = unicode('à', 'À');
+/// ô, or Ô with Shift
+const O_CI: Action = unicode('ô', 'Ô');
+/// œ, or Œ with Shift
+const OE: Action = unicode('œ', 'Œ');
+/// ç, or Ç with Shift
+const C_CE: Action = unicode('ç', 'Ç');
+/// ù, or Ù with Shift
+const U_GR: Action = unicode('ù', 'Ù');
+/// î, or Î with Shift
+const I_CI: Action = unicode('î', 'Î');
+
+/// Shift-Insert
+const S_INS: Action = m(&[LShift, Insert].as_slice());
+
+/// Change default layer to GAME
+const GAME: Action = d(6);
+/// Change default layer to BASE
+const BASE: Action = d(0);
+
+/// Type Unicode characters with Ctrl-Shift-U, on Linux
+const UC_LNX: Action = unicode_mode(UnicodeMode::Linux);
+/// Type Unicode characters with Option, on macOS
+const UC_MAC: Action = unicode_mode(UnicodeMode::MacOs);
+/// Type Unicode characters with WinCompose, on Windows
+const UC_WIN: Action = unicode_mode(UnicodeMode::WinCompose(RAlt));
+/// Type Unicode characters with the X11 compose key
+const UC_X11: Action = unicode_mode(UnicodeMode::Compose(RAlt));
+
+/// Toggle swap-hands, to type with the left hand while the right one holds
+/// the mouse
+const SWAP: Action = SWAP_HANDS_TOGGLE;
+
+/// Record the first dynamic macro, or stop recording
+const REC_1: Action = record_macro(0);
+/// Record the second dynamic macro, or stop recording
+const REC_2: Action = record_macro(1);
+/// Play the first dynamic macro
+const PLAY_1: Action = play_macro(0);
+/// Play the second dynamic macro
+const PLAY_2: Action = play_macro(1);
+
+/// Jump to the bootloader
+const BOOT: Action = Action::Custom(CustomAction::Bootloader);
+
+/// Features beyond the layers
+pub static CONFIG: Config = Config {
+    prefix_layers: &[PrefixLayer {
+        layer: 5,
+        prefix: &[Press(LCtrl), Tap(B), Release(LCtrl)],
+    }],
+    key_overrides: &[
+        // Shift-Backspace is Delete, except on the tmux layer
+        KeyOverride {
+            modifiers: &[LShift],
+            key: BSpace,
+            output: &[Delete],
+            layers: &[0, 1, 2, 3, 4, 6, 7],
+        },
+    ],
+    // Tap T or Y, hold for MISC, hold long for Shift-T or Shift-Y
+    long_holds: &[
+        LongHold {
+            key: (0, 4),
+            long: (3, 0),
+            timeout: LONG_TIMEOUT,
+            layers: &[0],
+        },
+        LongHold {
+            key: (0, 5),
+            long: (3, 9),
+            timeout: LONG_TIMEOUT,
+            layers: &[0],
+        },
+    ],
+    // On RAISE, tap for BASE and double tap for the gaming layer: the key is
+    // not used while typing, so waiting for a second tap delays nothing
+    tap_dances: &[TapDance {
+        key: (0, 0),
+        taps: &[(0, 0), (3, 1)],
+        timeout: TAP_DANCE_TIMEOUT,
+        layers: &[2],
+    }],
+    // LOWER and RAISE held together: ADJUST
+    tri_layers: &[TriLayer {
+        layers: &[1, 2],
+        layer: 7,
+    }],
+    save_macros: true,
+    ..Config::DEFAULT
+};
+
+#[rustfmt::skip]
+/// Layout
+pub static LAYERS: keyberon::layout::Layers<10, 4, 8, CustomAction> = keyberon::layout::layout! {
+    { /* 0: BASE */
+[  Q         {HT_W_W}  E   R         {HT_4_T}    {HT_4_Y}   U          I  {HT_W_O}     P        ],
+[ {HT_C_A}    S        D  {HT_5_F}    G           H         J          K   L          {HT_C_SC} ],
+[ {HT_S_ESC} {HT_A_X}  C   V         {HT_3_B}    {HT_3_N}   M          ,  {HT_A_DOT}  {HT_S_SL} ],
+[ {S_T}       n        n  {HT_1_TAB}  Space       BSpace   {HT_2_ENT}  n   n          {S_Y}     ],
+    } { /* 1: LOWER */
+        [ !  #  $    '(' ')'    ^       &       {S_INS}  *      ~    ],
+        [ =  -  '`'  '{' '}'    Left    PgDown  PgUp     Right  '\\' ],
+        [ @  &  %    '[' ']'    Delete  n       Home     '\''   '"'  ],
+        [ n  n  n     n  RAlt   Escape {HT_2_ENT} n      n      n    ],
+    } { /* 2: RAISE */
+        [ {BASE}  n     {E_AC}  {E_GR}       {E_CI}  Z       {U_GR}  {I_CI}  {O_CI}  PScreen ],
+        [ {A_GR}  '_'    +       &            |      Left    Down     Up      Right  PgUp    ],
+        [ {E_TR}  {OE}  {C_CE}  {CAPS_WORD}  {NUM}   N       M        ,       .      PgDown  ],
+        [ n      {GAME}  n      {HT_1_TAB}    RAlt   BSpace  Enter    n       n      n       ],
+    } { /* 3: NUMBERS Fx */
+        [ .  4  5   6          =         /       F1   F2   F3   F4  ],
+        [ 0  1  2   3          -         *       F5   F6   F7   F8  ],
+        [ ,  7  8   9          {LOCK}    +       F9   F10  F11  F12 ],
+        [ n  n  n  {HT_1_TAB}  Space    BSpace  {HT_2_ENT}    n    n    n   ],
+    } { /* 4: MISC TODO: mouse */
+        [ Pause  n                  n               R              n      n  n  n  n  n ],
+        [ {SWAP} VolUp              Mute            VolDown        n      n  n  n  n {SWAP} ],
+        [ n      MediaPreviousSong  MediaPlayPause  MediaNextSong  n {PLAY_1} {PLAY_2} n  n  n ],
+        [ n      n                  n               n              n      n  n  n  n  n ],
+    } { /* 5: TMUX, every key is sent after Ctrl-B */
+        [ Q  W  E  R    T      Y       U      I  O  P ],
+        [ A  S  D  F    G      H       J      K  L  ; ],
+        [ Z  X  C  V    B      N       M      ,  .  / ],
+        [ n  n  n  Tab  Space  BSpace  Enter  n  n  n ],
+    } { /* 6: Gaming */
+        [ Q  W  E   R           T      Y       U          I  {HT_W_O}     P       ],
+        [ A  S  D   F           G      H       J          K   L         {HT_C_SC} ],
+        [ Z  X  C   V           B      N       M          ,  {HT_A_DOT} {HT_S_SL} ],
+        [ n  n  n  {HT_1_TAB}  Space  BSpace  {HT_2_ENT}  n   n          n        ],
+    } { /* 7: ADJUST, LOWER and RAISE held together */
+        [ {BOOT}    n         n         n          n             {REC_1} {REC_2}  n  n  n ],
+        [ {UC_LNX} {UC_MAC}  {UC_WIN}  {UC_X11}   {AUTO_SHIFT}    n  n  n  n  n ],
+        [ {BASE}   {GAME}     n         n          n              n  n  n  n  n ],
+        [ n         n         n        {HT_1_TAB}  n              n {HT_2_ENT}  n  n  n ],
+    }
+};
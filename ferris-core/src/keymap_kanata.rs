@@ -0,0 +1,13 @@
+#![allow(unused_imports)]
+use crate::action::{osl, osm, sequence, unicode, Action, CustomAction};
+use crate::config::Config;
+use crate::sequence::SequenceEvent;
+use keyberon::action::{d, k, l, m, HoldTapAction, HoldTapConfig};
+use keyberon::key_code::KeyCode::*;
+use keyberon::layout::Layout;
+
+// `KBLayout` and `LAYERS`, generated by the build script from the Kanata configuration
+include!(concat!(env!("OUT_DIR"), "/keymap_kanata.rs"));
+
+/// Features beyond the layers
+pub static CONFIG: Config = Config::DEFAULT;
@@ -1,23 +1,37 @@
-use core::convert::Infallible;
-use keyberon::action::{d, k, l, m, Action, HoldTapAction, HoldTapConfig};
+use crate::action::{Action, CustomAction, ALT_REPEAT, REPEAT};
+use crate::config::Config;
+use crate::holdtap::permissive_hold;
+use crate::keyoverride::KeyOverride;
+use crate::repeat::AltRepeat;
+use crate::trilayer::TriLayer;
+use keyberon::action::{d, k, l, m, HoldTapAction, HoldTapConfig};
 use keyberon::key_code::KeyCode::*;
 use keyberon::layout::Layout;
 
 /// Keyboard Layout type to mask the number of layers
-pub type KBLayout = Layout<10, 4, 9, Infallible>;
+pub type KBLayout = Layout<10, 4, 9, CustomAction>;
 
 /// Timeout to consider a key as held
 const TIMEOUT: u16 = 200;
 /// Disable tap_hold_interval
 const TAP_HOLD_INTERVAL: u16 = 0;
+/// Timeout to consider a home row key as held
+const HRM_TIMEOUT: u16 = 250;
+/// Home row keys are held when another key is tapped while they are held,
+/// and tapped when pressed less than 150ms after the previous key
+const HRM: HoldTapConfig = HoldTapConfig::Custom(permissive_hold::<150>);
 
-/// Helper to create a HoldTapAction
+/// Helper to create a HoldTapAction, with `TIMEOUT` and
+/// `HoldTapConfig::Default` unless a timeout and a config are given
 macro_rules! ht {
     ($h:expr, $t:expr) => {
+        ht!($h, $t, TIMEOUT, HoldTapConfig::Default)
+    };
+    ($h:expr, $t:expr, $timeout:expr, $config:expr) => {
         Action::HoldTap(&HoldTapAction {
-            timeout: TIMEOUT,
+            timeout: $timeout,
             tap_hold_interval: TAP_HOLD_INTERVAL,
-            config: HoldTapConfig::Default,
+            config: $config,
             hold: $h,
             tap: $t,
         })
@@ -43,34 +57,34 @@ const LCF: Action = ht!(k(LCtrl), k(F));
 const LCX: Action = ht!(k(LCtrl), k(X));
 
 /// HoldTap(KeyCode(LShift), KeyCode(A))
-const LSA: Action = ht!(k(LShift), k(A));
+const LSA: Action = ht!(k(LShift), k(A), HRM_TIMEOUT, HRM);
 /// HoldTap(KeyCode(LShift), KeyCode(I))
-const LSI: Action = ht!(k(LShift), k(I));
+const LSI: Action = ht!(k(LShift), k(I), HRM_TIMEOUT, HRM);
 /// HoldTap(KeyCode(LShift), KeyCode(O))
-const LSO: Action = ht!(k(LShift), k(O));
+const LSO: Action = ht!(k(LShift), k(O), HRM_TIMEOUT, HRM);
 /// HoldTap(KeyCode(LShift), KeyCode(R))
-const LSR: Action = ht!(k(LShift), k(R));
+const LSR: Action = ht!(k(LShift), k(R), HRM_TIMEOUT, HRM);
 
 /// HoldTap(Layer(2), KeyCode(H))
-const H2: Action = ht!(l(2), k(H));
+const H2: Action = ht!(l(2), k(H), HRM_TIMEOUT, HRM);
 /// HoldTap(Layer(2), KeyCode(T))
-const T2: Action = ht!(l(2), k(T));
+const T2: Action = ht!(l(2), k(T), HRM_TIMEOUT, HRM);
 /// HoldTap(Layer(3), KeyCode(A)),
-const A3: Action = ht!(l(3), k(A));
+const A3: Action = ht!(l(3), k(A), HRM_TIMEOUT, HRM);
 /// HoldTap(Layer(3), KeyCode(E))
-const E3: Action = ht!(l(3), k(E));
+const E3: Action = ht!(l(3), k(E), HRM_TIMEOUT, HRM);
 /// HoldTap(Layer(4), KeyCode(H))
-const H4: Action = ht!(l(4), k(H));
+const H4: Action = ht!(l(4), k(H), HRM_TIMEOUT, HRM);
 /// HoldTap(Layer(4), KeyCode(T))
-const T4: Action = ht!(l(4), k(T));
+const T4: Action = ht!(l(4), k(T), HRM_TIMEOUT, HRM);
 /// HoldTap(Layer(5), KeyCode(N))
-const N5: Action = ht!(l(5), k(N));
+const N5: Action = ht!(l(5), k(N), HRM_TIMEOUT, HRM);
 /// HoldTap(Layer(6), KeyCode(S))
-const S6: Action = ht!(l(6), k(S));
+const S6: Action = ht!(l(6), k(S), HRM_TIMEOUT, HRM);
 /// HoldTap(Layer(7), KeyCode(I)),
-const I7: Action = ht!(l(7), k(I));
+const I7: Action = ht!(l(7), k(I), HRM_TIMEOUT, HRM);
 /// HoldTap(Layer(7), KeyCode(O))
-const O7: Action = ht!(l(7), k(O));
+const O7: Action = ht!(l(7), k(O), HRM_TIMEOUT, HRM);
 /// HoldTap(Layer(8), KeyCode(Space))
 const Sp8: Action = ht!(l(8), k(Space));
 
@@ -103,9 +117,63 @@ const DL1: Action = d(1);
 /// DefaultLayer(2)
 const DL2: Action = d(2);
 
+/// Features beyond the layers
+pub static CONFIG: Config = Config {
+    key_overrides: &[
+        // Shift-Backspace is Delete
+        KeyOverride {
+            modifiers: &[LShift],
+            key: BSpace,
+            output: &[Delete],
+            layers: &[],
+        },
+    ],
+    alt_repeats: &[
+        // Next and previous tab
+        AltRepeat {
+            keys: &[LCtrl, Tab],
+            alt: &[LCtrl, LShift, Tab],
+        },
+        // Next and previous workspace
+        AltRepeat {
+            keys: &[LCtrl, LAlt, Up],
+            alt: &[LCtrl, LAlt, Down],
+        },
+    ],
+    // Layer 8 is always accessible: holding it on top of another layer held
+    // activates it, instead of adding up past the last layer
+    tri_layers: &[
+        TriLayer {
+            layers: &[2, 8],
+            layer: 8,
+        },
+        TriLayer {
+            layers: &[3, 8],
+            layer: 8,
+        },
+        TriLayer {
+            layers: &[4, 8],
+            layer: 8,
+        },
+        TriLayer {
+            layers: &[5, 8],
+            layer: 8,
+        },
+        TriLayer {
+            layers: &[6, 8],
+            layer: 8,
+        },
+        TriLayer {
+            layers: &[7, 8],
+            layer: 8,
+        },
+    ],
+    ..Config::DEFAULT
+};
+
 #[rustfmt::skip]
 /// Layout
-pub static LAYERS: keyberon::layout::Layers<10, 4, 9, Infallible> = keyberon::layout::layout! {
+pub static LAYERS: keyberon::layout::Layers<10, 4, 9, CustomAction> = keyberon::layout::layout! {
     { // 0: Base Layer
         [ Q     D     R     W       B          J     F       U      P      SColon],
         [{LSA} {S6}  {H2}  {T4}     G          Y    {N5}    {E3}   {O7}    {LSI} ],
@@ -117,10 +185,12 @@ pub static LAYERS: keyberon::layout::Layers<10, 4, 9, Infallible> = keyberon::la
         [ B    {LCF} {LAG}  D      V       Escape  L      {LADot} {LCBSp} K   ],
         [ n     n     n    {LCAUp} E       {Sp8}  {LCADn}  n       n      n   ],
     } { // 2
+        // Set with d(2): (3, 5) holds layer 8 itself, for d(0), since
+        // transparent keys do nothing on the default layer
         [t  {LCSC} t  {LCSV}    t          t   n      n  n  t],
         [t   n     n   n        t          t   n      n  n  n],
         [t   t     t   t        t          t   n      n  n  t],
-        [n   n     n  {LCSTab}  t          t  {LCTab} n  n  n],
+        [n   n     n  {LCSTab}  t        {Sp8} {LCTab} n  n  n],
     } { // 3: Navigation
         [t      t    PgUp     t     t      t   t    t   t      t    ],
         [Left  Up    Down    Right  t      t  LGui  n  {LCA}  {LCAS}],
@@ -138,18 +208,18 @@ pub static LAYERS: keyberon::layout::Layers<10, 4, 9, Infallible> = keyberon::la
         [ n   n   n   MediaVolDown  t      t  MediaVolUp n n n],
     } { // 6: Functions
         [t  t   t    t  t    t  F7  F8  F9  F10],
-        [t  t  {LCA} t  t    t  F4  F5  F6  F11],
+        [t  t  {LCA} n  t    t  F4  F5  F6  F11],
         [t  t   t    t  t    t  F1  F2  F3  F12],
         [n  n   n    t  t    t  t   n   n   n  ],
     } { // 7: Numbers
         [/ 7 8 9 +     t t t t t],
-        [0 1 2 3 -     t t t n t],
+        [0 1 2 3 -     t n n n t],
         [* 4 5 6 =     t t t t t],
         [n n n t t     t t n n n],
     } { // 8: Always accessible
-        [t Escape :  t    t  {DL2}  t    t      t    Delete],
-        [t  %     / Enter !  {DL1} LGui  t      t    t     ],
-        [t  t     t  t    t  {DL0}  t   {RACm} {RCD} n     ],
+        [t Escape :  t    t  {DL2}  t    t      t    t     ],
+        [t  %     / Enter !  {DL1} LGui  n      n    t     ],
+        [t  t     t {REPEAT} {ALT_REPEAT} {DL0} t {RACm} {RCD} n ],
         [n  n     n  t   Tab   n    t    n      n    n     ],
     }
 };
@@ -0,0 +1,12 @@
+#![allow(unused_imports)]
+use crate::action::{osl, osm, Action, CustomAction};
+use crate::config::Config;
+use keyberon::action::{d, k, l, m, HoldTapAction, HoldTapConfig};
+use keyberon::key_code::KeyCode::*;
+use keyberon::layout::Layout;
+
+// `KBLayout` and `LAYERS`, generated by the build script from the ZMK keymap
+include!(concat!(env!("OUT_DIR"), "/keymap_zmk.rs"));
+
+/// Features beyond the layers
+pub static CONFIG: Config = Config::DEFAULT;
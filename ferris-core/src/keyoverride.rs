@@ -0,0 +1,147 @@
+//! Key overrides: keys sending something else while modifiers are held
+//!
+//! A key override replaces a key, when pressed with some modifiers, by other
+//! keys, such as Delete for Shift-Backspace. While it applies, the key and
+//! the modifiers of the override are left out of the report, and its output
+//! is sent instead, with its own modifiers if any.
+//!
+//! Overrides are checked against the keys currently pressed, so releasing the
+//! modifier while the key is held sends the key again.
+
+use heapless::Vec;
+use keyberon::key_code::KeyCode::{self, *};
+
+/// Maximum number of overrides applying at once
+const ACTIVE: usize = 4;
+
+/// A key sending something else while modifiers are held
+pub struct KeyOverride {
+    /// Modifiers to hold, either the left or the right one
+    pub modifiers: &'static [KeyCode],
+    /// Key to press with the modifiers
+    pub key: KeyCode,
+    /// Keys sent instead
+    pub output: &'static [KeyCode],
+    /// Layers where the override is enabled, all of them if empty
+    pub layers: &'static [usize],
+}
+
+/// Left modifier of the same kind as `kc`, or `kc` itself
+fn left(kc: KeyCode) -> KeyCode {
+    match kc {
+        RCtrl => LCtrl,
+        RShift => LShift,
+        RAlt => LAlt,
+        RGui => LGui,
+        kc => kc,
+    }
+}
+
+impl KeyOverride {
+    /// Whether the override applies to `keycodes` on `layer`
+    fn applies(&self, layer: usize, keycodes: &[KeyCode]) -> bool {
+        (self.layers.is_empty() || self.layers.contains(&layer))
+            && keycodes.contains(&self.key)
+            && self
+                .modifiers
+                .iter()
+                .all(|&m| keycodes.iter().any(|&kc| left(kc) == left(m)))
+    }
+
+    /// Whether `kc` is replaced by the override
+    fn replaces(&self, kc: KeyCode) -> bool {
+        kc == self.key || self.modifiers.iter().any(|&m| left(m) == left(kc))
+    }
+}
+
+/// Apply to `keycodes`, pressed on `layer`, the `overrides` matching them
+pub fn apply<const LEN: usize>(
+    overrides: &'static [KeyOverride],
+    layer: usize,
+    keycodes: &mut Vec<KeyCode, LEN>,
+) {
+    let active: Vec<&KeyOverride, ACTIVE> = overrides
+        .iter()
+        .filter(|o| o.applies(layer, keycodes))
+        .take(ACTIVE)
+        .collect();
+    keycodes.retain(|&kc| !active.iter().any(|o| o.replaces(kc)));
+    for &kc in active.iter().flat_map(|o| o.output) {
+        if !keycodes.contains(&kc) {
+            keycodes.push(kc).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Layer of the tests without the override, like tmux
+    const TMUX: usize = 5;
+
+    /// Shift-Backspace is Delete, except on the tmux layer
+    static OVERRIDES: [KeyOverride; 1] = [KeyOverride {
+        modifiers: &[LShift],
+        key: BSpace,
+        output: &[Delete],
+        layers: &[0, 1, 2, 3, 4, 6, 7],
+    }];
+
+    /// Report of `keycodes` pressed on `layer`
+    fn report(layer: usize, keycodes: &[KeyCode]) -> Vec<KeyCode, 8> {
+        let mut keycodes = Vec::from_slice(keycodes).unwrap();
+        apply(&OVERRIDES, layer, &mut keycodes);
+        keycodes
+    }
+
+    #[test]
+    fn shift_backspace() {
+        for layer in [0, 1, 2, 3, 4, 6, 7] {
+            assert_eq!(report(layer, &[LShift, BSpace]), [Delete]);
+        }
+        // Either Shift
+        assert_eq!(report(0, &[BSpace, RShift]), [Delete]);
+    }
+
+    #[test]
+    fn tmux_layer() {
+        assert_eq!(report(TMUX, &[LShift, BSpace]), [LShift, BSpace]);
+    }
+
+    #[test]
+    fn without_shift() {
+        assert_eq!(report(0, &[BSpace]), [BSpace]);
+        assert_eq!(report(0, &[LCtrl, BSpace]), [LCtrl, BSpace]);
+    }
+
+    #[test]
+    fn other_modifiers_kept() {
+        assert_eq!(report(0, &[LCtrl, LShift, BSpace]), [LCtrl, Delete]);
+    }
+
+    #[test]
+    fn release() {
+        // Shift is sent again once Backspace is released
+        assert_eq!(report(0, &[LShift, BSpace]), [Delete]);
+        assert_eq!(report(0, &[LShift]), [LShift]);
+        assert_eq!(report(0, &[LShift, A]), [LShift, A]);
+        // Backspace is sent again once Shift is released
+        assert_eq!(report(0, &[BSpace]), [BSpace]);
+    }
+
+    #[test]
+    #[cfg(feature = "keymap_borisfaure")]
+    fn borisfaure() {
+        for layer in 0..crate::LAYERS.len() {
+            let mut keycodes: Vec<KeyCode, 8> = Vec::from_slice(&[LShift, BSpace]).unwrap();
+            apply(crate::CONFIG.key_overrides, layer, &mut keycodes);
+            let expected: &[KeyCode] = if layer == TMUX {
+                &[LShift, BSpace]
+            } else {
+                &[Delete]
+            };
+            assert_eq!(keycodes, expected, "layer {}", layer);
+        }
+    }
+}
@@ -0,0 +1,185 @@
+//! Layer lock: keeping a momentary layer after the release of its key
+//!
+//! Pressing the layer lock key while a layer is held, by `l(x)` or a hold-tap,
+//! makes that layer the default layer, so it stays active once the key holding
+//! it is released. Pressing the layer lock key again, or one of the exit keys
+//! of the keymap configuration, restores the previous default layer. The exit
+//! key is still typed from the locked layer.
+
+use crate::KBLayout;
+use heapless::Vec;
+use keyberon::key_code::KeyCode;
+
+/// Maximum number of keys tracked at once
+const KEYS: usize = 16;
+
+/// State of the layer lock
+pub struct LayerLock {
+    /// Keys unlocking the layer
+    exits: &'static [KeyCode],
+    /// Default layer to restore when the layer is unlocked, if one is locked
+    previous: Option<usize>,
+    /// Keys of the layout on the last tick
+    pressed: Vec<KeyCode, KEYS>,
+}
+
+impl LayerLock {
+    /// Create a new state, with no layer locked, unlocked by `exits`
+    pub fn new(exits: &'static [KeyCode]) -> Self {
+        Self {
+            exits,
+            previous: None,
+            pressed: Vec::new(),
+        }
+    }
+
+    /// Lock the current layer, or unlock the locked one, `default` being the
+    /// current default layer
+    ///
+    /// Nothing is locked if no layer is held above the default one.
+    pub fn toggle(&mut self, layout: &mut KBLayout, default: usize) {
+        match self.previous.take() {
+            Some(previous) => layout.set_default_layer(previous),
+            None => {
+                let current = layout.current_layer();
+                if current != default {
+                    self.previous = Some(default);
+                    layout.set_default_layer(current);
+                }
+            }
+        }
+    }
+
+    /// Unlock the layer on the press of an exit key
+    pub fn tick(&mut self, keycodes: impl Iterator<Item = KeyCode>, layout: &mut KBLayout) {
+        let keycodes: Vec<KeyCode, KEYS> = keycodes.collect();
+        if let Some(previous) = self.previous {
+            let exit = keycodes
+                .iter()
+                .any(|kc| !self.pressed.contains(kc) && self.exits.contains(kc));
+            if exit {
+                self.previous = None;
+                layout.set_default_layer(previous);
+            }
+        }
+        self.pressed = keycodes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::numword::NumWord;
+    use crate::LAYERS;
+    use keyberon::action::l;
+    use keyberon::key_code::KeyCode::*;
+    use keyberon::layout::{Event, Layout};
+    use std::boxed::Box;
+
+    /// Key holding the layer 2
+    const HOLD: (u8, u8) = (3, 3);
+    /// Layer held by `HOLD`
+    const LAYER: usize = 2;
+    /// Layer of Num Word
+    const NUMBERS: usize = 3;
+
+    /// Layout where `HOLD` holds `LAYER`
+    fn layout() -> KBLayout {
+        let mut layers = LAYERS;
+        layers[0][3][3] = l(LAYER);
+        Layout::new(Box::leak(Box::new(layers)))
+    }
+
+    /// Send `event` to `layout`
+    fn event(layout: &mut KBLayout, event: Event) {
+        layout.event(event);
+        layout.tick();
+    }
+
+    /// Hold `LAYER`, lock it, then release its key
+    fn lock(layer_lock: &mut LayerLock, layout: &mut KBLayout) {
+        event(layout, Event::Press(HOLD.0, HOLD.1));
+        assert_eq!(layout.current_layer(), LAYER);
+        layer_lock.toggle(layout, 0);
+        event(layout, Event::Release(HOLD.0, HOLD.1));
+    }
+
+    /// Tap the keys of `keycodes`, all at once
+    fn tap(layer_lock: &mut LayerLock, layout: &mut KBLayout, keycodes: &[KeyCode]) {
+        layer_lock.tick(keycodes.iter().copied(), layout);
+        layer_lock.tick([].into_iter(), layout);
+    }
+
+    #[test]
+    fn lock_and_unlock() {
+        let mut layout = layout();
+        let mut layer_lock = LayerLock::new(&[Escape]);
+        lock(&mut layer_lock, &mut layout);
+        assert_eq!(layout.current_layer(), LAYER);
+        tap(&mut layer_lock, &mut layout, &[A]);
+        assert_eq!(layout.current_layer(), LAYER);
+        layer_lock.toggle(&mut layout, LAYER);
+        assert_eq!(layout.current_layer(), 0);
+    }
+
+    #[test]
+    fn exit_key() {
+        let mut layout = layout();
+        let mut layer_lock = LayerLock::new(&[Escape]);
+        lock(&mut layer_lock, &mut layout);
+        tap(&mut layer_lock, &mut layout, &[Escape]);
+        assert_eq!(layout.current_layer(), 0);
+        // The next lock key locks again
+        lock(&mut layer_lock, &mut layout);
+        assert_eq!(layout.current_layer(), LAYER);
+    }
+
+    #[test]
+    fn exit_key_held_when_locking() {
+        let mut layout = layout();
+        let mut layer_lock = LayerLock::new(&[Escape]);
+        layer_lock.tick([Escape].into_iter(), &mut layout);
+        lock(&mut layer_lock, &mut layout);
+        layer_lock.tick([Escape].into_iter(), &mut layout);
+        assert_eq!(layout.current_layer(), LAYER);
+    }
+
+    #[test]
+    fn nothing_held() {
+        let mut layout = layout();
+        let mut layer_lock = LayerLock::new(&[Escape]);
+        layer_lock.toggle(&mut layout, 0);
+        assert_eq!(layout.current_layer(), 0);
+        // The next lock key locks, it does not unlock
+        lock(&mut layer_lock, &mut layout);
+        assert_eq!(layout.current_layer(), LAYER);
+    }
+
+    #[test]
+    fn num_word_in_locked_layer() {
+        let mut layout = layout();
+        let mut layer_lock = LayerLock::new(&[Escape]);
+        let mut num_word = NumWord::new(&[]);
+        lock(&mut layer_lock, &mut layout);
+        num_word.toggle(NUMBERS, &mut layout, LAYER);
+        assert_eq!(layout.current_layer(), NUMBERS);
+        num_word.tick([Kb1].into_iter(), &mut layout);
+        num_word.tick([Space].into_iter(), &mut layout);
+        // Back to the locked layer, still locked
+        assert_eq!(layout.current_layer(), LAYER);
+        layer_lock.toggle(&mut layout, LAYER);
+        assert_eq!(layout.current_layer(), 0);
+    }
+
+    #[test]
+    fn lock_during_num_word() {
+        // Num Word is a default layer, not a layer held to lock
+        let mut layout = layout();
+        let mut layer_lock = LayerLock::new(&[Escape]);
+        let mut num_word = NumWord::new(&[]);
+        num_word.toggle(NUMBERS, &mut layout, 0);
+        layer_lock.toggle(&mut layout, NUMBERS);
+        num_word.tick([Space].into_iter(), &mut layout);
+        assert_eq!(layout.current_layer(), 0);
+    }
+}
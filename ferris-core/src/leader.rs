@@ -0,0 +1,286 @@
+//! Leader key: keys typed after it are looked up in a dictionary
+//!
+//! After the leader key, keys are not sent but collected, until they match a
+//! sequence of the dictionary, which is then played. The lookup is done
+//! after each key:
+//!
+//! - when the keys match a sequence and no longer one starts with them, the
+//!   sequence is played right away;
+//! - when they match a sequence but longer ones start with them, the next
+//!   key decides, the match being played if the timeout expires first;
+//! - when no sequence starts with them, the leader is cancelled;
+//! - the leader is also cancelled by Escape, by the leader key itself, and
+//!   when the timeout expires after a key without any match.
+//!
+//! Modifiers are not collected and are sent as usual.
+
+use crate::sequence::{Sequence, Sequencer};
+use heapless::Vec;
+use keyberon::key_code::KeyCode;
+
+/// Maximum number of keys after the leader key
+const LENGTH: usize = 8;
+/// Maximum number of keys tracked at once
+const KEYS: usize = 16;
+
+/// An entry of the leader dictionary
+pub struct LeaderSequence {
+    /// Keys to type after the leader key
+    pub keys: &'static [KeyCode],
+    /// What is played when they are typed
+    pub sequence: Sequence,
+}
+
+/// Result of the lookup of keys in the leader dictionary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup {
+    /// No sequence starts with the keys
+    None,
+    /// Some sequences start with the keys, but none matches yet
+    Prefix,
+    /// The keys match a sequence, and no longer one starts with them
+    Match(Sequence),
+    /// The keys match a sequence, but longer ones start with them
+    Ambiguous(Sequence),
+}
+
+/// Look `keys` up in `dictionary`
+pub fn lookup(dictionary: &[LeaderSequence], keys: &[KeyCode]) -> Lookup {
+    let exact = dictionary.iter().find(|e| e.keys == keys);
+    let longer = dictionary
+        .iter()
+        .any(|e| e.keys.len() > keys.len() && e.keys.starts_with(keys));
+    match (exact, longer) {
+        (Some(entry), false) => Lookup::Match(entry.sequence),
+        (Some(entry), true) => Lookup::Ambiguous(entry.sequence),
+        (None, true) => Lookup::Prefix,
+        (None, false) => Lookup::None,
+    }
+}
+
+/// State of the leader key
+pub struct Leader {
+    /// Sequences that can be typed after the leader key
+    dictionary: &'static [LeaderSequence],
+    /// Milliseconds to type the next key
+    timeout: u16,
+    /// Whether keys are being collected
+    active: bool,
+    /// Keys typed since the leader key
+    keys: Vec<KeyCode, LENGTH>,
+    /// Milliseconds since the leader key or the last key
+    elapsed: u16,
+    /// Keys of the layout on the last tick
+    pressed: Vec<KeyCode, KEYS>,
+    /// Keys collected, not sent until they are released
+    collected: Vec<KeyCode, KEYS>,
+}
+
+impl Leader {
+    /// Create a new leader state, looking keys up in `dictionary`
+    pub fn new(dictionary: &'static [LeaderSequence], timeout: u16) -> Self {
+        Self {
+            dictionary,
+            timeout,
+            active: false,
+            keys: Vec::new(),
+            elapsed: 0,
+            pressed: Vec::new(),
+            collected: Vec::new(),
+        }
+    }
+
+    /// Handle the press of the leader key
+    pub fn start(&mut self) {
+        self.active = !self.active;
+        self.keys.clear();
+        self.elapsed = 0;
+    }
+
+    /// Collect the keys newly pressed by the layout and play the sequence
+    /// they match
+    pub fn tick(&mut self, keycodes: impl Iterator<Item = KeyCode>, sequencer: &mut Sequencer) {
+        let keycodes: Vec<KeyCode, KEYS> = keycodes.collect();
+        self.collected.retain(|kc| keycodes.contains(kc));
+        if self.active {
+            for &kc in keycodes
+                .iter()
+                .filter(|kc| !kc.is_modifier() && !self.pressed.contains(kc))
+            {
+                if !self.active {
+                    break;
+                }
+                self.collected.push(kc).ok();
+                self.elapsed = 0;
+                if kc == KeyCode::Escape || self.keys.push(kc).is_err() {
+                    self.active = false;
+                    break;
+                }
+                match lookup(self.dictionary, &self.keys) {
+                    Lookup::Match(sequence) => {
+                        sequencer.push(sequence);
+                        self.active = false;
+                    }
+                    Lookup::None => self.active = false,
+                    Lookup::Prefix | Lookup::Ambiguous(_) => {}
+                }
+            }
+        }
+        if self.active {
+            self.elapsed += 1;
+            if self.elapsed > self.timeout {
+                if let Lookup::Ambiguous(sequence) = lookup(self.dictionary, &self.keys) {
+                    sequencer.push(sequence);
+                }
+                self.active = false;
+            }
+        }
+        self.pressed = keycodes;
+    }
+
+    /// Whether `kc`, pressed by the layout, can be sent
+    pub fn is_sent(&self, kc: KeyCode) -> bool {
+        !self.collected.contains(&kc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keyberon::key_code::KeyCode::*;
+
+    /// Dictionary of the tests
+    static DICTIONARY: [LeaderSequence; 3] = [
+        LeaderSequence {
+            keys: &[G],
+            sequence: Sequence::Key(Kb1),
+        },
+        LeaderSequence {
+            keys: &[G, S],
+            sequence: Sequence::Key(Kb2),
+        },
+        LeaderSequence {
+            keys: &[D, A],
+            sequence: Sequence::Key(Kb3),
+        },
+    ];
+
+    /// Timeout of the tests
+    const TIMEOUT: u16 = 10;
+
+    /// Tap `keys` one after the other, ticking `leader` and `sequencer`
+    fn tap(leader: &mut Leader, sequencer: &mut Sequencer, keys: &[KeyCode]) {
+        for &kc in keys {
+            leader.tick([kc].into_iter(), sequencer);
+            leader.tick([].into_iter(), sequencer);
+        }
+    }
+
+    /// Keys played by `sequencer` on its next tick
+    fn played(sequencer: &mut Sequencer) -> Vec<KeyCode, KEYS> {
+        sequencer.tick();
+        sequencer.keycodes().collect()
+    }
+
+    #[test]
+    fn lookups() {
+        assert_eq!(lookup(&DICTIONARY, &[D]), Lookup::Prefix);
+        assert_eq!(
+            lookup(&DICTIONARY, &[D, A]),
+            Lookup::Match(Sequence::Key(Kb3))
+        );
+        assert_eq!(
+            lookup(&DICTIONARY, &[G]),
+            Lookup::Ambiguous(Sequence::Key(Kb1))
+        );
+        assert_eq!(
+            lookup(&DICTIONARY, &[G, S]),
+            Lookup::Match(Sequence::Key(Kb2))
+        );
+        assert_eq!(lookup(&DICTIONARY, &[D, S]), Lookup::None);
+        assert_eq!(lookup(&DICTIONARY, &[X]), Lookup::None);
+    }
+
+    #[test]
+    fn prefix() {
+        let mut leader = Leader::new(&DICTIONARY, TIMEOUT);
+        let mut sequencer = Sequencer::default();
+        leader.start();
+        leader.tick([D].into_iter(), &mut sequencer);
+        assert!(!leader.is_sent(D));
+        leader.tick([].into_iter(), &mut sequencer);
+        assert!(leader.active);
+        assert!(!sequencer.is_playing());
+    }
+
+    #[test]
+    fn full_match() {
+        let mut leader = Leader::new(&DICTIONARY, TIMEOUT);
+        let mut sequencer = Sequencer::default();
+        leader.start();
+        tap(&mut leader, &mut sequencer, &[D, A]);
+        assert!(!leader.active);
+        assert_eq!(played(&mut sequencer), [Kb3]);
+        // Keys are sent again once the leader is done
+        tap(&mut leader, &mut sequencer, &[D]);
+        assert!(leader.is_sent(D));
+    }
+
+    #[test]
+    fn ambiguous_match_decided_by_next_key() {
+        let mut leader = Leader::new(&DICTIONARY, TIMEOUT);
+        let mut sequencer = Sequencer::default();
+        leader.start();
+        tap(&mut leader, &mut sequencer, &[G]);
+        assert!(!sequencer.is_playing());
+        tap(&mut leader, &mut sequencer, &[S]);
+        assert_eq!(played(&mut sequencer), [Kb2]);
+    }
+
+    #[test]
+    fn mismatch() {
+        let mut leader = Leader::new(&DICTIONARY, TIMEOUT);
+        let mut sequencer = Sequencer::default();
+        leader.start();
+        tap(&mut leader, &mut sequencer, &[D, S]);
+        assert!(!leader.active);
+        assert!(!sequencer.is_playing());
+    }
+
+    #[test]
+    fn escape() {
+        let mut leader = Leader::new(&DICTIONARY, TIMEOUT);
+        let mut sequencer = Sequencer::default();
+        leader.start();
+        tap(&mut leader, &mut sequencer, &[D, Escape]);
+        assert!(!leader.active);
+        assert!(!sequencer.is_playing());
+    }
+
+    #[test]
+    fn timeout() {
+        let mut leader = Leader::new(&DICTIONARY, TIMEOUT);
+        let mut sequencer = Sequencer::default();
+        leader.start();
+        tap(&mut leader, &mut sequencer, &[G]);
+        for _ in 0..TIMEOUT {
+            leader.tick([].into_iter(), &mut sequencer);
+        }
+        // The shorter match is played once the timeout expires
+        assert!(!leader.active);
+        assert_eq!(played(&mut sequencer), [Kb1]);
+    }
+
+    #[test]
+    fn timeout_without_match() {
+        let mut leader = Leader::new(&DICTIONARY, TIMEOUT);
+        let mut sequencer = Sequencer::default();
+        leader.start();
+        tap(&mut leader, &mut sequencer, &[D]);
+        for _ in 0..TIMEOUT {
+            leader.tick([].into_iter(), &mut sequencer);
+        }
+        assert!(!leader.active);
+        assert!(!sequencer.is_playing());
+    }
+}
@@ -0,0 +1,77 @@
+#![no_std]
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+//! Hardware independent logic of the
+//! [Ferris keyboard](https://github.com/pierrechevalier83/ferris) firmware
+//!
+//! This crate merges the scans of both halves, routes the events to the
+//! layout, dispatches the custom actions and generates the HID reports. It
+//! does not depend on the MCU so it can be built and tested on the host.
+
+#[cfg(test)]
+extern crate std;
+
+pub mod action;
+pub mod autoshift;
+pub mod capsword;
+pub mod combo;
+pub mod config;
+pub mod dynamicmacro;
+pub mod holdtap;
+pub mod host;
+pub mod keyboard;
+pub mod keyoverride;
+pub mod layerlock;
+pub mod leader;
+pub mod longhold;
+pub mod numword;
+pub mod oneshot;
+pub mod prefix;
+pub mod repeat;
+pub mod scan;
+pub mod sequence;
+pub mod swaphands;
+pub mod tapdance;
+pub mod trilayer;
+pub mod unicode;
+
+#[cfg(not(any(
+    feature = "keymap_basic",
+    feature = "keymap_borisfaure",
+    feature = "keymap_pierrec83",
+    feature = "keymap_zmk",
+    feature = "keymap_kanata"
+)))]
+compile_error!("Either feature \"keymap_basic\" or \"keymap_borisfaure\" or \"keymap_pierrec83\" or \"keymap_zmk\" or \"keymap_kanata\" must be enabled.");
+
+/// Basic layout for the keyboard
+#[cfg(feature = "keymap_basic")]
+mod keymap_basic;
+#[cfg(feature = "keymap_basic")]
+pub use keymap_basic::{KBLayout, CONFIG, LAYERS};
+
+/// Keymap by Boris Faure
+#[cfg(feature = "keymap_borisfaure")]
+mod keymap_borisfaure;
+#[cfg(feature = "keymap_borisfaure")]
+pub use keymap_borisfaure::{KBLayout, CONFIG, LAYERS};
+
+/// Keymap by @pierrec83
+#[cfg(feature = "keymap_pierrec83")]
+#[allow(non_upper_case_globals)]
+mod keymap_pierrec83;
+#[cfg(feature = "keymap_pierrec83")]
+pub use keymap_pierrec83::{KBLayout, CONFIG, LAYERS};
+
+/// Keymap imported from a ZMK `.keymap` file
+#[cfg(feature = "keymap_zmk")]
+mod keymap_zmk;
+#[cfg(feature = "keymap_zmk")]
+pub use keymap_zmk::{KBLayout, CONFIG, LAYERS};
+
+/// Keymap imported from a Kanata configuration
+#[cfg(feature = "keymap_kanata")]
+mod keymap_kanata;
+#[cfg(feature = "keymap_kanata")]
+pub use keymap_kanata::{KBLayout, CONFIG, LAYERS};
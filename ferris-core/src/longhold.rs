@@ -0,0 +1,212 @@
+//! Long holds: a third action on keys, after their tap and their hold
+//!
+//! A long hold key is left to the layout, usually as a hold-tap deciding
+//! between its tap and its hold. When it is released after being held for
+//! the timeout of the long hold with no other key pressed, the hold was not
+//! used: the layout sees the release, then a tap of another position of the
+//! matrix, one that is not wired, so its action can be anything. The action
+//! used is the one of the layer active once the key is released.
+//!
+//! Pressing another key while the long hold key is held uses its hold, such
+//! as a layer, and cancels the long hold, however long the key was held
+//! before. The timeout of the long hold should be longer than the one of the
+//! hold-tap, so the hold is decided first.
+
+use heapless::{Deque, Vec};
+use keyberon::layout::Event;
+
+/// Maximum number of long hold keys held at once
+const HELD: usize = 4;
+/// Maximum number of events waiting for the layout
+const OUTPUT: usize = 16;
+
+/// A key doing something else when held long enough
+pub struct LongHold {
+    /// Position of the key
+    pub key: (u8, u8),
+    /// Position pressed instead once the key is held for `timeout`
+    pub long: (u8, u8),
+    /// Milliseconds to hold the key for the long hold
+    pub timeout: u16,
+    /// Layers where the long hold is enabled, all of them if empty
+    pub layers: &'static [usize],
+}
+
+/// A long hold key held
+struct Held {
+    /// The long hold
+    long_hold: &'static LongHold,
+    /// Milliseconds since the press of the key, up to the timeout
+    elapsed: u16,
+}
+
+/// Detection of the long holds in the key events
+pub struct LongHolds {
+    /// Long holds of the keymap
+    long_holds: &'static [LongHold],
+    /// Long hold keys held
+    held: Vec<Held, HELD>,
+    /// Events for the layout
+    output: Deque<Event, OUTPUT>,
+}
+
+impl LongHolds {
+    /// Create the detection of `long_holds`
+    pub fn new(long_holds: &'static [LongHold]) -> Self {
+        Self {
+            long_holds,
+            held: Vec::new(),
+            output: Deque::new(),
+        }
+    }
+
+    /// Handle a key event, `layer` being the current layer
+    pub fn event(&mut self, event: Event, layer: usize) {
+        let key = event.coord();
+        if event.is_release() {
+            self.output.push_back(event).ok();
+            if let Some(index) = self.held.iter().position(|h| h.long_hold.key == key) {
+                let held = self.held.swap_remove(index);
+                if held.elapsed >= held.long_hold.timeout {
+                    let (i, j) = held.long_hold.long;
+                    self.output.push_back(Event::Press(i, j)).ok();
+                    self.output.push_back(Event::Release(i, j)).ok();
+                }
+            }
+            return;
+        }
+        // Another key uses the hold of the keys held
+        self.held.clear();
+        let long_hold = self
+            .long_holds
+            .iter()
+            .find(|l| l.key == key && (l.layers.is_empty() || l.layers.contains(&layer)));
+        if let Some(long_hold) = long_hold {
+            let held = Held {
+                long_hold,
+                elapsed: 0,
+            };
+            self.held.push(held).ok();
+        }
+        self.output.push_back(event).ok();
+    }
+
+    /// Advance the timeout of the keys held by one millisecond
+    pub fn tick(&mut self) {
+        for held in &mut self.held {
+            held.elapsed = held.elapsed.saturating_add(1).min(held.long_hold.timeout);
+        }
+    }
+
+    /// Next event for the layout
+    pub fn pop(&mut self) -> Option<Event> {
+        self.output.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Long hold of the tests: `(0, 4)` held long taps `(3, 0)` on the layer 0
+    static LONG_HOLDS: [LongHold; 1] = [LongHold {
+        key: (0, 4),
+        long: (3, 0),
+        timeout: 10,
+        layers: &[0],
+    }];
+
+    /// Events sent to the layout so far
+    fn output(long_holds: &mut LongHolds) -> Vec<Event, OUTPUT> {
+        core::iter::from_fn(|| long_holds.pop()).collect()
+    }
+
+    /// Tick `long_holds` for `ms` milliseconds
+    fn wait(long_holds: &mut LongHolds, ms: u16) {
+        for _ in 0..ms {
+            long_holds.tick();
+        }
+    }
+
+    #[test]
+    fn tap() {
+        let mut long_holds = LongHolds::new(&LONG_HOLDS);
+        long_holds.event(Event::Press(0, 4), 0);
+        wait(&mut long_holds, 2);
+        long_holds.event(Event::Release(0, 4), 0);
+        assert_eq!(
+            output(&mut long_holds),
+            [Event::Press(0, 4), Event::Release(0, 4)]
+        );
+    }
+
+    #[test]
+    fn hold() {
+        let mut long_holds = LongHolds::new(&LONG_HOLDS);
+        long_holds.event(Event::Press(0, 4), 0);
+        wait(&mut long_holds, 5);
+        long_holds.event(Event::Press(0, 0), 0);
+        long_holds.event(Event::Release(0, 0), 0);
+        long_holds.event(Event::Release(0, 4), 0);
+        assert_eq!(
+            output(&mut long_holds),
+            [
+                Event::Press(0, 4),
+                Event::Press(0, 0),
+                Event::Release(0, 0),
+                Event::Release(0, 4)
+            ]
+        );
+    }
+
+    #[test]
+    fn long_hold() {
+        let mut long_holds = LongHolds::new(&LONG_HOLDS);
+        long_holds.event(Event::Press(0, 4), 0);
+        wait(&mut long_holds, 100);
+        assert_eq!(output(&mut long_holds), [Event::Press(0, 4)]);
+        long_holds.event(Event::Release(0, 4), 0);
+        assert_eq!(
+            output(&mut long_holds),
+            [
+                Event::Release(0, 4),
+                Event::Press(3, 0),
+                Event::Release(3, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn long_hold_cancelled() {
+        // The hold is used after the timeout, such as a layer held while
+        // looking for a key
+        let mut long_holds = LongHolds::new(&LONG_HOLDS);
+        long_holds.event(Event::Press(0, 4), 0);
+        wait(&mut long_holds, 100);
+        long_holds.event(Event::Press(0, 0), 4);
+        long_holds.event(Event::Release(0, 0), 4);
+        wait(&mut long_holds, 100);
+        long_holds.event(Event::Release(0, 4), 4);
+        assert_eq!(
+            output(&mut long_holds),
+            [
+                Event::Press(0, 4),
+                Event::Press(0, 0),
+                Event::Release(0, 0),
+                Event::Release(0, 4)
+            ]
+        );
+    }
+
+    #[test]
+    fn disabled_layer() {
+        let mut long_holds = LongHolds::new(&LONG_HOLDS);
+        long_holds.event(Event::Press(0, 4), 1);
+        wait(&mut long_holds, 100);
+        long_holds.event(Event::Release(0, 4), 1);
+        assert_eq!(
+            output(&mut long_holds),
+            [Event::Press(0, 4), Event::Release(0, 4)]
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! Num Word: a layer kept while typing a number
+//!
+//! Num Word activates a layer, usually one with digits, as default layer.
+//! Digits, Backspace, Delete and the keys listed in the keymap configuration,
+//! such as operators, keep it, any other key turns it off, restoring the
+//! previous default layer. That key is still typed from the Num Word layer.
+//! Modifiers are ignored, so shifted operators can be listed by their
+//! unshifted key.
+
+use crate::KBLayout;
+use heapless::Vec;
+use keyberon::key_code::KeyCode::{self, *};
+
+/// Maximum number of keys tracked at once
+const KEYS: usize = 16;
+
+/// Whether `kc` types a digit, on the main block or on the keypad
+fn is_digit(kc: KeyCode) -> bool {
+    (Kb1 <= kc && kc <= Kb0) || (Kp1 <= kc && kc <= Kp0)
+}
+
+/// State of Num Word
+pub struct NumWord {
+    /// Keys, besides digits, keeping Num Word on
+    keys: &'static [KeyCode],
+    /// Default layer to restore when Num Word turns off, if it is on
+    previous: Option<usize>,
+    /// Keys of the layout on the last tick
+    pressed: Vec<KeyCode, KEYS>,
+}
+
+impl NumWord {
+    /// Create a new Num Word state, turned off, kept on by digits and `keys`
+    pub fn new(keys: &'static [KeyCode]) -> Self {
+        Self {
+            keys,
+            previous: None,
+            pressed: Vec::new(),
+        }
+    }
+
+    /// Whether `kc` keeps Num Word on
+    fn is_word(&self, kc: KeyCode) -> bool {
+        is_digit(kc) || matches!(kc, BSpace | Delete) || self.keys.contains(&kc)
+    }
+
+    /// Turn Num Word on with `layer`, or off if it was on, `default` being
+    /// the current default layer
+    pub fn toggle(&mut self, layer: usize, layout: &mut KBLayout, default: usize) {
+        match self.previous.take() {
+            Some(previous) => layout.set_default_layer(previous),
+            None => {
+                self.previous = Some(default);
+                layout.set_default_layer(layer);
+            }
+        }
+    }
+
+    /// Turn Num Word off on keys that are not part of a number
+    pub fn tick(&mut self, keycodes: impl Iterator<Item = KeyCode>, layout: &mut KBLayout) {
+        let keycodes: Vec<KeyCode, KEYS> = keycodes.collect();
+        if let Some(previous) = self.previous {
+            let end = keycodes
+                .iter()
+                .filter(|kc| !kc.is_modifier() && !self.pressed.contains(kc))
+                .any(|&kc| !self.is_word(kc));
+            if end {
+                self.previous = None;
+                layout.set_default_layer(previous);
+            }
+        }
+        self.pressed = keycodes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LAYERS;
+    use keyberon::layout::Layout;
+
+    /// Layer of Num Word in the tests
+    const NUMBERS: usize = 3;
+
+    /// Num Word kept on by the operators, turned on from the layer 1, and
+    /// its layout
+    fn num_word() -> (NumWord, KBLayout) {
+        let mut num_word = NumWord::new(&[Dot, Comma, Minus, Equal]);
+        let mut layout = Layout::new(&LAYERS);
+        layout.set_default_layer(1);
+        num_word.toggle(NUMBERS, &mut layout, 1);
+        (num_word, layout)
+    }
+
+    /// Tap the keys of `keycodes`, all at once
+    fn tap(num_word: &mut NumWord, layout: &mut KBLayout, keycodes: &[KeyCode]) {
+        num_word.tick(keycodes.iter().copied(), layout);
+        num_word.tick([].into_iter(), layout);
+    }
+
+    #[test]
+    fn digits() {
+        let (mut num_word, mut layout) = num_word();
+        assert_eq!(layout.current_layer(), NUMBERS);
+        for keycodes in [&[Kb1][..], &[Kb0], &[Kp5], &[BSpace], &[Delete]] {
+            tap(&mut num_word, &mut layout, keycodes);
+            assert_eq!(layout.current_layer(), NUMBERS);
+        }
+    }
+
+    #[test]
+    fn listed_keys() {
+        let (mut num_word, mut layout) = num_word();
+        // Modifiers are ignored, `+` is kept on as Shift and `=`
+        for keycodes in [&[Dot][..], &[Comma], &[Minus], &[LShift, Equal]] {
+            tap(&mut num_word, &mut layout, keycodes);
+            assert_eq!(layout.current_layer(), NUMBERS);
+        }
+    }
+
+    #[test]
+    fn other_keys() {
+        for kc in [Space, Enter, A, Slash] {
+            let (mut num_word, mut layout) = num_word();
+            tap(&mut num_word, &mut layout, &[Kb1]);
+            num_word.tick([kc].into_iter(), &mut layout);
+            // Back to the previous default layer
+            assert_eq!(layout.current_layer(), 1);
+            // Typing digits again does not turn it back on
+            tap(&mut num_word, &mut layout, &[Kb1]);
+            assert_eq!(layout.current_layer(), 1);
+        }
+    }
+
+    #[test]
+    fn key_held_when_turned_on() {
+        let mut layout = Layout::new(&LAYERS);
+        let mut num_word = NumWord::new(&[]);
+        num_word.tick([A].into_iter(), &mut layout);
+        num_word.toggle(NUMBERS, &mut layout, 0);
+        // A was pressed before, it does not end the number
+        num_word.tick([A, Kb2].into_iter(), &mut layout);
+        assert_eq!(layout.current_layer(), NUMBERS);
+    }
+
+    #[test]
+    fn toggle() {
+        let (mut num_word, mut layout) = num_word();
+        num_word.toggle(NUMBERS, &mut layout, NUMBERS);
+        assert_eq!(layout.current_layer(), 1);
+        tap(&mut num_word, &mut layout, &[A]);
+        assert_eq!(layout.current_layer(), 1);
+    }
+}
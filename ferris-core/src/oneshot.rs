@@ -0,0 +1,379 @@
+//! One-shot modifiers and layers
+//!
+//! A one-shot modifier is added to the next key pressed, until that key is
+//! released. A one-shot layer is used for the next key pressed. Several
+//! one-shot modifiers stack, and can be combined with a one-shot layer.
+//!
+//! Held while another key is pressed, a one-shot key acts as a regular
+//! modifier or momentary layer. Tapped twice, it is locked until tapped
+//! again. It is cancelled if no key is pressed before its timeout.
+//!
+//! One-shot layers are activated as default layer, so their transparent keys
+//! do nothing.
+
+use crate::KBLayout;
+use heapless::Vec;
+use keyberon::key_code::KeyCode;
+
+/// Maximum number of one-shot actions armed at once
+const ARMED: usize = 8;
+/// Maximum number of keys tracked at once
+const KEYS: usize = 16;
+
+/// What a one-shot action applies to the next key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OneShotTarget {
+    /// A modifier, such as `LShift`
+    Modifier(KeyCode),
+    /// A layer
+    Layer(usize),
+}
+
+/// A one-shot action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OneShot {
+    /// What is applied to the next key
+    pub target: OneShotTarget,
+    /// Milliseconds after its release before the action is cancelled
+    pub timeout: u16,
+}
+
+/// A one-shot action waiting for the next key
+struct Armed {
+    /// The action
+    one_shot: OneShot,
+    /// Milliseconds since its release
+    elapsed: u16,
+    /// Whether its key is still held
+    held: bool,
+    /// Whether another key was pressed while its key was held
+    used: bool,
+    /// Whether it was tapped twice, to keep it until tapped again
+    locked: bool,
+    /// Key using the modifier, until its release
+    until: Option<KeyCode>,
+}
+
+/// State of the one-shot actions
+pub struct OneShots {
+    /// One-shot actions waiting for the next key, or applied to it
+    armed: Vec<Armed, ARMED>,
+    /// Keys of the layout on the last tick
+    pressed: Vec<KeyCode, KEYS>,
+    /// Whether a key was pressed since the last tick
+    key_pressed: bool,
+}
+
+impl OneShots {
+    /// Create a new state, without any one-shot action armed
+    pub fn new() -> Self {
+        Self {
+            armed: Vec::new(),
+            pressed: Vec::new(),
+            key_pressed: false,
+        }
+    }
+
+    /// Remove the armed action at `index`, restoring the `default` layer if
+    /// it was a layer
+    fn cancel(&mut self, index: usize, layout: &mut KBLayout, default: usize) {
+        let armed = self.armed.swap_remove(index);
+        if let OneShotTarget::Layer(_) = armed.one_shot.target {
+            layout.set_default_layer(default);
+        }
+    }
+
+    /// Handle the press of a one-shot key
+    pub fn press(&mut self, one_shot: OneShot, layout: &mut KBLayout, default: usize) {
+        let target = one_shot.target;
+        if let Some(index) = self.armed.iter().position(|a| a.one_shot.target == target) {
+            let armed = &mut self.armed[index];
+            if armed.locked || armed.until.is_some() {
+                self.cancel(index, layout, default);
+            } else {
+                armed.locked = true;
+                armed.held = true;
+            }
+            return;
+        }
+        if let OneShotTarget::Layer(layer) = target {
+            // Only one one-shot layer at once
+            self.armed
+                .retain(|a| !matches!(a.one_shot.target, OneShotTarget::Layer(_)));
+            layout.set_default_layer(layer);
+        }
+        self.armed
+            .push(Armed {
+                one_shot,
+                elapsed: 0,
+                held: true,
+                used: false,
+                locked: false,
+                until: None,
+            })
+            .ok();
+    }
+
+    /// Handle the release of a one-shot key
+    pub fn release(&mut self, one_shot: OneShot, layout: &mut KBLayout, default: usize) {
+        let target = one_shot.target;
+        if let Some(index) = self.armed.iter().position(|a| a.one_shot.target == target) {
+            let armed = &mut self.armed[index];
+            armed.held = false;
+            if armed.used && !armed.locked {
+                self.cancel(index, layout, default);
+            }
+        }
+    }
+
+    /// Record that a key was pressed
+    pub fn key_pressed(&mut self) {
+        self.key_pressed = true;
+    }
+
+    /// Apply the armed actions to the keys newly pressed, and cancel the
+    /// expired ones
+    ///
+    /// `one_shot_pressed` tells whether the key pressed since the last tick
+    /// was a one-shot key, which does not use the one-shot layer.
+    pub fn tick(
+        &mut self,
+        one_shot_pressed: bool,
+        keycodes: impl Iterator<Item = KeyCode>,
+        layout: &mut KBLayout,
+        default: usize,
+    ) {
+        let keycodes: Vec<KeyCode, KEYS> = keycodes.collect();
+        let new_key = keycodes
+            .iter()
+            .copied()
+            .find(|kc| !kc.is_modifier() && !self.pressed.contains(kc));
+        let key_pressed = self.key_pressed && !one_shot_pressed;
+        let mut index = self.armed.len();
+        while index > 0 {
+            index -= 1;
+            let armed = &mut self.armed[index];
+            let mut done = false;
+            match armed.one_shot.target {
+                OneShotTarget::Modifier(_) => match (armed.until, new_key) {
+                    (Some(kc), _) => done = !keycodes.contains(&kc),
+                    (None, Some(_)) if !armed.locked && armed.held => armed.used = true,
+                    (None, Some(kc)) if !armed.locked => armed.until = Some(kc),
+                    _ => {}
+                },
+                OneShotTarget::Layer(_) if key_pressed && !armed.locked => {
+                    if armed.held {
+                        armed.used = true;
+                    } else {
+                        done = true;
+                    }
+                }
+                OneShotTarget::Layer(_) => {}
+            }
+            if !armed.held && !armed.locked && armed.until.is_none() {
+                armed.elapsed = armed.elapsed.saturating_add(1);
+                done |= armed.elapsed > armed.one_shot.timeout;
+            }
+            if done {
+                self.cancel(index, layout, default);
+            }
+        }
+        self.key_pressed = false;
+        self.pressed = keycodes;
+    }
+
+    /// Whether a one-shot layer is active
+    pub fn has_layer(&self) -> bool {
+        self.armed
+            .iter()
+            .any(|a| matches!(a.one_shot.target, OneShotTarget::Layer(_)))
+    }
+
+    /// Modifiers to add to the report
+    pub fn modifiers(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.armed.iter().filter_map(|a| match a.one_shot.target {
+            OneShotTarget::Modifier(kc) => Some(kc),
+            OneShotTarget::Layer(_) => None,
+        })
+    }
+}
+
+impl Default for OneShots {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LAYERS;
+    use keyberon::key_code::KeyCode::*;
+    use keyberon::layout::Layout;
+
+    /// Milliseconds before a one-shot action is cancelled
+    const TIMEOUT: u16 = 10;
+    /// One-shot Shift
+    const SHIFT: OneShot = OneShot {
+        target: OneShotTarget::Modifier(LShift),
+        timeout: TIMEOUT,
+    };
+    /// One-shot Control
+    const CTRL: OneShot = OneShot {
+        target: OneShotTarget::Modifier(LCtrl),
+        timeout: TIMEOUT,
+    };
+    /// One-shot layer 1
+    const LAYER: OneShot = OneShot {
+        target: OneShotTarget::Layer(1),
+        timeout: TIMEOUT,
+    };
+
+    /// One-shot actions and the layout, ticked the way the keyboard does
+    struct Keys(OneShots, KBLayout);
+
+    impl Keys {
+        /// Create the state, with nothing armed
+        fn new() -> Self {
+            Self(OneShots::new(), Layout::new(&LAYERS))
+        }
+
+        /// Press the key of `one_shot`
+        fn press(&mut self, one_shot: OneShot) {
+            self.0.key_pressed();
+            self.0.press(one_shot, &mut self.1, 0);
+            self.0.tick(true, [].into_iter(), &mut self.1, 0);
+        }
+
+        /// Release the key of `one_shot`
+        fn release(&mut self, one_shot: OneShot) {
+            self.0.release(one_shot, &mut self.1, 0);
+            self.0.tick(false, [].into_iter(), &mut self.1, 0);
+        }
+
+        /// Tap the key of `one_shot`
+        fn tap(&mut self, one_shot: OneShot) {
+            self.press(one_shot);
+            self.release(one_shot);
+        }
+
+        /// Tick with `keycodes` pressed by the layout, `pressed` telling
+        /// whether a key was pressed since the last tick
+        fn tick(&mut self, pressed: bool, keycodes: &[KeyCode]) {
+            if pressed {
+                self.0.key_pressed();
+            }
+            self.0.tick(false, keycodes.iter().copied(), &mut self.1, 0);
+        }
+
+        /// Tap `kc`, returning the modifiers added to it
+        fn tap_key(&mut self, kc: KeyCode) -> heapless::Vec<KeyCode, ARMED> {
+            self.tick(true, &[kc]);
+            let modifiers = self.0.modifiers().collect();
+            self.tick(false, &[]);
+            modifiers
+        }
+    }
+
+    #[test]
+    fn modifier_for_the_next_key_only() {
+        let mut keys = Keys::new();
+        keys.tap(SHIFT);
+        assert_eq!(keys.tap_key(A), [LShift]);
+        assert_eq!(keys.tap_key(B), []);
+    }
+
+    #[test]
+    fn modifier_until_the_key_is_released() {
+        let mut keys = Keys::new();
+        keys.tap(SHIFT);
+        keys.tick(true, &[A]);
+        keys.tick(false, &[A]);
+        // Modifiers pressed by the layout do not use it
+        keys.tick(true, &[A, LCtrl]);
+        assert!(keys.0.modifiers().eq([LShift]));
+        keys.tick(false, &[]);
+        assert_eq!(keys.0.modifiers().next(), None);
+    }
+
+    #[test]
+    fn held_modifier() {
+        let mut keys = Keys::new();
+        keys.press(SHIFT);
+        assert_eq!(keys.tap_key(A), [LShift]);
+        assert_eq!(keys.tap_key(B), [LShift]);
+        keys.release(SHIFT);
+        assert_eq!(keys.tap_key(C), []);
+    }
+
+    #[test]
+    fn layer() {
+        let mut keys = Keys::new();
+        keys.tap(LAYER);
+        assert_eq!(keys.1.current_layer(), 1);
+        assert!(keys.0.has_layer());
+        keys.tick(true, &[A]);
+        assert_eq!(keys.1.current_layer(), 0);
+        assert!(!keys.0.has_layer());
+    }
+
+    #[test]
+    fn held_layer() {
+        let mut keys = Keys::new();
+        keys.press(LAYER);
+        keys.tap_key(A);
+        assert_eq!(keys.1.current_layer(), 1);
+        keys.release(LAYER);
+        assert_eq!(keys.1.current_layer(), 0);
+    }
+
+    #[test]
+    fn chained_modifiers() {
+        let mut keys = Keys::new();
+        keys.tap(SHIFT);
+        keys.tap(CTRL);
+        assert_eq!(keys.tap_key(A), [LShift, LCtrl]);
+        assert_eq!(keys.tap_key(B), []);
+    }
+
+    #[test]
+    fn modifier_and_layer() {
+        let mut keys = Keys::new();
+        keys.tap(SHIFT);
+        keys.tap(LAYER);
+        // The one-shot layer does not use the one-shot modifier
+        assert!(keys.0.modifiers().eq([LShift]));
+        assert_eq!(keys.1.current_layer(), 1);
+        assert_eq!(keys.tap_key(A), [LShift]);
+        assert_eq!(keys.1.current_layer(), 0);
+        assert_eq!(keys.tap_key(B), []);
+    }
+
+    #[test]
+    fn locked() {
+        let mut keys = Keys::new();
+        keys.tap(SHIFT);
+        keys.tap(SHIFT);
+        assert_eq!(keys.tap_key(A), [LShift]);
+        assert_eq!(keys.tap_key(B), [LShift]);
+        keys.tap(SHIFT);
+        assert_eq!(keys.tap_key(C), []);
+    }
+
+    #[test]
+    fn timeout() {
+        for one_shot in [SHIFT, LAYER] {
+            let mut keys = Keys::new();
+            keys.tap(one_shot);
+            for _ in 0..TIMEOUT - 1 {
+                keys.tick(false, &[]);
+            }
+            assert!(keys.0.modifiers().next().is_some() || keys.0.has_layer());
+            keys.tick(false, &[]);
+            assert_eq!(keys.0.modifiers().next(), None);
+            assert!(!keys.0.has_layer());
+            assert_eq!(keys.1.current_layer(), 0);
+            assert_eq!(keys.tap_key(A), []);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Layers sending a prefix before each key
+//!
+//! Useful for tmux: with Ctrl-B as prefix, every command becomes a single
+//! keystroke. Keys newly pressed on such a layer are held back while the
+//! prefix is played by the [`Sequencer`], then sent. Modifiers are sent
+//! right away, without prefix.
+
+use crate::sequence::{Sequence, SequenceEvent, Sequencer};
+use heapless::Vec;
+use keyberon::key_code::KeyCode;
+
+/// Maximum number of keys tracked at once
+const KEYS: usize = 16;
+
+/// A layer sending a prefix before each key
+pub struct PrefixLayer {
+    /// Index of the layer
+    pub layer: usize,
+    /// Steps played before each key, such as
+    /// `&[Press(LCtrl), Tap(B), Release(LCtrl)]`
+    pub prefix: &'static [SequenceEvent],
+}
+
+/// Sends the prefix of the current layer before the keys pressed on it
+pub struct Prefixer {
+    /// Layers with a prefix
+    layers: &'static [PrefixLayer],
+    /// Keys of the layout on the last tick
+    pressed: Vec<KeyCode, KEYS>,
+    /// Keys held back until their prefix is played
+    held: Vec<KeyCode, KEYS>,
+}
+
+impl Prefixer {
+    /// Create a new prefixer for `layers`
+    pub fn new(layers: &'static [PrefixLayer]) -> Self {
+        Self {
+            layers,
+            pressed: Vec::new(),
+            held: Vec::new(),
+        }
+    }
+
+    /// Look for keys newly pressed on `layer` by the layout, and queue the
+    /// prefix of the layer before them
+    pub fn tick(
+        &mut self,
+        layer: usize,
+        keycodes: impl Iterator<Item = KeyCode>,
+        sequencer: &mut Sequencer,
+    ) {
+        let keycodes: Vec<KeyCode, KEYS> = keycodes.collect();
+        // Keys released before their prefix was played are tapped after it
+        for &kc in self.held.iter().filter(|kc| !keycodes.contains(kc)) {
+            sequencer.push(Sequence::Key(kc));
+        }
+        self.held.retain(|kc| keycodes.contains(kc));
+        if !sequencer.is_playing() {
+            self.held.clear();
+        }
+        if let Some(prefix_layer) = self.layers.iter().find(|p| p.layer == layer) {
+            for &kc in keycodes
+                .iter()
+                .filter(|kc| !kc.is_modifier() && !self.pressed.contains(kc))
+            {
+                sequencer.push(Sequence::Events(prefix_layer.prefix));
+                self.held.push(kc).ok();
+            }
+        }
+        self.pressed = keycodes;
+    }
+
+    /// Whether `kc`, pressed by the layout, can be sent
+    pub fn is_sent(&self, kc: KeyCode) -> bool {
+        !self.held.contains(&kc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::SequenceEvent::{Press, Release, Tap};
+    use keyberon::key_code::KeyCode::*;
+    use std::vec::Vec;
+
+    /// Layer of the tests sending Ctrl-B before each key
+    const TMUX: usize = 5;
+
+    /// Ctrl-B before the keys of the layer 5
+    static LAYERS: [PrefixLayer; 1] = [PrefixLayer {
+        layer: TMUX,
+        prefix: &[Press(LCtrl), Tap(B), Release(LCtrl)],
+    }];
+
+    /// Reports of the prefix sent before a key
+    const PREFIX: [&[KeyCode]; 5] = [&[LCtrl], &[LCtrl, B], &[LCtrl], &[], &[]];
+
+    /// Prefixer and sequencer, ticked the way the keyboard does
+    struct Keys(Prefixer, Sequencer);
+
+    impl Keys {
+        /// Create a prefixer for `LAYERS`
+        fn new() -> Self {
+            Self(Prefixer::new(&LAYERS), Sequencer::default())
+        }
+
+        /// Keys sent on each tick for `ticks` ticks, `keycodes` being
+        /// pressed by the layout on `layer`
+        fn tick(&mut self, layer: usize, keycodes: &[KeyCode], ticks: usize) -> Vec<Vec<KeyCode>> {
+            (0..ticks)
+                .map(|_| {
+                    let Self(prefixer, sequencer) = self;
+                    prefixer.tick(layer, keycodes.iter().copied(), sequencer);
+                    sequencer.tick();
+                    keycodes
+                        .iter()
+                        .copied()
+                        .filter(|&kc| prefixer.is_sent(kc))
+                        .chain(sequencer.keycodes())
+                        .collect()
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn prefix_before_each_key() {
+        let mut keys = Keys::new();
+        let mut expected: Vec<&[KeyCode]> = PREFIX.to_vec();
+        expected.extend([&[C][..], &[C]]);
+        assert_eq!(keys.tick(TMUX, &[C], 7), expected);
+        assert_eq!(keys.tick(TMUX, &[], 1), [&[][..]]);
+        let mut expected: Vec<&[KeyCode]> = PREFIX.to_vec();
+        expected.push(&[N]);
+        assert_eq!(keys.tick(TMUX, &[N], 6), expected);
+    }
+
+    #[test]
+    fn key_held_with_the_next_one() {
+        let mut keys = Keys::new();
+        keys.tick(TMUX, &[C], 7);
+        // Only the new key waits for a prefix
+        let expected: Vec<&[KeyCode]> = PREFIX.iter().map(|_| &[C][..]).collect();
+        let reports = keys.tick(TMUX, &[C, N], 5);
+        assert_eq!(
+            reports.iter().map(|r| &r[..1]).collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(keys.tick(TMUX, &[C, N], 1), [&[C, N][..]]);
+    }
+
+    #[test]
+    fn key_released_before_its_prefix() {
+        let mut keys = Keys::new();
+        keys.tick(TMUX, &[C], 1);
+        // The key is tapped once the prefix is played
+        let reports = keys.tick(TMUX, &[], 6);
+        assert_eq!(reports[..3], PREFIX[1..4]);
+        assert_eq!(reports[3..], [&[C][..], &[], &[]]);
+    }
+
+    #[test]
+    fn modifiers_without_prefix() {
+        let mut keys = Keys::new();
+        assert_eq!(keys.tick(TMUX, &[LShift], 1), [&[LShift][..]]);
+        assert!(!keys.1.is_playing());
+    }
+
+    #[test]
+    fn other_layers_without_prefix() {
+        let mut keys = Keys::new();
+        assert_eq!(keys.tick(0, &[C], 1), [&[C][..]]);
+        assert!(!keys.1.is_playing());
+    }
+}
@@ -0,0 +1,214 @@
+//! Repeat key and alternate repeat key
+//!
+//! The repeat key sends the last key pressed again, with the modifiers held
+//! with it, for as long as it is held. The alternate repeat key sends its
+//! counterpart instead, such as Down after Up, from the pairs listed in the
+//! keymap configuration and then from the default pairs.
+//!
+//! The last key is taken from the keycodes sent, not from the positions, so
+//! it is kept across layer changes. Keys sent by the repeat keys themselves
+//! are not recorded.
+
+use heapless::Vec;
+use keyberon::key_code::KeyCode::{self, *};
+
+/// Maximum number of keys tracked at once
+const KEYS: usize = 16;
+/// Maximum number of keys, modifiers included, sent by a repeat key
+const SENT: usize = 8;
+
+/// Keys sent by the alternate repeat key after other keys, and the other way
+/// around
+pub struct AltRepeat {
+    /// Keys, modifiers included, such as `[LCtrl, Tab]`
+    pub keys: &'static [KeyCode],
+    /// Their counterpart, such as `[LCtrl, LShift, Tab]`
+    pub alt: &'static [KeyCode],
+}
+
+/// Pairs of the alternate repeat key available on every keymap
+pub const DEFAULT_ALT_REPEATS: &[AltRepeat] = &[
+    AltRepeat {
+        keys: &[Left],
+        alt: &[Right],
+    },
+    AltRepeat {
+        keys: &[Up],
+        alt: &[Down],
+    },
+    AltRepeat {
+        keys: &[Home],
+        alt: &[End],
+    },
+    AltRepeat {
+        keys: &[PgUp],
+        alt: &[PgDown],
+    },
+];
+
+/// Whether `a` and `b` hold the same keys, in any order
+fn same_keys(a: &[KeyCode], b: &[KeyCode]) -> bool {
+    a.len() == b.len() && a.iter().all(|kc| b.contains(kc))
+}
+
+/// State of the repeat keys
+pub struct Repeat {
+    /// Counterparts for the alternate repeat key, on top of the defaults
+    alt_repeats: &'static [AltRepeat],
+    /// Last key pressed, with the modifiers held with it
+    last: Vec<KeyCode, SENT>,
+    /// Keys sent while a repeat key is held
+    sent: Vec<KeyCode, SENT>,
+    /// Keys pressed on the last tick
+    pressed: Vec<KeyCode, KEYS>,
+}
+
+impl Repeat {
+    /// Create a new state, with no key pressed yet, using `alt_repeats` and
+    /// then the defaults for the alternate repeat key
+    pub fn new(alt_repeats: &'static [AltRepeat]) -> Self {
+        Self {
+            alt_repeats,
+            last: Vec::new(),
+            sent: Vec::new(),
+            pressed: Vec::new(),
+        }
+    }
+
+    /// Counterpart of the last key, if any
+    fn alt(&self) -> &'static [KeyCode] {
+        self.alt_repeats
+            .iter()
+            .chain(DEFAULT_ALT_REPEATS)
+            .find_map(|a| {
+                if same_keys(a.keys, &self.last) {
+                    Some(a.alt)
+                } else if same_keys(a.alt, &self.last) {
+                    Some(a.keys)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(&[])
+    }
+
+    /// Handle the press of the repeat key, or of the alternate repeat key if
+    /// `alt`
+    pub fn press(&mut self, alt: bool) {
+        self.sent = if alt {
+            self.alt().iter().copied().take(SENT).collect()
+        } else {
+            self.last.clone()
+        };
+    }
+
+    /// Handle the release of a repeat key
+    pub fn release(&mut self) {
+        self.sent.clear();
+    }
+
+    /// Record the last key pressed among `keycodes`, the keys currently sent
+    /// with their modifiers
+    pub fn tick(&mut self, keycodes: impl Iterator<Item = KeyCode>) {
+        let keycodes: Vec<KeyCode, KEYS> = keycodes.collect();
+        let new_key = keycodes
+            .iter()
+            .copied()
+            .find(|kc| !kc.is_modifier() && !self.pressed.contains(kc));
+        if let Some(kc) = new_key.filter(|_| self.sent.is_empty()) {
+            self.last.clear();
+            for &m in keycodes.iter().filter(|kc| kc.is_modifier()) {
+                if !self.last.contains(&m) {
+                    self.last.push(m).ok();
+                }
+            }
+            self.last.push(kc).ok();
+        }
+        self.pressed = keycodes;
+    }
+
+    /// Keys sent by the repeat key held
+    pub fn keycodes(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.sent.iter().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pairs of a keymap, on top of the defaults
+    const ALT_REPEATS: &[AltRepeat] = &[AltRepeat {
+        keys: &[LCtrl, Tab],
+        alt: &[LCtrl, LShift, Tab],
+    }];
+
+    /// Tick `repeat` with `keycodes` pressed, and then with nothing pressed
+    fn tap(repeat: &mut Repeat, keycodes: &[KeyCode]) {
+        repeat.tick(keycodes.iter().copied());
+        repeat.tick([].into_iter());
+    }
+
+    /// Keys sent by the repeat key, or the alternate one if `alt`, pressed
+    /// after `keycodes`
+    fn repeated(keycodes: &[KeyCode], alt: bool) -> Vec<KeyCode, SENT> {
+        let mut repeat = Repeat::new(ALT_REPEATS);
+        tap(&mut repeat, keycodes);
+        repeat.press(alt);
+        repeat.keycodes().collect()
+    }
+
+    #[test]
+    fn repeat() {
+        assert_eq!(repeated(&[A], false), [A]);
+        assert_eq!(repeated(&[], false), []);
+    }
+
+    #[test]
+    fn repeat_with_modifiers() {
+        assert_eq!(repeated(&[LShift, A], false), [LShift, A]);
+        assert_eq!(repeated(&[LCtrl, LAlt, Left], false), [LCtrl, LAlt, Left]);
+    }
+
+    #[test]
+    fn repeat_last_key() {
+        let mut repeat = Repeat::new(ALT_REPEATS);
+        repeat.tick([LShift, A].into_iter());
+        // B is pressed while A is held, and Shift is released
+        repeat.tick([A, B].into_iter());
+        repeat.tick([].into_iter());
+        repeat.press(false);
+        assert_eq!(repeat.keycodes().collect::<Vec<_, SENT>>(), [B]);
+        // The keys sent by the repeat key are not recorded
+        repeat.tick(repeat.sent.clone().into_iter());
+        repeat.release();
+        tap(&mut repeat, &[]);
+        repeat.press(false);
+        assert_eq!(repeat.keycodes().collect::<Vec<_, SENT>>(), [B]);
+    }
+
+    #[test]
+    fn alt_repeat_defaults() {
+        assert_eq!(repeated(&[Up], true), [Down]);
+        assert_eq!(repeated(&[Left], true), [Right]);
+        assert_eq!(repeated(&[Home], true), [End]);
+        assert_eq!(repeated(&[PgUp], true), [PgDown]);
+        assert_eq!(repeated(&[Down], true), [Up]);
+        assert_eq!(repeated(&[Right], true), [Left]);
+        assert_eq!(repeated(&[End], true), [Home]);
+        assert_eq!(repeated(&[PgDown], true), [PgUp]);
+    }
+
+    #[test]
+    fn alt_repeat_with_modifiers() {
+        assert_eq!(repeated(&[LCtrl, Tab], true), [LCtrl, LShift, Tab]);
+        assert_eq!(repeated(&[LShift, LCtrl, Tab], true), [LCtrl, Tab]);
+        // Modifiers are part of the pair
+        assert_eq!(repeated(&[LShift, Up], true), []);
+    }
+
+    #[test]
+    fn alt_repeat_without_counterpart() {
+        assert_eq!(repeated(&[A], true), []);
+    }
+}
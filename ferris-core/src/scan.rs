@@ -0,0 +1,47 @@
+//! Merge of the scans of both halves of the keyboard
+
+use keyberon::debounce::Debouncer;
+use keyberon::layout::Event;
+
+/// Number of columns on each half
+pub const COLS: usize = 5;
+/// Number of rows on each half
+pub const ROWS: usize = 4;
+
+/// Keys pressed on one half, as scanned
+pub type Scan = [[bool; COLS]; ROWS];
+
+/// Debounced scans of both halves
+///
+/// The right half is seen as the columns 5 to 9 of the layout.
+pub struct Scanner {
+    /// Debouncer for the left side
+    debouncer_left: Debouncer<Scan>,
+    /// Debouncer for the right side
+    debouncer_right: Debouncer<Scan>,
+}
+
+impl Scanner {
+    /// Create a new scanner, with no key pressed
+    pub fn new() -> Self {
+        Self {
+            debouncer_left: Debouncer::new([[false; COLS]; ROWS], [[false; COLS]; ROWS], 5),
+            debouncer_right: Debouncer::new([[false; COLS]; ROWS], [[false; COLS]; ROWS], 5),
+        }
+    }
+
+    /// Events generated by the new scans of the left and right halves
+    pub fn events(&mut self, left: Scan, right: Scan) -> impl Iterator<Item = Event> + '_ {
+        self.debouncer_left.events(left).chain(
+            self.debouncer_right
+                .events(right)
+                .map(|e| e.transform(|i, j| (i, COLS as u8 + j))),
+        )
+    }
+}
+
+impl Default for Scanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
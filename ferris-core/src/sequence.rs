@@ -0,0 +1,271 @@
+//! Sequences of key presses and releases, played over successive reports
+//!
+//! Unlike `m(&[...])` which presses keys together, a sequence changes the
+//! HID report one step per tick, so the host sees every press and release in
+//! order. Sequences and strings triggered while another one is playing are
+//! queued.
+
+use crate::host::{HostLayout, Keystroke};
+use crate::unicode::{self, UnicodeMode};
+use heapless::{Deque, Vec};
+use keyberon::key_code::KeyCode;
+
+/// Maximum number of sequences waiting to be played
+const QUEUE: usize = 8;
+/// Maximum number of keys pressed at once by a sequence
+const PRESSED: usize = 8;
+
+/// One step of a sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceEvent {
+    /// Press a key, until it is released
+    Press(KeyCode),
+    /// Release a key pressed earlier in the sequence
+    Release(KeyCode),
+    /// Press a key and release it on the next tick
+    Tap(KeyCode),
+    /// Wait some milliseconds
+    Delay(u16),
+}
+
+/// Something to play
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sequence {
+    /// Steps to play in order
+    Events(&'static [SequenceEvent]),
+    /// Text to type, with the layout of the host
+    Text(&'static str),
+    /// Key to tap
+    Key(KeyCode),
+    /// Unicode character to type with the input method of the host
+    Unicode(char),
+}
+
+/// One step played by the sequencer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// Step of a sequence
+    Event(SequenceEvent),
+    /// Keystroke typing a character, tapped with its modifiers
+    Keystroke(Keystroke),
+}
+
+/// Player of sequences
+pub struct Sequencer {
+    /// Sequences waiting to be played
+    queue: Deque<Sequence, QUEUE>,
+    /// Sequence being played, with the position of its next step
+    current: Option<(Sequence, usize)>,
+    /// Keys pressed by the sequence
+    pressed: Vec<KeyCode, PRESSED>,
+    /// Keys to release on the next tick
+    tapped: Vec<KeyCode, PRESSED>,
+    /// Milliseconds to wait before the next step
+    delay: u16,
+    /// Layout of the host, to type characters
+    host_layout: HostLayout,
+    /// Input method of the host for Unicode characters
+    unicode_mode: UnicodeMode,
+}
+
+impl Sequencer {
+    /// Create a new sequencer, playing nothing, typing characters on
+    /// `host_layout` and Unicode characters with `unicode_mode`
+    pub fn new(host_layout: HostLayout, unicode_mode: UnicodeMode) -> Self {
+        Self {
+            queue: Deque::new(),
+            current: None,
+            pressed: Vec::new(),
+            tapped: Vec::new(),
+            delay: 0,
+            host_layout,
+            unicode_mode,
+        }
+    }
+
+    /// Change the input method of the host for Unicode characters
+    pub fn set_unicode_mode(&mut self, unicode_mode: UnicodeMode) {
+        self.unicode_mode = unicode_mode;
+    }
+
+    /// Queue a sequence to play after the ones already queued
+    ///
+    /// The sequence is dropped if the queue is full.
+    pub fn push(&mut self, sequence: Sequence) {
+        self.queue.push_back(sequence).ok();
+    }
+
+    /// Whether a sequence is playing or queued
+    pub fn is_playing(&self) -> bool {
+        self.current.is_some() || !self.queue.is_empty() || !self.pressed.is_empty()
+    }
+
+    /// Whether the modifiers held on the keyboard must be left out of the
+    /// report, so they do not disturb the input method typing a Unicode
+    /// character
+    pub fn masks_modifiers(&self) -> bool {
+        matches!(self.current, Some((Sequence::Unicode(_), _)))
+    }
+
+    /// Press `kc` until it is released
+    fn press(&mut self, kc: KeyCode) {
+        if !self.pressed.contains(&kc) {
+            self.pressed.push(kc).ok();
+        }
+    }
+
+    /// Release `kc`
+    fn release(&mut self, kc: KeyCode) {
+        self.pressed.retain(|&k| k != kc);
+    }
+
+    /// Next step to play, moving to the next sequence when needed
+    fn next_step(&mut self) -> Option<Step> {
+        loop {
+            let (sequence, position) = match self.current {
+                Some(current) => current,
+                None => (self.queue.pop_front()?, 0),
+            };
+            let step = match sequence {
+                Sequence::Events(events) => events
+                    .get(position)
+                    .map(|&event| (position + 1, Step::Event(event))),
+                Sequence::Key(kc) => {
+                    (position == 0).then_some((1, Step::Event(SequenceEvent::Tap(kc))))
+                }
+                // Characters that cannot be typed are skipped
+                Sequence::Text(text) => text[position..].char_indices().find_map(|(i, c)| {
+                    let keystroke = self.host_layout.keystroke(c)?;
+                    Some((position + i + c.len_utf8(), Step::Keystroke(keystroke)))
+                }),
+                Sequence::Unicode(c) => unicode::steps(self.unicode_mode, self.host_layout, c)
+                    .get(position)
+                    .map(|&step| (position + 1, step)),
+            };
+            match step {
+                Some((position, step)) => {
+                    self.current = Some((sequence, position));
+                    return Some(step);
+                }
+                None => self.current = None,
+            }
+        }
+    }
+
+    /// Play the next step, if any
+    pub fn tick(&mut self) {
+        if self.delay > 0 {
+            self.delay -= 1;
+            return;
+        }
+        if !self.tapped.is_empty() {
+            for kc in core::mem::take(&mut self.tapped) {
+                self.release(kc);
+            }
+            return;
+        }
+        match self.next_step() {
+            Some(Step::Event(SequenceEvent::Press(kc))) => self.press(kc),
+            Some(Step::Event(SequenceEvent::Release(kc))) => self.release(kc),
+            Some(Step::Event(SequenceEvent::Tap(kc))) => {
+                self.press(kc);
+                self.tapped.push(kc).ok();
+            }
+            Some(Step::Keystroke(keystroke)) => {
+                for kc in keystroke.keycodes() {
+                    self.press(kc);
+                    self.tapped.push(kc).ok();
+                }
+            }
+            Some(Step::Event(SequenceEvent::Delay(ms))) => self.delay = ms,
+            // Keys left pressed at the end of the sequences are released
+            None => self.pressed.clear(),
+        }
+    }
+
+    /// Keys currently pressed by the sequence
+    pub fn keycodes(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.pressed.iter().copied()
+    }
+}
+
+impl Default for Sequencer {
+    fn default() -> Self {
+        Self::new(HostLayout::Us, UnicodeMode::Linux)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keyberon::key_code::KeyCode::*;
+    use std::vec::Vec;
+    use SequenceEvent::*;
+
+    /// Keys pressed after each tick, until nothing is played
+    fn play(sequencer: &mut Sequencer) -> Vec<Vec<KeyCode>> {
+        let mut reports = Vec::new();
+        while sequencer.is_playing() && reports.len() < 100 {
+            sequencer.tick();
+            reports.push(sequencer.keycodes().collect());
+        }
+        reports
+    }
+
+    #[test]
+    fn steps_in_order() {
+        let mut sequencer = Sequencer::default();
+        sequencer.push(Sequence::Events(&[
+            Press(LShift),
+            Tap(A),
+            Release(LShift),
+            Tap(B),
+        ]));
+        assert_eq!(
+            play(&mut sequencer),
+            [&[LShift][..], &[LShift, A], &[LShift], &[], &[B], &[], &[],]
+        );
+    }
+
+    #[test]
+    fn keys_left_pressed() {
+        let mut sequencer = Sequencer::default();
+        sequencer.push(Sequence::Events(&[Press(A), Press(B)]));
+        assert_eq!(play(&mut sequencer), [&[A][..], &[A, B], &[]]);
+    }
+
+    #[test]
+    fn delay() {
+        let mut sequencer = Sequencer::default();
+        sequencer.push(Sequence::Events(&[Tap(A), Delay(3), Tap(B)]));
+        assert_eq!(
+            play(&mut sequencer),
+            [&[A][..], &[], &[], &[], &[], &[], &[B], &[], &[]]
+        );
+    }
+
+    #[test]
+    fn queue() {
+        let mut sequencer = Sequencer::default();
+        sequencer.push(Sequence::Key(A));
+        sequencer.push(Sequence::Events(&[Tap(B)]));
+        assert_eq!(play(&mut sequencer), [&[A][..], &[], &[B], &[], &[]]);
+    }
+
+    #[test]
+    fn queue_overflow() {
+        let mut sequencer = Sequencer::default();
+        for _ in 0..QUEUE {
+            sequencer.push(Sequence::Key(A));
+        }
+        // The queue is full, this one is dropped
+        sequencer.push(Sequence::Key(B));
+        let reports = play(&mut sequencer);
+        let taps = |kc| reports.iter().filter(|r| r.as_slice() == [kc]).count();
+        assert_eq!(taps(A), QUEUE);
+        assert_eq!(taps(B), 0);
+        // The queue has room again
+        sequencer.push(Sequence::Key(B));
+        assert_eq!(play(&mut sequencer), [&[B][..], &[], &[]]);
+    }
+}
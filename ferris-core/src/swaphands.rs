@@ -0,0 +1,187 @@
+//! Swap-hands: typing the keys of one half with the other half
+//!
+//! While swap-hands is on, key events are mirrored across the split, column
+//! `c` becoming column `9 - c`, before anything else sees them, so one hand
+//! can type the whole keymap. The thumb row is mirrored with the table of the
+//! keymap configuration instead, so thumb keys can be kept in place.
+//!
+//! Releases are mirrored like the press of their key, so keys held while
+//! swap-hands changes are released where they were pressed.
+
+use heapless::Vec;
+use keyberon::layout::Event;
+
+/// Maximum number of keys tracked at once
+const KEYS: usize = 16;
+/// Number of columns of the matrix
+const COLS: u8 = 10;
+/// Row of the thumb keys
+const THUMB_ROW: u8 = 3;
+
+/// State of swap-hands
+pub struct SwapHands {
+    /// Column typed instead of each column of the thumb row
+    thumbs: [u8; COLS as usize],
+    /// Number of swap-hands keys held
+    held: usize,
+    /// Whether swap-hands was toggled on
+    toggled: bool,
+    /// Keys pressed, with the position sent for them
+    pressed: Vec<((u8, u8), (u8, u8)), KEYS>,
+}
+
+impl SwapHands {
+    /// Create a new state, swap-hands being off, mirroring the thumb row
+    /// with `thumbs`
+    pub fn new(thumbs: [u8; COLS as usize]) -> Self {
+        Self {
+            thumbs,
+            held: 0,
+            toggled: false,
+            pressed: Vec::new(),
+        }
+    }
+
+    /// Whether swap-hands is on
+    pub fn is_active(&self) -> bool {
+        (self.held > 0) != self.toggled
+    }
+
+    /// Handle the press of a key turning swap-hands on while held
+    pub fn press(&mut self) {
+        self.held += 1;
+    }
+
+    /// Handle the release of a key turning swap-hands on while held
+    pub fn release(&mut self) {
+        self.held = self.held.saturating_sub(1);
+    }
+
+    /// Turn swap-hands on if it is off, and off otherwise
+    pub fn toggle(&mut self) {
+        self.toggled = !self.toggled;
+    }
+
+    /// Position mirroring `(i, j)`
+    fn mirror(&self, i: u8, j: u8) -> (u8, u8) {
+        match i {
+            THUMB_ROW => (i, self.thumbs.get(usize::from(j)).copied().unwrap_or(j)),
+            _ => (i, COLS - 1 - j),
+        }
+    }
+
+    /// Mirror `event` if swap-hands is on, or if its key was pressed while
+    /// it was on
+    pub fn event(&mut self, event: Event) -> Event {
+        let key = event.coord();
+        if event.is_release() {
+            return match self.pressed.iter().position(|(k, _)| *k == key) {
+                Some(index) => {
+                    let (_, (i, j)) = self.pressed.swap_remove(index);
+                    Event::Release(i, j)
+                }
+                None => event,
+            };
+        }
+        if !self.is_active() {
+            return event;
+        }
+        let mirrored = self.mirror(key.0, key.1);
+        // A press that cannot be tracked is not mirrored, so its release
+        // still matches it
+        match self.pressed.push((key, mirrored)) {
+            Ok(()) => event.transform(|_, _| mirrored),
+            Err(_) => event,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Thumb row kept in place
+    const THUMBS: [u8; COLS as usize] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+
+    /// Position sent for the press then the release of `key`
+    fn tap(swap_hands: &mut SwapHands, key: (u8, u8)) -> [(u8, u8); 2] {
+        let press = swap_hands.event(Event::Press(key.0, key.1));
+        let release = swap_hands.event(Event::Release(key.0, key.1));
+        [press.coord(), release.coord()]
+    }
+
+    #[test]
+    fn mirror() {
+        let swap_hands = SwapHands::new([9, 8, 7, 6, 5, 4, 3, 2, 1, 0]);
+        for i in 0..THUMB_ROW {
+            for j in 0..COLS {
+                assert_eq!(swap_hands.mirror(i, j), (i, COLS - 1 - j));
+            }
+        }
+        assert_eq!(swap_hands.mirror(3, 3), (3, 6));
+        assert_eq!(swap_hands.mirror(3, 5), (3, 4));
+    }
+
+    #[test]
+    fn thumb_row_table() {
+        let swap_hands = SwapHands::new(THUMBS);
+        assert_eq!(swap_hands.mirror(0, 0), (0, 9));
+        for j in 0..COLS {
+            assert_eq!(swap_hands.mirror(THUMB_ROW, j), (THUMB_ROW, j));
+        }
+    }
+
+    #[test]
+    fn off() {
+        let mut swap_hands = SwapHands::new(THUMBS);
+        assert!(!swap_hands.is_active());
+        assert_eq!(tap(&mut swap_hands, (1, 1)), [(1, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn hold() {
+        let mut swap_hands = SwapHands::new(THUMBS);
+        swap_hands.press();
+        assert!(swap_hands.is_active());
+        assert_eq!(tap(&mut swap_hands, (1, 1)), [(1, 8), (1, 8)]);
+        assert_eq!(tap(&mut swap_hands, (3, 4)), [(3, 4), (3, 4)]);
+        swap_hands.release();
+        assert!(!swap_hands.is_active());
+        assert_eq!(tap(&mut swap_hands, (1, 1)), [(1, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn toggle() {
+        let mut swap_hands = SwapHands::new(THUMBS);
+        swap_hands.toggle();
+        assert_eq!(tap(&mut swap_hands, (0, 4)), [(0, 5), (0, 5)]);
+        // Holding the swap-hands key while toggled on swaps back
+        swap_hands.press();
+        assert!(!swap_hands.is_active());
+        swap_hands.release();
+        assert!(swap_hands.is_active());
+        swap_hands.toggle();
+        assert_eq!(tap(&mut swap_hands, (0, 4)), [(0, 4), (0, 4)]);
+    }
+
+    #[test]
+    fn released_after_the_swap() {
+        let mut swap_hands = SwapHands::new(THUMBS);
+        swap_hands.press();
+        let press = swap_hands.event(Event::Press(2, 0));
+        swap_hands.release();
+        let release = swap_hands.event(Event::Release(2, 0));
+        assert_eq!(press, Event::Press(2, 9));
+        assert_eq!(release, Event::Release(2, 9));
+    }
+
+    #[test]
+    fn released_during_the_swap() {
+        let mut swap_hands = SwapHands::new(THUMBS);
+        let press = swap_hands.event(Event::Press(2, 0));
+        swap_hands.toggle();
+        let release = swap_hands.event(Event::Release(2, 0));
+        assert_eq!(press, Event::Press(2, 0));
+        assert_eq!(release, Event::Release(2, 0));
+    }
+}
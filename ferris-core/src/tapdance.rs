@@ -0,0 +1,280 @@
+//! Tap dances: keys doing something different depending on how many times
+//! they are tapped
+//!
+//! Each press of a tap dance key within the timeout of the previous one
+//! counts as a tap. The dance ends when the timeout expires, another key is
+//! pressed, or the last tap count is reached. The layout then sees a press of
+//! the position given for that number of taps, usually the key itself for a
+//! single tap and positions of the matrix that are not wired for the others,
+//! so their action can be anything, hold-taps and layers included.
+//!
+//! If the key is still held when the dance ends, the position stays pressed
+//! until the key is released: with a hold-tap at that position, tap then hold
+//! triggers its hold action. The action used is the one of the layer active
+//! when the dance ends.
+
+use heapless::{Deque, Vec};
+use keyberon::layout::Event;
+
+/// Maximum number of tap dance keys held at once after their dance
+const HELD: usize = 4;
+/// Maximum number of events waiting for the layout
+const OUTPUT: usize = 16;
+
+/// A key doing something different depending on how many times it is tapped
+pub struct TapDance {
+    /// Position of the key
+    pub key: (u8, u8),
+    /// Position pressed after 1, 2, 3... taps
+    pub taps: &'static [(u8, u8)],
+    /// Milliseconds to tap the key again
+    pub timeout: u16,
+    /// Layers where the tap dance is enabled, all of them if empty
+    pub layers: &'static [usize],
+}
+
+/// A tap dance in progress
+struct Dance {
+    /// The tap dance
+    dance: &'static TapDance,
+    /// Number of taps so far
+    taps: usize,
+    /// Whether the key is pressed
+    pressed: bool,
+    /// Milliseconds since the last press or release of the key
+    elapsed: u16,
+}
+
+/// Detection of the tap dances in the key events
+pub struct TapDances {
+    /// Tap dances of the keymap
+    dances: &'static [TapDance],
+    /// Tap dance in progress
+    current: Option<Dance>,
+    /// Keys held after their dance, with the position pressed instead
+    held: Vec<((u8, u8), (u8, u8)), HELD>,
+    /// Events for the layout
+    output: Deque<Event, OUTPUT>,
+}
+
+impl TapDances {
+    /// Create the detection of `dances`
+    pub fn new(dances: &'static [TapDance]) -> Self {
+        Self {
+            dances,
+            current: None,
+            held: Vec::new(),
+            output: Deque::new(),
+        }
+    }
+
+    /// End the dance in progress, pressing the position for its number of
+    /// taps
+    fn resolve(&mut self) {
+        let Some(current) = self.current.take() else {
+            return;
+        };
+        let (i, j) = current.dance.taps[current.taps - 1];
+        self.output.push_back(Event::Press(i, j)).ok();
+        if !current.pressed || self.held.push((current.dance.key, (i, j))).is_err() {
+            self.output.push_back(Event::Release(i, j)).ok();
+        }
+    }
+
+    /// Handle a key event, `layer` being the current layer
+    pub fn event(&mut self, event: Event, layer: usize) {
+        let key = event.coord();
+        if event.is_release() {
+            if let Some(index) = self.held.iter().position(|(k, _)| *k == key) {
+                let (_, (i, j)) = self.held.swap_remove(index);
+                self.output.push_back(Event::Release(i, j)).ok();
+                return;
+            }
+        }
+        if let Some(current) = &mut self.current {
+            if current.dance.key == key {
+                current.pressed = event.is_press();
+                current.elapsed = 0;
+                if event.is_press() {
+                    current.taps += 1;
+                    if current.taps == current.dance.taps.len() {
+                        self.resolve();
+                    }
+                }
+                return;
+            }
+            if event.is_press() {
+                self.resolve();
+            }
+        }
+        if event.is_press() {
+            let dance = self.dances.iter().find(|d| {
+                d.key == key
+                    && !d.taps.is_empty()
+                    && (d.layers.is_empty() || d.layers.contains(&layer))
+            });
+            if let Some(dance) = dance {
+                self.current = Some(Dance {
+                    dance,
+                    taps: 1,
+                    pressed: true,
+                    elapsed: 0,
+                });
+                if dance.taps.len() == 1 {
+                    self.resolve();
+                }
+                return;
+            }
+        }
+        self.output.push_back(event).ok();
+    }
+
+    /// Advance the timeout of the dance in progress by one millisecond
+    pub fn tick(&mut self) {
+        if let Some(current) = &mut self.current {
+            current.elapsed += 1;
+            if current.elapsed >= current.dance.timeout {
+                self.resolve();
+            }
+        }
+    }
+
+    /// Next event for the layout
+    pub fn pop(&mut self) -> Option<Event> {
+        self.output.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tap dance of the tests: `(0, 0)` once, `(3, 1)` twice, `(3, 2)`
+    /// three times
+    static DANCES: [TapDance; 1] = [TapDance {
+        key: (0, 0),
+        taps: &[(0, 0), (3, 1), (3, 2)],
+        timeout: 10,
+        layers: &[0],
+    }];
+
+    /// Events sent to the layout so far
+    fn output(dances: &mut TapDances) -> Vec<Event, OUTPUT> {
+        core::iter::from_fn(|| dances.pop()).collect()
+    }
+
+    /// Tick `dances` for `ms` milliseconds
+    fn wait(dances: &mut TapDances, ms: u16) {
+        for _ in 0..ms {
+            dances.tick();
+        }
+    }
+
+    #[test]
+    fn single_tap() {
+        let mut dances = TapDances::new(&DANCES);
+        dances.event(Event::Press(0, 0), 0);
+        dances.event(Event::Release(0, 0), 0);
+        assert_eq!(output(&mut dances), []);
+        wait(&mut dances, 10);
+        assert_eq!(
+            output(&mut dances),
+            [Event::Press(0, 0), Event::Release(0, 0)]
+        );
+    }
+
+    #[test]
+    fn double_tap() {
+        let mut dances = TapDances::new(&DANCES);
+        for _ in 0..2 {
+            dances.event(Event::Press(0, 0), 0);
+            wait(&mut dances, 5);
+            dances.event(Event::Release(0, 0), 0);
+            wait(&mut dances, 5);
+        }
+        assert_eq!(output(&mut dances), []);
+        wait(&mut dances, 5);
+        assert_eq!(
+            output(&mut dances),
+            [Event::Press(3, 1), Event::Release(3, 1)]
+        );
+    }
+
+    #[test]
+    fn last_tap_count() {
+        let mut dances = TapDances::new(&DANCES);
+        for _ in 0..3 {
+            dances.event(Event::Press(0, 0), 0);
+            dances.event(Event::Release(0, 0), 0);
+        }
+        // The dance ends with the last press, without waiting
+        assert_eq!(
+            output(&mut dances),
+            [Event::Press(3, 2), Event::Release(3, 2)]
+        );
+    }
+
+    #[test]
+    fn hold() {
+        let mut dances = TapDances::new(&DANCES);
+        dances.event(Event::Press(0, 0), 0);
+        wait(&mut dances, 10);
+        assert_eq!(output(&mut dances), [Event::Press(0, 0)]);
+        wait(&mut dances, 100);
+        dances.event(Event::Release(0, 0), 0);
+        assert_eq!(output(&mut dances), [Event::Release(0, 0)]);
+    }
+
+    #[test]
+    fn tap_then_hold() {
+        let mut dances = TapDances::new(&DANCES);
+        dances.event(Event::Press(0, 0), 0);
+        dances.event(Event::Release(0, 0), 0);
+        dances.event(Event::Press(0, 0), 0);
+        wait(&mut dances, 10);
+        assert_eq!(output(&mut dances), [Event::Press(3, 1)]);
+        dances.event(Event::Release(0, 0), 0);
+        assert_eq!(output(&mut dances), [Event::Release(3, 1)]);
+    }
+
+    #[test]
+    fn interrupt() {
+        let mut dances = TapDances::new(&DANCES);
+        dances.event(Event::Press(0, 0), 0);
+        dances.event(Event::Release(0, 0), 0);
+        dances.event(Event::Press(1, 1), 0);
+        assert_eq!(
+            output(&mut dances),
+            [Event::Press(0, 0), Event::Release(0, 0), Event::Press(1, 1)]
+        );
+    }
+
+    #[test]
+    fn interrupt_while_held() {
+        let mut dances = TapDances::new(&DANCES);
+        dances.event(Event::Press(0, 0), 0);
+        dances.event(Event::Press(1, 1), 0);
+        dances.event(Event::Release(1, 1), 0);
+        dances.event(Event::Release(0, 0), 0);
+        assert_eq!(
+            output(&mut dances),
+            [
+                Event::Press(0, 0),
+                Event::Press(1, 1),
+                Event::Release(1, 1),
+                Event::Release(0, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn other_layer() {
+        let mut dances = TapDances::new(&DANCES);
+        dances.event(Event::Press(0, 0), 1);
+        dances.event(Event::Release(0, 0), 1);
+        assert_eq!(
+            output(&mut dances),
+            [Event::Press(0, 0), Event::Release(0, 0)]
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! Tri-layers: a layer activated by holding several layers together
+//!
+//! When all the layers of a tri-layer are held, by `l(x)` keys or hold-taps
+//! held with `l(x)`, its layer is activated instead, without repeating its
+//! actions on each of them. The layout sees the release of the keys holding
+//! the layers, whose own releases are then ignored, and the layer of the
+//! tri-layer becomes the default layer, so its transparent keys do nothing.
+//! Releasing one of the keys goes back to the layer still held, and releasing
+//! all of them restores the default layer.
+
+use crate::action::Action;
+use crate::KBLayout;
+use heapless::Vec;
+use keyberon::layout::Event;
+
+/// Maximum number of keys holding a layer at once
+const HOLDERS: usize = 8;
+
+/// A layer activated when some layers are held together
+pub struct TriLayer {
+    /// Layers to hold together
+    pub layers: &'static [usize],
+    /// Layer activated
+    pub layer: usize,
+}
+
+/// A key that may hold a layer
+struct Holder {
+    /// Position of the key
+    key: (u8, u8),
+    /// Layer held by the key
+    layer: usize,
+    /// Whether the layer was activated by the key
+    active: bool,
+    /// Whether the key was released for the layout
+    released: bool,
+}
+
+/// Layer held by `action`, if any
+fn held_layer(action: &Action) -> Option<usize> {
+    match action {
+        Action::Layer(layer) => Some(*layer),
+        Action::HoldTap(ht) => match ht.hold {
+            Action::Layer(layer) => Some(layer),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// State of the tri-layers
+pub struct TriLayers {
+    /// Tri-layers of the keymap
+    tri_layers: &'static [TriLayer],
+    /// Keys held that may hold a layer
+    holders: Vec<Holder, HOLDERS>,
+    /// Default layer to restore once the keys released for the layout are
+    /// released, if a tri-layer was activated
+    previous: Option<usize>,
+}
+
+impl TriLayers {
+    /// Create a new state, with no layer held
+    pub fn new(tri_layers: &'static [TriLayer]) -> Self {
+        Self {
+            tri_layers,
+            holders: Vec::new(),
+            previous: None,
+        }
+    }
+
+    /// Handle a key event, `action` being the action of the key
+    ///
+    /// Returns whether the event is sent to the layout: the release of a key
+    /// already released for the layout is not.
+    pub fn event(&mut self, event: Event, action: &Action) -> bool {
+        let key = event.coord();
+        if event.is_press() {
+            if let Some(layer) = held_layer(action).filter(|_| !self.tri_layers.is_empty()) {
+                let holder = Holder {
+                    key,
+                    layer,
+                    active: false,
+                    released: false,
+                };
+                self.holders.push(holder).ok();
+            }
+            return true;
+        }
+        match self.holders.iter().position(|h| h.key == key) {
+            Some(index) => !self.holders.swap_remove(index).released,
+            None => true,
+        }
+    }
+
+    /// Whether `layer` is held by a key
+    fn is_held(&self, layer: usize) -> bool {
+        self.holders.iter().any(|h| h.active && h.layer == layer)
+    }
+
+    /// Activate the tri-layer whose layers are all held, if any, `default`
+    /// being the default layer
+    pub fn tick(&mut self, layout: &mut KBLayout, default: usize) {
+        // The layout adds up the layers held: a key has activated its layer
+        // once that layer and the ones already active add up to the current
+        // layer
+        let current = layout.current_layer();
+        let mut active: usize = self
+            .holders
+            .iter()
+            .filter(|h| h.active && !h.released)
+            .map(|h| h.layer)
+            .sum();
+        for holder in self.holders.iter_mut().filter(|h| !h.active && !h.released) {
+            if active + holder.layer == current {
+                holder.active = true;
+                active += holder.layer;
+            }
+        }
+        // Keys activating their layers on the same tick
+        let pending: usize = self
+            .holders
+            .iter()
+            .filter(|h| !h.released)
+            .map(|h| h.layer)
+            .sum();
+        if active != current && current != default && pending == current {
+            for holder in self.holders.iter_mut().filter(|h| !h.released) {
+                holder.active = true;
+            }
+        }
+        let tri_layers = self.tri_layers;
+        match tri_layers
+            .iter()
+            .find(|t| t.layers.iter().all(|&l| self.is_held(l)))
+        {
+            Some(tri_layer) => {
+                let holders = self.holders.iter_mut().filter(|h| h.active && !h.released);
+                for holder in holders.filter(|h| tri_layer.layers.contains(&h.layer)) {
+                    holder.released = true;
+                    layout.event(Event::Release(holder.key.0, holder.key.1));
+                }
+                self.previous.get_or_insert(default);
+                layout.set_default_layer(tri_layer.layer);
+            }
+            None => {
+                let Some(previous) = self.previous else {
+                    return;
+                };
+                // Back to the layer still held, if any
+                let held = self.holders.iter().filter(|h| h.released).map(|h| h.layer);
+                match held.max() {
+                    Some(layer) => layout.set_default_layer(layer),
+                    None => {
+                        self.previous = None;
+                        layout.set_default_layer(previous);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LAYERS;
+    use keyberon::action::l;
+    use keyberon::layout::Layout;
+    use std::boxed::Box;
+
+    /// Key holding LOWER, the layer 1, or holding it on top of RAISE
+    const LOWER: (u8, u8) = (3, 3);
+    /// Key holding RAISE, the layer 2, or holding it on top of LOWER
+    const RAISE: (u8, u8) = (3, 6);
+
+    /// LOWER and RAISE held together: ADJUST, the layer 3
+    static TRI_LAYERS: [TriLayer; 1] = [TriLayer {
+        layers: &[1, 2],
+        layer: 3,
+    }];
+
+    /// Layout and tri-layers, routed the way the keyboard does
+    struct Keys {
+        /// Layers of the layout
+        layers: &'static [[[Action; 10]; 4]],
+        /// Layout
+        layout: KBLayout,
+        /// Tri-layers
+        tri_layers: TriLayers,
+    }
+
+    impl Keys {
+        /// Create the layout, with LOWER and RAISE on the layers 0, 1 and 2
+        fn new() -> Self {
+            let mut layers = LAYERS;
+            layers[0][3][3] = l(1);
+            layers[0][3][6] = l(2);
+            layers[1][3][6] = l(2);
+            layers[2][3][3] = l(1);
+            let layers = &*Box::leak(Box::new(layers));
+            Self {
+                layers,
+                layout: Layout::new(layers),
+                tri_layers: TriLayers::new(&TRI_LAYERS),
+            }
+        }
+
+        /// Send `event`, then tick until the layout has handled it and the
+        /// releases sent by the tri-layers
+        fn event(&mut self, event: Event) {
+            let (i, j) = event.coord();
+            let action = &self.layers[self.layout.current_layer()][i as usize][j as usize];
+            if self.tri_layers.event(event, action) {
+                self.layout.event(event);
+            }
+            for _ in 0..4 {
+                self.layout.tick();
+                self.tri_layers.tick(&mut self.layout, 0);
+            }
+        }
+
+        /// Press the key at `key`
+        fn press(&mut self, key: (u8, u8)) {
+            self.event(Event::Press(key.0, key.1));
+        }
+
+        /// Release the key at `key`
+        fn release(&mut self, key: (u8, u8)) {
+            self.event(Event::Release(key.0, key.1));
+        }
+
+        /// Current layer
+        fn layer(&self) -> usize {
+            self.layout.current_layer()
+        }
+    }
+
+    #[test]
+    fn lower_then_raise() {
+        let mut keys = Keys::new();
+        keys.press(LOWER);
+        assert_eq!(keys.layer(), 1);
+        keys.press(RAISE);
+        assert_eq!(keys.layer(), 3);
+    }
+
+    #[test]
+    fn raise_then_lower() {
+        let mut keys = Keys::new();
+        keys.press(RAISE);
+        assert_eq!(keys.layer(), 2);
+        keys.press(LOWER);
+        assert_eq!(keys.layer(), 3);
+    }
+
+    #[test]
+    fn release_lower() {
+        let mut keys = Keys::new();
+        keys.press(LOWER);
+        keys.press(RAISE);
+        keys.release(LOWER);
+        assert_eq!(keys.layer(), 2);
+        keys.press(LOWER);
+        assert_eq!(keys.layer(), 3);
+    }
+
+    #[test]
+    fn release_raise() {
+        let mut keys = Keys::new();
+        keys.press(RAISE);
+        keys.press(LOWER);
+        keys.release(RAISE);
+        assert_eq!(keys.layer(), 1);
+        keys.press(RAISE);
+        assert_eq!(keys.layer(), 3);
+    }
+
+    #[test]
+    fn release_both() {
+        for (first, second) in [(LOWER, RAISE), (RAISE, LOWER)] {
+            let mut keys = Keys::new();
+            keys.press(LOWER);
+            keys.press(RAISE);
+            keys.release(first);
+            keys.release(second);
+            assert_eq!(keys.layer(), 0);
+            // Nothing is left held
+            keys.press(RAISE);
+            assert_eq!(keys.layer(), 2);
+            keys.release(RAISE);
+            assert_eq!(keys.layer(), 0);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! Typing Unicode characters through an input method of the host
+//!
+//! Hosts have no standard way to receive a character that is not on their
+//! keyboard layout, so characters are typed with one of the input methods
+//! below, selected in the keymap configuration or at runtime:
+//!
+//! - [`UnicodeMode::Linux`]: Ctrl-Shift-U, the code point in hexadecimal, then
+//!   Space, as understood by GTK and IBus;
+//! - [`UnicodeMode::MacOs`]: the code point in hexadecimal while Option is
+//!   held, with the "Unicode Hex Input" source selected;
+//! - [`UnicodeMode::WinCompose`]: the compose key of WinCompose, U, the code
+//!   point in hexadecimal, then Enter;
+//! - [`UnicodeMode::Compose`]: the X11 compose sequence of the character,
+//!   only for the common accented letters and symbols of [`compose`].
+//!
+//! Except on macOS, whose "Unicode Hex Input" source has a US layout, the
+//! characters of the input method are typed with the layout of the host.
+
+use crate::host::HostLayout;
+use crate::sequence::{SequenceEvent, Step};
+use heapless::Vec;
+use keyberon::key_code::KeyCode::{self, *};
+
+/// Maximum number of steps to type a character
+pub const STEPS: usize = 16;
+
+/// Input method used to type Unicode characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnicodeMode {
+    /// Ctrl-Shift-U, then the code point, for GTK and IBus on Linux
+    Linux,
+    /// Code point typed while Option is held, for the "Unicode Hex Input"
+    /// source of macOS
+    MacOs,
+    /// Compose key of WinCompose, such as `RAlt`, then U and the code point
+    WinCompose(KeyCode),
+    /// X11 compose sequences, after the compose key, such as `RAlt`
+    Compose(KeyCode),
+}
+
+/// Keys typed after the compose key for the lowercase character `c`, in the
+/// default X11 compose table
+///
+/// The uppercase variant of a character is typed with its letters shifted.
+pub fn compose(c: char) -> Option<[char; 2]> {
+    let keys = match c {
+        'à' => ['`', 'a'],
+        'á' => ['\'', 'a'],
+        'â' => ['^', 'a'],
+        'ã' => ['~', 'a'],
+        'ä' => ['"', 'a'],
+        'å' => ['o', 'a'],
+        'æ' => ['a', 'e'],
+        'ç' => [',', 'c'],
+        'è' => ['`', 'e'],
+        'é' => ['\'', 'e'],
+        'ê' => ['^', 'e'],
+        'ë' => ['"', 'e'],
+        'ì' => ['`', 'i'],
+        'í' => ['\'', 'i'],
+        'î' => ['^', 'i'],
+        'ï' => ['"', 'i'],
+        'ñ' => ['~', 'n'],
+        'ò' => ['`', 'o'],
+        'ó' => ['\'', 'o'],
+        'ô' => ['^', 'o'],
+        'õ' => ['~', 'o'],
+        'ö' => ['"', 'o'],
+        'ø' => ['/', 'o'],
+        'œ' => ['o', 'e'],
+        'ù' => ['`', 'u'],
+        'ú' => ['\'', 'u'],
+        'û' => ['^', 'u'],
+        'ü' => ['"', 'u'],
+        'ý' => ['\'', 'y'],
+        'ÿ' => ['"', 'y'],
+        'ß' => ['s', 's'],
+        '€' => ['=', 'e'],
+        '°' => ['o', 'o'],
+        '«' => ['<', '<'],
+        '»' => ['>', '>'],
+        _ => return None,
+    };
+    Some(keys)
+}
+
+/// Steps typing a character
+type Steps = Vec<Step, STEPS>;
+
+/// Add to `steps` the keystrokes typing `chars` on `layout`, skipping the
+/// characters missing from it
+fn push_chars(steps: &mut Steps, layout: HostLayout, chars: impl IntoIterator<Item = char>) {
+    for c in chars {
+        if let Some(keystroke) = layout.keystroke(c) {
+            steps.push(Step::Keystroke(keystroke)).ok();
+        }
+    }
+}
+
+/// Hexadecimal digits of `value`, with at least `min` digits
+fn hex(value: u32, min: u32) -> impl Iterator<Item = char> {
+    let digits = (1..8)
+        .find(|&n| value >> (4 * n) == 0)
+        .unwrap_or(8)
+        .max(min);
+    (0..digits)
+        .rev()
+        .filter_map(move |n| char::from_digit((value >> (4 * n)) & 0xF, 16))
+}
+
+/// Steps typing `c` with `mode`, on the host `layout`
+///
+/// Nothing is typed for characters missing from the compose table.
+pub fn steps(mode: UnicodeMode, layout: HostLayout, c: char) -> Steps {
+    use SequenceEvent::{Press, Release, Tap};
+
+    let mut steps = Steps::new();
+    match mode {
+        UnicodeMode::Linux => {
+            steps.extend([Press(LCtrl), Press(LShift)].map(Step::Event));
+            push_chars(&mut steps, layout, ['u']);
+            steps.extend([Release(LShift), Release(LCtrl)].map(Step::Event));
+            push_chars(&mut steps, layout, hex(c as u32, 1));
+            steps.push(Step::Event(Tap(Space))).ok();
+        }
+        UnicodeMode::MacOs => {
+            steps.push(Step::Event(Press(LAlt))).ok();
+            // Characters beyond the BMP are typed as surrogate pairs
+            for &mut unit in c.encode_utf16(&mut [0; 2]) {
+                push_chars(&mut steps, HostLayout::Us, hex(unit.into(), 4));
+            }
+            steps.push(Step::Event(Release(LAlt))).ok();
+        }
+        UnicodeMode::WinCompose(key) => {
+            steps.push(Step::Event(Tap(key))).ok();
+            push_chars(&mut steps, layout, ['u']);
+            push_chars(&mut steps, layout, hex(c as u32, 1));
+            steps.push(Step::Event(Tap(Enter))).ok();
+        }
+        UnicodeMode::Compose(key) => {
+            let lower = c.to_lowercase().next().unwrap_or(c);
+            if let Some(keys) = compose(lower) {
+                steps.push(Step::Event(Tap(key))).ok();
+                let upper = lower != c;
+                let keys = keys.map(|k| if upper { k.to_ascii_uppercase() } else { k });
+                push_chars(&mut steps, layout, keys);
+            }
+        }
+    }
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::Keystroke;
+    use std::string::String;
+    use SequenceEvent::{Press, Release, Tap};
+
+    /// Step tapping `key`, with Shift if `shift`
+    fn key(key: KeyCode, shift: bool) -> Step {
+        Step::Keystroke(Keystroke {
+            key,
+            shift,
+            altgr: false,
+        })
+    }
+
+    /// Steps typing `chars` on `layout`
+    fn chars(layout: HostLayout, chars: &str) -> Steps {
+        let mut steps = Steps::new();
+        push_chars(&mut steps, layout, chars.chars());
+        steps
+    }
+
+    /// Concatenation of `parts`
+    fn concat<const N: usize>(parts: [&[Step]; N]) -> Steps {
+        parts.iter().flat_map(|part| part.iter().copied()).collect()
+    }
+
+    #[test]
+    fn hex_digits() {
+        assert_eq!(hex(0, 1).collect::<String>(), "0");
+        assert_eq!(hex(0xe9, 1).collect::<String>(), "e9");
+        assert_eq!(hex(0xe9, 4).collect::<String>(), "00e9");
+        assert_eq!(hex(0x1f600, 4).collect::<String>(), "1f600");
+        assert_eq!(hex(u32::MAX, 1).collect::<String>(), "ffffffff");
+    }
+
+    #[test]
+    fn linux() {
+        let typed = steps(UnicodeMode::Linux, HostLayout::Us, 'é');
+        let expected = concat([
+            &[Step::Event(Press(LCtrl)), Step::Event(Press(LShift))],
+            &[key(U, false)],
+            &[Step::Event(Release(LShift)), Step::Event(Release(LCtrl))],
+            &[key(E, false), key(Kb9, false)],
+            &[Step::Event(Tap(Space))],
+        ]);
+        assert_eq!(typed, expected);
+    }
+
+    #[test]
+    fn linux_on_the_host_layout() {
+        // The code point is typed with the digits of AZERTY, shifted
+        let typed = steps(UnicodeMode::Linux, HostLayout::Azerty, 'é');
+        assert_eq!(typed[5..7], chars(HostLayout::Azerty, "e9"));
+        assert_eq!(typed[6], key(Kb9, true));
+    }
+
+    #[test]
+    fn mac_os() {
+        let typed = steps(UnicodeMode::MacOs, HostLayout::Azerty, 'é');
+        let expected = concat([
+            &[Step::Event(Press(LAlt))],
+            &chars(HostLayout::Us, "00e9"),
+            &[Step::Event(Release(LAlt))],
+        ]);
+        assert_eq!(typed, expected);
+    }
+
+    #[test]
+    fn mac_os_surrogate_pair() {
+        // U+1F600 is typed as the surrogate pair D83D DE00
+        let typed = steps(UnicodeMode::MacOs, HostLayout::Us, '😀');
+        let expected = concat([
+            &[Step::Event(Press(LAlt))],
+            &chars(HostLayout::Us, "d83dde00"),
+            &[Step::Event(Release(LAlt))],
+        ]);
+        assert_eq!(typed, expected);
+    }
+
+    #[test]
+    fn win_compose() {
+        let typed = steps(UnicodeMode::WinCompose(RAlt), HostLayout::Us, '€');
+        let expected = concat([
+            &[Step::Event(Tap(RAlt))],
+            &chars(HostLayout::Us, "u20ac"),
+            &[Step::Event(Tap(Enter))],
+        ]);
+        assert_eq!(typed, expected);
+    }
+
+    #[test]
+    fn compose_sequence() {
+        let typed = steps(UnicodeMode::Compose(RAlt), HostLayout::Us, 'é');
+        let expected = concat([
+            &[Step::Event(Tap(RAlt))],
+            &[key(Quote, false), key(E, false)],
+        ]);
+        assert_eq!(typed, expected);
+    }
+
+    #[test]
+    fn uppercase_compose_sequence() {
+        let typed = steps(UnicodeMode::Compose(RAlt), HostLayout::Us, 'À');
+        let expected = concat([
+            &[Step::Event(Tap(RAlt))],
+            &[key(Grave, false), key(A, true)],
+        ]);
+        assert_eq!(typed, expected);
+        let typed = steps(UnicodeMode::Compose(RAlt), HostLayout::Us, 'Œ');
+        let expected = concat([&[Step::Event(Tap(RAlt))], &[key(O, true), key(E, true)]]);
+        assert_eq!(typed, expected);
+    }
+
+    #[test]
+    fn missing_from_compose_table() {
+        assert_eq!(compose('✓'), None);
+        assert!(steps(UnicodeMode::Compose(RAlt), HostLayout::Us, '✓').is_empty());
+        // Other modes type any character
+        assert!(!steps(UnicodeMode::Linux, HostLayout::Us, '✓').is_empty());
+    }
+}
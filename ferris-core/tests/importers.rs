@@ -0,0 +1,16 @@
+//! Tests of the importers of the build script
+//!
+//! Cargo does not run the tests of a build script, so its modules are built
+//! again here, with their tests.
+
+/// Importer for Kanata `.kbd` configuration files
+#[path = "../build/kanata.rs"]
+mod kanata;
+/// Code generation for the 34 keys layout, partly used by the build script
+/// only
+#[allow(dead_code)]
+#[path = "../build/layout.rs"]
+mod layout;
+/// Importer for ZMK `.keymap` files
+#[path = "../build/zmk.rs"]
+mod zmk;
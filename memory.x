@@ -2,6 +2,7 @@
 MEMORY
 {
   /* NOTE K = KiBi = 1024 bytes */
-  FLASH : ORIGIN = 0x08000000, LENGTH = 128K
+  /* The last 2K page stores the dynamic macros */
+  FLASH : ORIGIN = 0x08000000, LENGTH = 126K
   RAM : ORIGIN = 0x20000000, LENGTH = 16K
 }
@@ -8,13 +8,15 @@
 // Some panic handler needs to be included. This one halts the processor on panic.
 use panic_halt as _;
 
+use ferris_core::dynamicmacro::STORAGE;
+use ferris_core::keyboard::{Command, Keyboard};
+use ferris_core::scan::Scanner;
 use hal::gpio::{Input, Output, Pin, PullUp, PushPull};
 use hal::prelude::*;
 use hal::usb;
 use hal::{stm32, timers};
-use keyberon::debounce::Debouncer;
 use keyberon::key_code::KbHidReport;
-use keyberon::layout::{CustomEvent, Event, Layout};
+use keyberon::layout::Event;
 use keyberon::matrix::Matrix;
 use rtic::app;
 use stm32f0xx_hal as hal;
@@ -26,34 +28,12 @@ use usb_device::device::{UsbDeviceBuilder, UsbDeviceState, UsbVidPid};
 mod io_expander;
 /// Right side of the keyboard
 mod right;
+/// Storage of the dynamic macros in flash
+mod storage;
 
 use io_expander::IoExpander;
 use right::Right;
-
-#[cfg(not(any(
-    feature = "keymap_basic",
-    feature = "keymap_borisfaure",
-    feature = "keymap_pierrec83"
-)))]
-compile_error!("Either feature \"keymap_basic\" or \"keymap_borisfaure\" or \"keymap_pierrec83\" must be enabled.");
-
-/// Basic layout for the keyboard
-#[cfg(feature = "keymap_basic")]
-mod keymap_basic;
-#[cfg(feature = "keymap_basic")]
-use keymap_basic::{KBLayout, LAYERS};
-
-/// Keymap by Boris Faure
-#[cfg(feature = "keymap_borisfaure")]
-mod keymap_borisfaure;
-#[cfg(feature = "keymap_borisfaure")]
-use keymap_borisfaure::{KBLayout, LAYERS};
-
-/// Keymap by @pierrec83
-#[cfg(feature = "keymap_pierrec83")]
-mod keymap_pierrec83;
-#[cfg(feature = "keymap_pierrec83")]
-use keymap_pierrec83::{KBLayout, LAYERS};
+use storage::Storage;
 
 // Ensure one of the models is set as feature
 #[cfg(not(any(
@@ -108,9 +88,11 @@ mod app {
         usb_dev: UsbDevice,
         /// The HID class
         usb_class: UsbClass,
-        /// Layout of the keyboard
+        /// Logic of the keyboard
         #[lock_free]
-        layout: KBLayout,
+        keyboard: Keyboard,
+        /// Dynamic macros waiting to be saved to flash
+        macros: Option<[u8; STORAGE]>,
     }
 
     #[local]
@@ -119,12 +101,12 @@ mod app {
         matrix: Matrix<Pin<Input<PullUp>>, Pin<Output<PushPull>>, 5, 4>,
         /// Right side
         right: Right,
-        /// Debouncer for the left side
-        debouncer_left: Debouncer<[[bool; 5]; 4]>,
-        /// Debouncer for the right side
-        debouncer_right: Debouncer<[[bool; 5]; 4]>,
+        /// Debounced scans of both sides
+        scanner: Scanner,
         /// Timer when to scan the matrices
         timer: timers::Timer<stm32::TIM3>,
+        /// Storage of the dynamic macros
+        storage: Storage,
     }
 
     #[init(local = [bus: Option<UsbBusAllocator<usb::UsbBusType>> = None])]
@@ -190,18 +172,23 @@ mod app {
         })
         .unwrap();
 
+        let storage = Storage::new(c.device.FLASH);
+        let mut keyboard = Keyboard::new();
+        keyboard.load_macros(storage.load());
+
         (
             Shared {
                 usb_dev,
                 usb_class,
-                layout: Layout::new(&LAYERS),
+                keyboard,
+                macros: None,
             },
             Local {
                 matrix,
                 right,
-                debouncer_left: Debouncer::new([[false; 5]; 4], [[false; 5]; 4], 5),
-                debouncer_right: Debouncer::new([[false; 5]; 4], [[false; 5]; 4], 5),
+                scanner: Scanner::new(),
                 timer,
+                storage,
             },
             init::Monotonics(),
         )
@@ -216,23 +203,43 @@ mod app {
         });
     }
 
-    #[task(priority = 2, capacity = 8, shared = [layout])]
+    #[task(priority = 2, capacity = 8, shared = [keyboard])]
     fn handle_event(c: handle_event::Context, event: Event) {
-        c.shared.layout.event(event)
+        c.shared.keyboard.event(event)
     }
 
-    #[task(priority = 2, shared = [usb_dev, usb_class, layout])]
+    /// Save the dynamic macros when asked to, out of the tasks that asked
+    ///
+    /// The program runs from the flash it writes: the CPU stalls while the
+    /// page is erased and written, about 40 ms during which no task runs.
+    /// The keyboard freezes for that time, the scans and USB polls missed
+    /// resuming once the page is saved.
+    #[idle(shared = [macros], local = [storage])]
+    fn idle(mut c: idle::Context) -> ! {
+        loop {
+            match c.shared.macros.lock(|m| m.take()) {
+                Some(bytes) => c.local.storage.save(&bytes),
+                None => cortex_m::asm::wfi(),
+            }
+        }
+    }
+
+    #[task(priority = 2, shared = [usb_dev, usb_class, keyboard, macros])]
     fn tick_keyberon(mut c: tick_keyberon::Context) {
-        let tick = c.shared.layout.tick();
+        let command = c.shared.keyboard.tick();
+        if let Some(Command::SaveMacros) = command {
+            let bytes = c.shared.keyboard.save_macros();
+            c.shared.macros.lock(|m| *m = Some(bytes));
+        }
         if c.shared.usb_dev.lock(|d| d.state()) != UsbDeviceState::Configured {
             return;
         }
-        if let CustomEvent::Release(_Infallible) = tick {
+        if let Some(Command::Bootloader) = command {
             unsafe {
                 cortex_m::asm::bootload(0x1FFFC800 as _);
             }
         }
-        let report: KbHidReport = c.shared.layout.keycodes().collect();
+        let report: KbHidReport = c.shared.keyboard.report();
         if !c
             .shared
             .usb_class
@@ -246,20 +253,14 @@ mod app {
     #[task(
         binds = TIM3,
         priority = 1,
-        local = [matrix, debouncer_left, debouncer_right, timer, right],
+        local = [matrix, scanner, timer, right],
     )]
     fn tick(c: tick::Context) {
         c.local.timer.wait().ok();
 
-        for event in c.local.debouncer_left.events(c.local.matrix.get().unwrap()) {
-            handle_event::spawn(event).unwrap();
-        }
-        for event in c
-            .local
-            .debouncer_right
-            .events(c.local.right.scan())
-            .map(|e| e.transform(|i, j| (i, 5 + j)))
-        {
+        let left = c.local.matrix.get().unwrap();
+        let right = c.local.right.scan();
+        for event in c.local.scanner.events(left, right) {
             handle_event::spawn(event).unwrap();
         }
         tick_keyberon::spawn().unwrap();
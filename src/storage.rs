@@ -0,0 +1,70 @@
+//! Storage of the dynamic macros in the last page of the flash
+//!
+//! The page is kept out of the program by `memory.x`.
+
+use ferris_core::dynamicmacro::STORAGE;
+use hal::stm32::FLASH;
+use stm32f0xx_hal as hal;
+
+/// Address of the last 2K page of the flash of the STM32F072
+const PAGE: u32 = 0x0801_F800;
+/// First key unlocking the flash controller
+const KEY1: u32 = 0x4567_0123;
+/// Second key unlocking the flash controller
+const KEY2: u32 = 0xCDEF_89AB;
+
+/// Dynamic macros storage in flash
+pub struct Storage {
+    /// Flash controller
+    flash: FLASH,
+}
+
+impl Storage {
+    /// Create a new storage using the flash controller
+    pub fn new(flash: FLASH) -> Self {
+        Self { flash }
+    }
+
+    /// Bytes saved in the page
+    pub fn load(&self) -> &'static [u8] {
+        unsafe { core::slice::from_raw_parts(PAGE as *const u8, STORAGE) }
+    }
+
+    /// Wait for the end of the current flash operation
+    fn wait(&self) {
+        while self.flash.sr.read().bsy().bit_is_set() {}
+    }
+
+    /// Erase the page and write `bytes` in it, unless they are already saved
+    ///
+    /// The CPU stalls on any read of the flash until the page is erased and
+    /// written, freezing the whole keyboard for about 40 ms.
+    pub fn save(&mut self, bytes: &[u8; STORAGE]) {
+        if self.load() == bytes {
+            return;
+        }
+        self.wait();
+        if self.flash.cr.read().lock().bit_is_set() {
+            self.flash.keyr.write(|w| unsafe { w.bits(KEY1) });
+            self.flash.keyr.write(|w| unsafe { w.bits(KEY2) });
+        }
+
+        self.flash.cr.modify(|_, w| w.per().set_bit());
+        self.flash.ar.write(|w| unsafe { w.bits(PAGE) });
+        self.flash.cr.modify(|_, w| w.strt().set_bit());
+        self.wait();
+        self.flash.cr.modify(|_, w| w.per().clear_bit());
+
+        // The flash is programmed by half-words
+        self.flash.cr.modify(|_, w| w.pg().set_bit());
+        for (i, chunk) in bytes.chunks(2).enumerate() {
+            let half_word = u16::from_le_bytes([chunk[0], chunk.get(1).copied().unwrap_or(0xff)]);
+            let address = (PAGE as usize + 2 * i) as *mut u16;
+            unsafe { core::ptr::write_volatile(address, half_word) };
+            self.wait();
+        }
+        self.flash
+            .cr
+            .modify(|_, w| w.pg().clear_bit().lock().set_bit());
+    }
+}
@@ -0,0 +1,126 @@
+//! Render the layers as ASCII art
+//!
+//! Each key shows its tap legend above its hold legend. Transparent keys are
+//! drawn as `_` and keys doing nothing as `X`, like in Kanata configurations.
+
+use crate::legend::{legend, Kind, Legend};
+use crate::KEYS;
+use ferris_core::action::Action;
+use std::fmt::Write;
+
+/// Inner width of a key
+const WIDTH: usize = 7;
+/// Space between the two halves
+const GAP: usize = 3;
+
+/// Center `text` in a key, truncating it if needed
+fn center(text: &str) -> Vec<char> {
+    let text: String = text.chars().take(WIDTH).collect();
+    format!("{:^width$}", text, width = WIDTH).chars().collect()
+}
+
+/// The two lines of text of a key
+fn lines(legend: &Legend) -> [Vec<char>; 2] {
+    match legend.kind {
+        Kind::Active => [center(&legend.tap), center(&legend.hold)],
+        Kind::Transparent => [center("_"), center("")],
+        Kind::NoOp => [center("X"), center("")],
+    }
+}
+
+/// Draw a key at the top-left corner `(x, y)` of `canvas`
+///
+/// Keys next to each other share their borders.
+fn draw(canvas: &mut [Vec<char>], x: usize, y: usize, legend: &Legend) {
+    for line in [y, y + 3] {
+        canvas[line][x] = '+';
+        canvas[line][x + WIDTH + 1] = '+';
+        for c in &mut canvas[line][x + 1..=x + WIDTH] {
+            *c = '-';
+        }
+    }
+    for (i, text) in lines(legend).iter().enumerate() {
+        let line = &mut canvas[y + 1 + i];
+        line[x] = '|';
+        line[x + WIDTH + 1] = '|';
+        line[x + 1..=x + WIDTH].copy_from_slice(text);
+    }
+}
+
+/// Render one layer
+pub fn layer<const C: usize, const R: usize>(
+    out: &mut String,
+    index: usize,
+    actions: &[[Action; C]; R],
+) {
+    let width = 10 * (WIDTH + 1) + 1 + GAP;
+    let mut canvas = vec![vec![' '; width]; 4 * 3 + 1];
+    for (row, col) in KEYS {
+        let x = col * (WIDTH + 1) + if col >= 5 { GAP } else { 0 };
+        draw(&mut canvas, x, row * 3, &legend(&actions[row][col]));
+    }
+    writeln!(out, "Layer {}", index).unwrap();
+    for line in canvas {
+        let line: String = line.into_iter().collect();
+        writeln!(out, "{}", line.trim_end()).unwrap();
+    }
+    writeln!(out).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keyberon::action::{k, l, HoldTapAction, HoldTapConfig};
+    use keyberon::key_code::KeyCode::*;
+
+    /// Shift when held, A when tapped
+    const SHIFT_A: Action = Action::HoldTap(&HoldTapAction {
+        timeout: 200,
+        tap_hold_interval: 0,
+        config: HoldTapConfig::Default,
+        hold: k(LShift),
+        tap: k(A),
+    });
+
+    /// A layer with a few keys on the left half, transparent thumb keys and
+    /// keys doing nothing elsewhere
+    fn actions() -> [[Action; 10]; 4] {
+        let mut actions = [[Action::NoOp; 10]; 4];
+        actions[0][0] = k(Q);
+        actions[1][0] = SHIFT_A;
+        actions[0][4] = k(BSpace);
+        actions[3][3] = l(1);
+        actions[3][4] = Action::Trans;
+        actions
+    }
+
+    #[test]
+    fn layer() {
+        let mut out = String::new();
+        super::layer(&mut out, 2, &actions());
+        let expected = [
+            "Layer 2",
+            "+-------+-------+-------+-------+-------+  +-------+-------+-------+-------+-------+",
+            "|   Q   |   X   |   X   |   X   | Bspc  |  |   X   |   X   |   X   |   X   |   X   |",
+            "|       |       |       |       |       |  |       |       |       |       |       |",
+            "+-------+-------+-------+-------+-------+  +-------+-------+-------+-------+-------+",
+            "|   A   |   X   |   X   |   X   |   X   |  |   X   |   X   |   X   |   X   |   X   |",
+            "|  Sft  |       |       |       |       |  |       |       |       |       |       |",
+            "+-------+-------+-------+-------+-------+  +-------+-------+-------+-------+-------+",
+            "|   X   |   X   |   X   |   X   |   X   |  |   X   |   X   |   X   |   X   |   X   |",
+            "|       |       |       |       |       |  |       |       |       |       |       |",
+            "+-------+-------+-------+-------+-------+  +-------+-------+-------+-------+-------+",
+            "                        |       |   _   |  |   X   |   X   |",
+            "                        |  L1   |       |  |       |       |",
+            "                        +-------+-------+  +-------+-------+",
+            "",
+        ];
+        assert_eq!(out.lines().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn truncated_legend() {
+        assert_eq!(center("Ctl+Alt+Del").iter().collect::<String>(), "Ctl+Alt");
+        assert_eq!(center("Tab").iter().collect::<String>(), "  Tab  ");
+    }
+}
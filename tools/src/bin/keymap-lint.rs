@@ -0,0 +1,19 @@
+//! Check the layers of the keymap for unreachable layers and dead keys
+//!
+//! Usage: `keymap-lint`, exits with an error if any error is found.
+
+use ferris_tools::lint::{lint, Severity};
+use ferris_tools::{CONFIG, LAYERS};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let diagnostics = lint(&LAYERS, CONFIG.tri_layers);
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,33 @@
+//! Render every layer of the keymap as ASCII art or SVG
+//!
+//! Usage: `keymap-viz [ascii|svg]`, the result being written on the standard
+//! output.
+
+use ferris_tools::{ascii, svg, LAYERS};
+use std::env;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let format = env::args().nth(1).unwrap_or_else(|| "ascii".to_string());
+    let mut out = String::new();
+    match format.as_str() {
+        "ascii" => {
+            for (i, layer) in LAYERS.iter().enumerate() {
+                ascii::layer(&mut out, i, layer);
+            }
+        }
+        "svg" => {
+            svg::header(&mut out, LAYERS.len());
+            for (i, layer) in LAYERS.iter().enumerate() {
+                svg::layer(&mut out, i, layer);
+            }
+            svg::footer(&mut out);
+        }
+        _ => {
+            eprintln!("Usage: keymap-viz [ascii|svg]");
+            return ExitCode::FAILURE;
+        }
+    }
+    print!("{}", out);
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,348 @@
+//! Short legends describing what a key does
+
+use ferris_core::action::{Action, CustomAction};
+use ferris_core::oneshot::OneShotTarget;
+use ferris_core::sequence::Sequence;
+use ferris_core::unicode::UnicodeMode;
+use keyberon::key_code::KeyCode::{self, *};
+use std::fmt;
+
+/// Kind of key, to draw them differently
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// The key does something on this layer
+    Active,
+    /// The key uses the action of the default layer
+    Transparent,
+    /// The key does nothing
+    NoOp,
+}
+
+/// What a key does when tapped and when held
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Legend {
+    /// Legend when tapped, may be empty
+    pub tap: String,
+    /// Legend when held, may be empty
+    pub hold: String,
+    /// Kind of key
+    pub kind: Kind,
+}
+
+impl Legend {
+    /// Legend of a key only active when tapped
+    fn tap(tap: String) -> Self {
+        Self {
+            tap,
+            hold: String::new(),
+            kind: Kind::Active,
+        }
+    }
+
+    /// Legend of a key only active when held
+    fn hold(hold: String) -> Self {
+        Self {
+            tap: String::new(),
+            hold,
+            kind: Kind::Active,
+        }
+    }
+}
+
+impl fmt::Display for Legend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.kind, self.tap.is_empty(), self.hold.is_empty()) {
+            (Kind::Transparent, _, _) => f.write_str("_"),
+            (Kind::NoOp, _, _) => f.write_str("X"),
+            (_, false, true) => f.write_str(&self.tap),
+            (_, true, false) => write!(f, "({})", self.hold),
+            _ => write!(f, "{} ({})", self.tap, self.hold),
+        }
+    }
+}
+
+/// Short name of a keycode
+pub fn keycode(kc: KeyCode) -> String {
+    let name = match kc {
+        Kb1 => "1",
+        Kb2 => "2",
+        Kb3 => "3",
+        Kb4 => "4",
+        Kb5 => "5",
+        Kb6 => "6",
+        Kb7 => "7",
+        Kb8 => "8",
+        Kb9 => "9",
+        Kb0 => "0",
+        Enter => "Ent",
+        Escape => "Esc",
+        BSpace => "Bspc",
+        Space => "Spc",
+        Minus => "-",
+        Equal => "=",
+        LBracket => "[",
+        RBracket => "]",
+        Bslash => "\\",
+        SColon => ";",
+        Quote => "'",
+        Grave => "`",
+        Comma => ",",
+        Dot => ".",
+        Slash => "/",
+        CapsLock => "Caps",
+        PScreen => "PrSc",
+        ScrollLock => "ScrLk",
+        Insert => "Ins",
+        Delete => "Del",
+        PgDown => "PgDn",
+        NumLock => "NumLk",
+        Application => "Menu",
+        LShift | RShift => "Sft",
+        LCtrl | RCtrl => "Ctl",
+        LAlt => "Alt",
+        RAlt => "AltGr",
+        LGui | RGui => "Gui",
+        VolUp | MediaVolUp => "Vol+",
+        VolDown | MediaVolDown => "Vol-",
+        Mute | MediaMute => "Mute",
+        MediaPlayPause => "Play",
+        MediaNextSong => "Next",
+        MediaPreviousSong => "Prev",
+        _ => return format!("{:?}", kc),
+    };
+    name.to_string()
+}
+
+/// Symbol typed with Shift and a keycode on a US layout
+fn shifted(kc: KeyCode) -> Option<&'static str> {
+    Some(match kc {
+        Kb1 => "!",
+        Kb2 => "@",
+        Kb3 => "#",
+        Kb4 => "$",
+        Kb5 => "%",
+        Kb6 => "^",
+        Kb7 => "&",
+        Kb8 => "*",
+        Kb9 => "(",
+        Kb0 => ")",
+        Minus => "_",
+        Equal => "+",
+        LBracket => "{",
+        RBracket => "}",
+        Bslash => "|",
+        SColon => ":",
+        Quote => "\"",
+        Grave => "~",
+        Comma => "<",
+        Dot => ">",
+        Slash => "?",
+        _ => return None,
+    })
+}
+
+/// Prefix of a modifier in a chord, such as `C-` in `C-Tab`
+fn prefix(kc: KeyCode) -> &'static str {
+    match kc {
+        LCtrl | RCtrl => "C-",
+        LShift | RShift => "S-",
+        LAlt => "A-",
+        RAlt => "AG-",
+        _ => "G-",
+    }
+}
+
+/// Legend of keycodes pressed together
+pub fn chord(kcs: &[KeyCode]) -> String {
+    let (mods, keys): (Vec<KeyCode>, Vec<KeyCode>) = kcs.iter().partition(|kc| kc.is_modifier());
+    match (mods.as_slice(), keys.as_slice()) {
+        ([LShift] | [RShift], [kc]) if shifted(*kc).is_some() => shifted(*kc).unwrap().to_string(),
+        (_, []) => mods
+            .iter()
+            .map(|kc| keycode(*kc))
+            .collect::<Vec<_>>()
+            .join("+"),
+        _ => {
+            let prefixes: String = mods.iter().map(|kc| prefix(*kc)).collect();
+            let keys = keys
+                .iter()
+                .map(|kc| keycode(*kc))
+                .collect::<Vec<_>>()
+                .join("+");
+            prefixes + &keys
+        }
+    }
+}
+
+/// Join non empty legends
+fn join<'a>(legends: impl Iterator<Item = &'a str>) -> String {
+    legends
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+/// Legend of an action
+pub fn legend(action: &Action) -> Legend {
+    match action {
+        Action::NoOp => Legend {
+            tap: String::new(),
+            hold: String::new(),
+            kind: Kind::NoOp,
+        },
+        Action::Trans => Legend {
+            tap: String::new(),
+            hold: String::new(),
+            kind: Kind::Transparent,
+        },
+        Action::KeyCode(kc) => Legend::tap(keycode(*kc)),
+        Action::MultipleKeyCodes(kcs) => Legend::tap(chord(kcs)),
+        Action::MultipleActions(actions) => {
+            let legends: Vec<Legend> = actions.iter().map(legend).collect();
+            Legend {
+                tap: join(legends.iter().map(|l| l.tap.as_str())),
+                hold: join(legends.iter().map(|l| l.hold.as_str())),
+                kind: Kind::Active,
+            }
+        }
+        Action::Layer(layer) => Legend::hold(format!("L{}", layer)),
+        Action::DefaultLayer(layer) => Legend::tap(format!("DF{}", layer)),
+        Action::HoldTap(ht) => {
+            let tap = legend(&ht.tap);
+            let hold = legend(&ht.hold);
+            Legend {
+                tap: join([tap.tap.as_str(), tap.hold.as_str()].into_iter()),
+                hold: join([hold.tap.as_str(), hold.hold.as_str()].into_iter()),
+                kind: Kind::Active,
+            }
+        }
+        Action::Custom(custom) => custom_legend(custom),
+    }
+}
+
+/// Legend of a custom action
+fn custom_legend(action: &CustomAction) -> Legend {
+    match action {
+        CustomAction::Bootloader => Legend::tap("Boot".to_string()),
+        CustomAction::Leader => Legend::tap("Lead".to_string()),
+        CustomAction::CapsWord => Legend::tap("CapsW".to_string()),
+        CustomAction::NumWord(layer) => Legend::tap(format!("NumW{}", layer)),
+        CustomAction::LayerLock => Legend::tap("Lock".to_string()),
+        CustomAction::Unicode(lower, _) => Legend::tap(lower.to_string()),
+        CustomAction::Char(c) => Legend::tap(c.to_string()),
+        CustomAction::AutoShift => Legend::tap("AShift".to_string()),
+        CustomAction::Repeat => Legend::tap("Rep".to_string()),
+        CustomAction::AltRepeat => Legend::tap("AltRep".to_string()),
+        CustomAction::SwapHands => Legend::tap("Swap".to_string()),
+        CustomAction::SwapHandsToggle => Legend::tap("SwapT".to_string()),
+        CustomAction::MacroRecord(slot) => Legend::tap(format!("Rec{}", slot + 1)),
+        CustomAction::MacroPlay(slot) => Legend::tap(format!("Play{}", slot + 1)),
+        CustomAction::UnicodeMode(mode) => Legend::tap(
+            match mode {
+                UnicodeMode::Linux => "UC Lnx",
+                UnicodeMode::MacOs => "UC Mac",
+                UnicodeMode::WinCompose(_) => "UC Win",
+                UnicodeMode::Compose(_) => "UC X11",
+            }
+            .to_string(),
+        ),
+        CustomAction::Sequence(Sequence::Text(text)) => Legend::tap(format!("{:?}", text)),
+        CustomAction::Sequence(Sequence::Key(kc)) => Legend::tap(keycode(*kc)),
+        CustomAction::Sequence(Sequence::Events(_)) => Legend::tap("Macro".to_string()),
+        CustomAction::Sequence(Sequence::Unicode(c)) => Legend::tap(c.to_string()),
+        CustomAction::OneShot(one_shot) => match one_shot.target {
+            OneShotTarget::Modifier(kc) => Legend::tap(format!("OS {}", keycode(kc))),
+            OneShotTarget::Layer(layer) => Legend::hold(format!("OSL{}", layer)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferris_core::action::{
+        ch, num_word, osl, osm, play_macro, string, unicode, unicode_mode, CAPS_WORD, LAYER_LOCK,
+    };
+    use keyberon::action::{d, k, l, m, HoldTapAction, HoldTapConfig};
+
+    /// A hold-tap of `hold` and `tap`
+    fn hold_tap(hold: Action, tap: Action) -> Action {
+        Action::HoldTap(Box::leak(Box::new(HoldTapAction {
+            timeout: 200,
+            tap_hold_interval: 0,
+            config: HoldTapConfig::Default,
+            hold,
+            tap,
+        })))
+    }
+
+    /// A static slice of `items`, as referenced by the actions
+    fn leak<T: Clone>(items: &[T]) -> &'static &'static [T] {
+        Box::leak(Box::new(&*Box::leak(items.to_vec().into_boxed_slice())))
+    }
+
+    /// Displayed legend of an action
+    fn display(action: &Action) -> String {
+        legend(action).to_string()
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(display(&k(A)), "A");
+        assert_eq!(display(&k(Kb1)), "1");
+        assert_eq!(display(&k(BSpace)), "Bspc");
+        assert_eq!(display(&k(F13)), "F13");
+        assert_eq!(display(&Action::Trans), "_");
+        assert_eq!(display(&Action::NoOp), "X");
+        assert_eq!(legend(&Action::Trans).kind, Kind::Transparent);
+        assert_eq!(legend(&Action::NoOp).kind, Kind::NoOp);
+    }
+
+    #[test]
+    fn chords() {
+        assert_eq!(chord(&[LShift, Kb1]), "!");
+        assert_eq!(chord(&[RShift, Slash]), "?");
+        assert_eq!(chord(&[LShift, A]), "S-A");
+        assert_eq!(chord(&[LCtrl, LShift, Tab]), "C-S-Tab");
+        assert_eq!(chord(&[LCtrl, LAlt]), "Ctl+Alt");
+        assert_eq!(display(&m(leak(&[RAlt, E]))), "AG-E");
+    }
+
+    #[test]
+    fn layers() {
+        assert_eq!(display(&l(2)), "(L2)");
+        assert_eq!(display(&d(1)), "DF1");
+        assert_eq!(display(&osl(3, 500)), "(OSL3)");
+    }
+
+    #[test]
+    fn hold_taps() {
+        assert_eq!(display(&hold_tap(k(LShift), k(A))), "A (Sft)");
+        assert_eq!(display(&hold_tap(l(1), k(Space))), "Spc (L1)");
+        assert_eq!(display(&hold_tap(k(LCtrl), CAPS_WORD)), "CapsW (Ctl)");
+        assert_eq!(display(&hold_tap(osl(2, 500), k(Escape))), "Esc (OSL2)");
+        let tap_hold = legend(&hold_tap(k(RAlt), l(4)));
+        assert_eq!(tap_hold.tap, "L4");
+        assert_eq!(tap_hold.hold, "AltGr");
+    }
+
+    #[test]
+    fn multiple_actions() {
+        let actions = Action::MultipleActions(leak(&[k(LCtrl), l(1)]));
+        assert_eq!(display(&actions), "Ctl (L1)");
+    }
+
+    #[test]
+    fn custom_actions() {
+        assert_eq!(display(&CAPS_WORD), "CapsW");
+        assert_eq!(display(&num_word(3)), "NumW3");
+        assert_eq!(display(&LAYER_LOCK), "Lock");
+        assert_eq!(display(&unicode('é', 'É')), "é");
+        assert_eq!(display(&ch('€')), "€");
+        assert_eq!(display(&play_macro(0)), "Play1");
+        assert_eq!(display(&osm(LShift, 500)), "OS Sft");
+        assert_eq!(display(&string("hi")), "\"hi\"");
+        assert_eq!(display(&unicode_mode(UnicodeMode::Linux)), "UC Lnx");
+        assert_eq!(display(&unicode_mode(UnicodeMode::Compose(RAlt))), "UC X11");
+    }
+}
@@ -0,0 +1,32 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+//! Host tools to inspect the keymaps of the Ferris firmware
+//!
+//! The keymap selected with the `keymap_*` feature comes from `ferris-core`,
+//! like in the firmware, so the tools always show what gets flashed.
+
+pub use ferris_core::{CONFIG, LAYERS};
+
+pub mod ascii;
+pub mod legend;
+pub mod lint;
+pub mod svg;
+
+/// Matrix positions of the 34 keys of the Ferris, row by row
+///
+/// The first 5 columns are on the left half, the other 5 on the right half.
+/// Only the 4 middle keys of the last row are wired, for the thumbs.
+pub const KEYS: [(usize, usize); 34] = {
+    let mut keys = [(0, 0); 34];
+    let mut i = 0;
+    while i < 30 {
+        keys[i] = (i / 10, i % 10);
+        i += 1;
+    }
+    while i < 34 {
+        keys[i] = (3, i - 30 + 3);
+        i += 1;
+    }
+    keys
+};
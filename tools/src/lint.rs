@@ -0,0 +1,737 @@
+//! Static analysis of the layers
+//!
+//! The layers reachable from the default layer 0 are explored the way
+//! keyberon resolves actions. keyberon adds up the layers held by `l(x)`
+//! keys: holding `l(2)` on the layer 1, itself held, activates the layer 3.
+//! When no layer is held, the default layer is active, and transparent keys
+//! use its actions. One-shot layers and the layer of a tri-layer, active when
+//! all its layers are held, are activated as default layer, and `d(x)`
+//! changes the default layer.
+//!
+//! Errors are layer indices beyond the layer count, layers held together
+//! adding up past the last layer, unreachable layers and default layers
+//! without a way back to layer 0. Warnings are transparent keys over keys
+//! doing nothing, keys that cannot be pressed and thumb keys activating a
+//! layer that do not activate it from another layer.
+
+use crate::legend::legend;
+use crate::KEYS;
+use ferris_core::action::{Action, CustomAction};
+use ferris_core::oneshot::OneShotTarget;
+use ferris_core::trilayer::TriLayer;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// How bad a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The keymap works but something looks wrong
+    Warning,
+    /// The keymap is broken
+    Error,
+}
+
+/// Something wrong found in the layers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// How bad it is
+    pub severity: Severity,
+    /// Layer where the problem is
+    pub layer: usize,
+    /// Description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: layer {}: {}", severity, self.layer, self.message)
+    }
+}
+
+/// A layer change done by an action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LayerChange {
+    /// Layer added to the layers held while the key is held
+    Momentary(usize),
+    /// Layer activated as default layer for the next key
+    OneShot(usize),
+    /// New default layer
+    Default(usize),
+}
+
+/// Layer changes an action can do, whether tapped or held
+fn layer_changes(action: &Action, changes: &mut Vec<LayerChange>) {
+    match action {
+        Action::Layer(layer) => changes.push(LayerChange::Momentary(*layer)),
+        Action::DefaultLayer(layer) => changes.push(LayerChange::Default(*layer)),
+        Action::MultipleActions(actions) => {
+            for action in actions.iter() {
+                layer_changes(action, changes);
+            }
+        }
+        Action::HoldTap(ht) => {
+            layer_changes(&ht.hold, changes);
+            layer_changes(&ht.tap, changes);
+        }
+        Action::Custom(CustomAction::OneShot(one_shot)) => {
+            if let OneShotTarget::Layer(layer) = one_shot.target {
+                changes.push(LayerChange::OneShot(layer));
+            }
+        }
+        Action::Custom(CustomAction::NumWord(layer)) => changes.push(LayerChange::Default(*layer)),
+        _ => {}
+    }
+}
+
+/// Describe the key at `(row, col)`
+fn key(row: usize, col: usize) -> String {
+    format!("key ({}, {})", row, col)
+}
+
+/// Keys held while exploring the layers
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Held {
+    /// Layer active when no layer is held: the default layer, or the layer
+    /// of a one-shot action or of a tri-layer
+    base: usize,
+    /// Keys holding a layer for the layout, with their layer, sorted
+    layers: Vec<((usize, usize), usize)>,
+    /// Keys held, including the ones released for the layout by a
+    /// tri-layer, sorted
+    keys: Vec<(usize, usize)>,
+}
+
+impl Held {
+    /// Sum of the layers held
+    fn sum(&self) -> usize {
+        self.layers.iter().map(|(_, layer)| layer).sum()
+    }
+
+    /// Layer active: the sum of the layers held, if any, or the base layer
+    fn current(&self) -> usize {
+        if self.layers.is_empty() {
+            self.base
+        } else {
+            self.sum()
+        }
+    }
+
+    /// The same keys, and `key` holding `layer`
+    fn hold(&self, key: (usize, usize), layer: usize) -> Self {
+        let mut held = self.clone();
+        held.layers.push((key, layer));
+        held.layers.sort();
+        held.keys.push(key);
+        held.keys.sort();
+        held
+    }
+}
+
+/// Layers reachable from a default layer
+struct Reachable {
+    /// Layers active while holding keys, the default layer included
+    layers: BTreeSet<usize>,
+    /// Keys holding a layer in every way found to activate each layer
+    held: BTreeMap<usize, BTreeSet<(usize, usize)>>,
+}
+
+/// Analysis of a set of layers
+struct Linter<'a, const C: usize, const R: usize, const L: usize> {
+    /// The layers
+    layers: &'a [[[Action; C]; R]; L],
+    /// Layers activated when some layers are held together
+    tri_layers: &'a [TriLayer],
+    /// Diagnostics found so far
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a, const C: usize, const R: usize, const L: usize> Linter<'a, C, R, L> {
+    /// Record a diagnostic
+    fn report(&mut self, severity: Severity, layer: usize, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            layer,
+            message,
+        });
+    }
+
+    /// Action of a key on `layer`, resolving transparency with `default`
+    fn action(&self, default: usize, layer: usize, row: usize, col: usize) -> &'a Action {
+        match &self.layers[layer][row][col] {
+            Action::Trans => &self.layers[default][row][col],
+            action => action,
+        }
+    }
+
+    /// Layer changes of a key on `layer`, resolving transparency with `default`
+    fn changes(&self, default: usize, layer: usize, row: usize, col: usize) -> Vec<LayerChange> {
+        let mut changes = Vec::new();
+        layer_changes(self.action(default, layer, row, col), &mut changes);
+        changes
+    }
+
+    /// Report layer changes to layers that do not exist
+    fn check_indices(&mut self) {
+        for layer in 0..L {
+            for (row, col) in KEYS {
+                let mut changes = Vec::new();
+                layer_changes(&self.layers[layer][row][col], &mut changes);
+                for change in changes {
+                    let (LayerChange::Momentary(target)
+                    | LayerChange::OneShot(target)
+                    | LayerChange::Default(target)) = change;
+                    if target >= L {
+                        let message = format!(
+                            "{} uses layer {} but there are only {} layers",
+                            key(row, col),
+                            target,
+                            L
+                        );
+                        self.report(Severity::Error, layer, message);
+                    }
+                }
+            }
+        }
+        for tri_layer in self.tri_layers {
+            if tri_layer.layer >= L {
+                let message = format!(
+                    "tri-layer of layers {:?} uses layer {} but there are only {} layers",
+                    tri_layer.layers, tri_layer.layer, L
+                );
+                self.report(Severity::Error, tri_layer.layer, message);
+            }
+        }
+    }
+
+    /// Activate the tri-layer whose layers are all held, if any: the keys
+    /// holding them are released for the layout and its layer becomes the
+    /// base layer
+    fn tri_layer(&self, mut held: Held) -> Held {
+        let layers: Vec<usize> = held.layers.iter().map(|(_, layer)| *layer).collect();
+        let tri_layer = self
+            .tri_layers
+            .iter()
+            .find(|t| t.layer < L && t.layers.iter().all(|l| layers.contains(l)));
+        if let Some(tri_layer) = tri_layer {
+            held.layers
+                .retain(|(_, layer)| !tri_layer.layers.contains(layer));
+            held.base = tri_layer.layer;
+        }
+        held
+    }
+
+    /// Explore the layers activated by holding keys from `default`
+    ///
+    /// Keys holding a layer that adds up with the layers held past the last
+    /// layer are reported, and so are the thumb keys holding a layer on the
+    /// default layer that do not activate it from the other layers.
+    fn explore(&mut self, default: usize) -> Reachable {
+        let thumbs: Vec<((usize, usize), usize)> = KEYS
+            .into_iter()
+            .filter(|(row, _)| *row == 3)
+            .filter_map(|(row, col)| {
+                let mut changes = Vec::new();
+                layer_changes(&self.layers[default][row][col], &mut changes);
+                match changes.first() {
+                    Some(LayerChange::Momentary(target)) => Some(((row, col), *target)),
+                    _ => None,
+                }
+            })
+            .collect();
+        let mut reachable = Reachable {
+            layers: BTreeSet::new(),
+            held: BTreeMap::new(),
+        };
+        let start = Held {
+            base: default,
+            layers: Vec::new(),
+            keys: Vec::new(),
+        };
+        let mut seen = BTreeSet::from([start.clone()]);
+        let mut todo = vec![start];
+        let mut diagnostics = Vec::new();
+        while let Some(held) = todo.pop() {
+            let current = held.current();
+            reachable.layers.insert(current);
+            let keys: BTreeSet<(usize, usize)> = held.layers.iter().map(|(key, _)| *key).collect();
+            reachable
+                .held
+                .entry(current)
+                .and_modify(|held| held.retain(|key| keys.contains(key)))
+                .or_insert(keys);
+            for (row, col) in KEYS.into_iter().filter(|k| !held.keys.contains(k)) {
+                let thumb = thumbs.iter().find(|(thumb, _)| *thumb == (row, col));
+                let target = thumb.map(|&(_, target)| target);
+                // Whether the thumb key activates its layer, or a tri-layer
+                let mut activates = false;
+                for change in self.changes(held.base, current, row, col) {
+                    let next = match change {
+                        LayerChange::Momentary(layer) if layer < L => {
+                            let next = self.tri_layer(held.hold((row, col), layer));
+                            activates |= next.layers.len() <= held.layers.len()
+                                || Some(held.sum() + layer) == target;
+                            if next.current() >= L {
+                                let message = format!(
+                                    "{} holds layer {} on top of the layers held, adding up to layer {} but there are only {} layers",
+                                    key(row, col),
+                                    layer,
+                                    next.current(),
+                                    L
+                                );
+                                diagnostics.push((Severity::Error, current, message));
+                                continue;
+                            }
+                            next
+                        }
+                        // Used by the next key, once the keys held are released
+                        LayerChange::OneShot(layer) if layer < L => Held {
+                            base: layer,
+                            layers: Vec::new(),
+                            keys: Vec::new(),
+                        },
+                        _ => continue,
+                    };
+                    if seen.insert(next.clone()) {
+                        todo.push(next);
+                    }
+                }
+                match target {
+                    Some(target) if !activates && current != default && current != target => {
+                        let message = format!(
+                            "thumb {} does not activate layer {} like on the default layer",
+                            key(row, col),
+                            target
+                        );
+                        diagnostics.push((Severity::Warning, current, message));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        for (severity, layer, message) in diagnostics {
+            self.report(severity, layer, message);
+        }
+        reachable
+    }
+
+    /// Default layers reachable from `default`
+    fn defaults_from(&self, default: usize, momentary: &BTreeSet<usize>) -> BTreeSet<usize> {
+        let mut defaults = BTreeSet::new();
+        for &layer in momentary {
+            for (row, col) in KEYS {
+                for change in self.changes(default, layer, row, col) {
+                    if let LayerChange::Default(target) = change {
+                        if target < L {
+                            defaults.insert(target);
+                        }
+                    }
+                }
+            }
+        }
+        defaults
+    }
+
+    /// Transparent keys over keys doing nothing
+    fn check_transparency(&mut self, default: usize, momentary: &BTreeSet<usize>) {
+        for &layer in momentary {
+            let keys: Vec<String> = KEYS
+                .into_iter()
+                .filter(|&(row, col)| {
+                    matches!(self.layers[layer][row][col], Action::Trans)
+                        && (layer == default
+                            || matches!(self.layers[default][row][col], Action::NoOp))
+                })
+                .map(|(row, col)| format!("({}, {})", row, col))
+                .collect();
+            if keys.is_empty() {
+                continue;
+            }
+            let message = if layer == default {
+                format!(
+                    "transparent keys do nothing when this is the default layer: {}",
+                    keys.join(", ")
+                )
+            } else {
+                format!(
+                    "transparent keys are over nothing on the default layer {}: {}",
+                    default,
+                    keys.join(", ")
+                )
+            };
+            self.report(Severity::Warning, layer, message);
+        }
+    }
+
+    /// Keys that cannot be pressed since they are held to activate their layer
+    fn check_dead_keys(&mut self, default: usize, reachable: &Reachable) {
+        for (&layer, keys) in &reachable.held {
+            if layer == default {
+                continue;
+            }
+            for &(row, col) in keys {
+                let action = &self.layers[layer][row][col];
+                if !matches!(action, Action::Trans | Action::NoOp) {
+                    let message = format!(
+                        "{} `{}` cannot be pressed since it is held to activate this layer",
+                        key(row, col),
+                        legend(action)
+                    );
+                    self.report(Severity::Warning, layer, message);
+                }
+            }
+        }
+    }
+
+    /// Run every check
+    fn run(mut self) -> Vec<Diagnostic> {
+        self.check_indices();
+        let mut defaults = BTreeSet::from([0]);
+        let mut edges: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); L];
+        let mut reachable = BTreeSet::new();
+        let mut todo = vec![0];
+        while let Some(default) = todo.pop() {
+            let momentary = self.explore(default);
+            edges[default] = self.defaults_from(default, &momentary.layers);
+            for &target in &edges[default] {
+                if defaults.insert(target) {
+                    todo.push(target);
+                }
+            }
+            self.check_transparency(default, &momentary.layers);
+            self.check_dead_keys(default, &momentary);
+            reachable.extend(momentary.layers);
+        }
+        for layer in (0..L).filter(|l| !reachable.contains(l)) {
+            self.report(Severity::Error, layer, "unreachable layer".to_string());
+        }
+        for &default in defaults.iter().filter(|&&d| d != 0) {
+            let mut seen = BTreeSet::from([default]);
+            let mut todo = vec![default];
+            while let Some(layer) = todo.pop() {
+                for &target in &edges[layer] {
+                    if seen.insert(target) {
+                        todo.push(target);
+                    }
+                }
+            }
+            if !seen.contains(&0) {
+                let message =
+                    "default layer without any way back to the default layer 0".to_string();
+                self.report(Severity::Error, default, message);
+            }
+        }
+        self.diagnostics.sort_by(|a, b| {
+            (a.layer, a.severity, &a.message).cmp(&(b.layer, b.severity, &b.message))
+        });
+        self.diagnostics.dedup();
+        self.diagnostics
+    }
+}
+
+/// Analyse the layers and return every problem found
+pub fn lint<const C: usize, const R: usize, const L: usize>(
+    layers: &[[[Action; C]; R]; L],
+    tri_layers: &[TriLayer],
+) -> Vec<Diagnostic> {
+    Linter {
+        layers,
+        tri_layers,
+        diagnostics: Vec::new(),
+    }
+    .run()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferris_core::action::osl;
+    use keyberon::action::{d, k, l};
+    use keyberon::key_code::KeyCode::*;
+
+    /// A layer doing nothing
+    const EMPTY: [[Action; 10]; 4] = [[Action::NoOp; 10]; 4];
+
+    /// An error on `layer`
+    fn error(layer: usize, message: &str) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            layer,
+            message: message.to_string(),
+        }
+    }
+
+    /// A warning on `layer`
+    fn warning(layer: usize, message: &str) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            layer,
+            message: message.to_string(),
+        }
+    }
+
+    /// Layers where layer 0 holds layer 1 on `(3, 3)`, and layer 1 goes back
+    fn two_layers() -> [[[Action; 10]; 4]; 2] {
+        let mut layers = [EMPTY; 2];
+        layers[0][0][0] = k(A);
+        layers[0][3][3] = l(1);
+        layers[1][0][0] = k(Kb1);
+        layers
+    }
+
+    #[test]
+    fn clean() {
+        assert_eq!(lint(&two_layers(), &[]), vec![]);
+    }
+
+    #[test]
+    fn layer_beyond_count() {
+        let mut layers = two_layers();
+        layers[1][0][1] = l(5);
+        assert_eq!(
+            lint(&layers, &[]),
+            vec![error(
+                1,
+                "key (0, 1) uses layer 5 but there are only 2 layers"
+            )]
+        );
+    }
+
+    #[test]
+    fn tri_layer_beyond_count() {
+        let tri_layers = [TriLayer {
+            layers: &[0, 1],
+            layer: 2,
+        }];
+        assert_eq!(
+            lint(&two_layers(), &tri_layers),
+            vec![error(
+                2,
+                "tri-layer of layers [0, 1] uses layer 2 but there are only 2 layers"
+            )]
+        );
+    }
+
+    #[test]
+    fn unreachable_layer() {
+        let mut layers = two_layers();
+        layers[0][3][3] = Action::NoOp;
+        assert_eq!(lint(&layers, &[]), vec![error(1, "unreachable layer")]);
+    }
+
+    #[test]
+    fn reachable_by_tri_layer() {
+        let mut layers = [EMPTY; 4];
+        layers[0][3][3] = l(1);
+        layers[0][3][6] = l(2);
+        layers[1][3][6] = l(2);
+        layers[2][3][3] = l(1);
+        layers[3][3][3] = l(1);
+        layers[3][3][6] = l(2);
+        let tri_layers = [TriLayer {
+            layers: &[1, 2],
+            layer: 3,
+        }];
+        assert_eq!(lint(&layers, &tri_layers), vec![]);
+    }
+
+    #[test]
+    fn default_layer_without_way_back() {
+        let mut layers = two_layers();
+        layers[0][3][3] = d(1);
+        assert_eq!(
+            lint(&layers, &[]),
+            vec![error(
+                1,
+                "default layer without any way back to the default layer 0"
+            )]
+        );
+        layers[1][3][4] = d(0);
+        assert_eq!(lint(&layers, &[]), vec![]);
+    }
+
+    #[test]
+    fn transparent_over_nothing() {
+        let mut layers = two_layers();
+        layers[1][0][0] = Action::Trans;
+        layers[1][0][1] = Action::Trans;
+        assert_eq!(
+            lint(&layers, &[]),
+            vec![warning(
+                1,
+                "transparent keys are over nothing on the default layer 0: (0, 1)"
+            )]
+        );
+    }
+
+    #[test]
+    fn transparent_on_default_layer() {
+        let mut layers = two_layers();
+        layers[0][3][3] = d(1);
+        layers[1][0][0] = Action::Trans;
+        layers[1][3][4] = d(0);
+        assert_eq!(
+            lint(&layers, &[]),
+            vec![warning(
+                1,
+                "transparent keys do nothing when this is the default layer: (0, 0)"
+            )]
+        );
+    }
+
+    #[test]
+    fn key_held_to_activate_its_layer() {
+        let mut layers = two_layers();
+        layers[1][3][3] = k(Space);
+        assert_eq!(
+            lint(&layers, &[]),
+            vec![warning(
+                1,
+                "key (3, 3) `Spc` cannot be pressed since it is held to activate this layer"
+            )]
+        );
+    }
+
+    #[test]
+    fn layers_adding_up() {
+        // Layer 2 is held by both thumbs, the second one holding layer 1 on
+        // top of layer 1
+        let mut layers = [EMPTY; 3];
+        layers[0][3][3] = l(1);
+        layers[1][3][6] = l(1);
+        assert_eq!(lint(&layers, &[]), vec![]);
+    }
+
+    #[test]
+    fn layers_adding_up_past_count() {
+        let mut layers = two_layers();
+        layers[1][3][4] = l(1);
+        assert_eq!(
+            lint(&layers, &[]),
+            vec![error(
+                1,
+                "key (3, 4) holds layer 1 on top of the layers held, adding up to layer 2 but there are only 2 layers"
+            )]
+        );
+    }
+
+    #[test]
+    fn transparent_key_adding_up_past_count() {
+        let mut layers = two_layers();
+        layers[0][3][4] = l(1);
+        layers[1][3][4] = Action::Trans;
+        assert_eq!(
+            lint(&layers, &[]),
+            vec![error(
+                1,
+                "key (3, 4) holds layer 1 on top of the layers held, adding up to layer 2 but there are only 2 layers"
+            )]
+        );
+    }
+
+    #[test]
+    fn tri_layer_instead_of_sum() {
+        let mut layers = [EMPTY; 4];
+        layers[0][3][3] = l(1);
+        layers[0][3][6] = l(3);
+        layers[1][3][6] = l(3);
+        layers[3][3][3] = l(1);
+        layers[2][3][3] = l(1);
+        layers[2][3][6] = l(3);
+        let tri_layers = [TriLayer {
+            layers: &[1, 3],
+            layer: 2,
+        }];
+        assert_eq!(lint(&layers, &tri_layers), vec![]);
+    }
+
+    #[test]
+    fn one_shot_layer() {
+        // The one-shot layer is used once layer 1 is released, so its thumb
+        // holds layer 1 without adding it up to layer 2
+        let mut layers = [EMPTY; 3];
+        layers[0][3][3] = l(1);
+        layers[1][0][0] = osl(2, 1000);
+        layers[2][3][3] = l(1);
+        assert_eq!(lint(&layers, &[]), vec![]);
+    }
+
+    #[test]
+    fn thumb_key_not_activating_its_layer() {
+        let mut layers = [EMPTY; 3];
+        layers[0][3][3] = l(1);
+        layers[0][3][6] = l(2);
+        layers[1][3][6] = l(1);
+        layers[2][3][3] = k(Space);
+        assert_eq!(
+            lint(&layers, &[]),
+            vec![warning(
+                2,
+                "thumb key (3, 3) does not activate layer 1 like on the default layer"
+            )]
+        );
+    }
+
+    /// Errors found in the keymap selected with the `keymap_*` feature
+    fn keymap_errors() -> Vec<Diagnostic> {
+        lint(&crate::LAYERS, crate::CONFIG.tri_layers)
+            .into_iter()
+            .filter(|d| d.severity == Severity::Error)
+            .collect()
+    }
+
+    #[test]
+    fn thumb_key_adding_up_to_another_layer() {
+        let mut layers = [EMPTY; 4];
+        layers[0][3][3] = l(1);
+        layers[0][3][6] = l(2);
+        layers[1][3][6] = l(2);
+        layers[2][3][3] = l(1);
+        assert_eq!(
+            lint(&layers, &[]),
+            vec![
+                warning(
+                    1,
+                    "thumb key (3, 6) does not activate layer 2 like on the default layer"
+                ),
+                warning(
+                    2,
+                    "thumb key (3, 3) does not activate layer 1 like on the default layer"
+                )
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "keymap_basic")]
+    fn keymap_basic() {
+        assert_eq!(keymap_errors(), vec![]);
+    }
+
+    #[test]
+    #[cfg(feature = "keymap_borisfaure")]
+    fn keymap_borisfaure() {
+        assert_eq!(keymap_errors(), vec![]);
+    }
+
+    #[test]
+    #[cfg(feature = "keymap_pierrec83")]
+    fn keymap_pierrec83() {
+        assert_eq!(keymap_errors(), vec![]);
+    }
+
+    #[test]
+    #[cfg(feature = "keymap_zmk")]
+    fn keymap_zmk() {
+        assert_eq!(keymap_errors(), vec![]);
+    }
+
+    #[test]
+    #[cfg(feature = "keymap_kanata")]
+    fn keymap_kanata() {
+        assert_eq!(keymap_errors(), vec![]);
+    }
+}
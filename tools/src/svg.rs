@@ -0,0 +1,218 @@
+//! Render the layers as SVG on the physical shape of the Ferris
+//!
+//! Tap legends are drawn in the middle of the keys and hold legends below
+//! them, in a different color. Transparent keys have a dashed outline and
+//! keys doing nothing are greyed out.
+
+use crate::legend::{legend, Kind};
+use crate::KEYS;
+use ferris_core::action::Action;
+use std::fmt::Write;
+
+/// Size of a key, in pixels
+const KEY: f32 = 60.0;
+/// Space around the keys and between the layers, in pixels
+const MARGIN: f32 = 20.0;
+/// Height of a layer, with its title, in pixels
+const LAYER_HEIGHT: f32 = 5.5 * KEY + MARGIN;
+/// Vertical offset of each column of the left half, in keys
+const STAGGER: [f32; 5] = [0.6, 0.25, 0.0, 0.25, 0.4];
+
+/// Position, in keys, of the top-left corner of a key on the left half
+fn left_position(row: usize, col: usize) -> (f32, f32) {
+    match (row, col) {
+        (3, 3) => (3.4, 4.0),
+        (3, 4) => (4.5, 4.2),
+        _ => (col as f32, row as f32 + STAGGER[col]),
+    }
+}
+
+/// Position, in keys, of the top-left corner of a key
+///
+/// The right half mirrors the left one, one key apart.
+fn position(row: usize, col: usize) -> (f32, f32) {
+    if col < 5 {
+        left_position(row, col)
+    } else {
+        let (x, y) = left_position(row, 9 - col);
+        (10.0 - x, y)
+    }
+}
+
+/// Escape text for SVG
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Total width of the SVG
+fn width() -> f32 {
+    11.0 * KEY + 2.0 * MARGIN
+}
+
+/// Start the SVG document for `layers` layers
+pub fn header(out: &mut String, layers: usize) {
+    let height = layers as f32 * LAYER_HEIGHT + MARGIN;
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" text-anchor="middle">"#,
+        w = width(),
+        h = height
+    )
+    .unwrap();
+    writeln!(out, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+}
+
+/// End the SVG document
+pub fn footer(out: &mut String) {
+    writeln!(out, "</svg>").unwrap();
+}
+
+/// Render one layer
+pub fn layer<const C: usize, const R: usize>(
+    out: &mut String,
+    index: usize,
+    actions: &[[Action; C]; R],
+) {
+    let top = index as f32 * LAYER_HEIGHT + MARGIN;
+    writeln!(
+        out,
+        r#"<text x="{}" y="{}" font-size="18" font-weight="bold">Layer {}</text>"#,
+        width() / 2.0,
+        top + 14.0,
+        index
+    )
+    .unwrap();
+    for (row, col) in KEYS {
+        let legend = legend(&actions[row][col]);
+        let (x, y) = position(row, col);
+        let x = MARGIN + x * KEY;
+        let y = top + MARGIN + y * KEY;
+        let (fill, stroke) = match legend.kind {
+            Kind::Active => ("#f4f4f4", r##"stroke="#444""##),
+            Kind::Transparent => ("white", r##"stroke="#aaa" stroke-dasharray="4 3""##),
+            Kind::NoOp => ("#d8d8d8", r##"stroke="#888""##),
+        };
+        writeln!(
+            out,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" rx="6" fill="{}" {}/>"#,
+            x + 2.0,
+            y + 2.0,
+            KEY - 4.0,
+            KEY - 4.0,
+            fill,
+            stroke
+        )
+        .unwrap();
+        let cx = x + KEY / 2.0;
+        let (tap, hold) = match legend.kind {
+            Kind::Active => (legend.tap, legend.hold),
+            Kind::Transparent => ("▽".to_string(), String::new()),
+            Kind::NoOp => ("✕".to_string(), String::new()),
+        };
+        if !tap.is_empty() {
+            writeln!(
+                out,
+                r#"<text x="{}" y="{}" font-size="14">{}</text>"#,
+                cx,
+                y + KEY / 2.0 + 2.0,
+                escape(&tap)
+            )
+            .unwrap();
+        }
+        if !hold.is_empty() {
+            writeln!(
+                out,
+                r##"<text x="{}" y="{}" font-size="11" fill="#1565c0">{}</text>"##,
+                cx,
+                y + KEY - 10.0,
+                escape(&hold)
+            )
+            .unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keyberon::action::{k, l, m, HoldTapAction, HoldTapConfig};
+    use keyberon::key_code::KeyCode::*;
+
+    /// Shift when held, A when tapped
+    const SHIFT_A: Action = Action::HoldTap(&HoldTapAction {
+        timeout: 200,
+        tap_hold_interval: 0,
+        config: HoldTapConfig::Default,
+        hold: k(LShift),
+        tap: k(A),
+    });
+
+    /// `<`, to be escaped
+    const LESS_THAN: Action = m(&[LShift, Comma].as_slice());
+
+    /// A layer with a few keys, transparent keys on the right half and keys
+    /// doing nothing elsewhere
+    fn actions() -> [[Action; 10]; 4] {
+        let mut actions = [[Action::NoOp; 10]; 4];
+        actions[0][0] = k(Q);
+        actions[1][0] = SHIFT_A;
+        actions[0][1] = LESS_THAN;
+        actions[3][3] = l(1);
+        actions[0][5..].fill(Action::Trans);
+        actions
+    }
+
+    /// SVG document of `layers` copies of `actions()`
+    fn document(layers: usize) -> String {
+        let mut out = String::new();
+        header(&mut out, layers);
+        for index in 0..layers {
+            layer(&mut out, index, &actions());
+        }
+        footer(&mut out);
+        out
+    }
+
+    #[test]
+    fn document_size() {
+        let out = document(2);
+        assert!(out.starts_with(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="700" height="720" viewBox="0 0 700 720""#
+        ));
+        assert!(out.ends_with("</svg>\n"));
+        assert!(out.contains(r#"font-weight="bold">Layer 0</text>"#));
+        assert!(out.contains(r#"font-weight="bold">Layer 1</text>"#));
+    }
+
+    #[test]
+    fn keys() {
+        let out = document(1);
+        // The background and the 34 keys
+        assert_eq!(out.matches("<rect ").count(), 1 + 34);
+        assert_eq!(out.matches("stroke-dasharray").count(), 5);
+        assert_eq!(out.matches(r##"fill="#d8d8d8""##).count(), 34 - 5 - 4);
+        assert_eq!(out.matches(">✕</text>").count(), 34 - 5 - 4);
+        assert_eq!(out.matches(">▽</text>").count(), 5);
+    }
+
+    #[test]
+    fn legends() {
+        let out = document(1);
+        assert!(out.contains(r#"<text x="50" y="108" font-size="14">Q</text>"#));
+        assert!(out.contains(r#"font-size="14">A</text>"#));
+        assert!(out.contains(r##"font-size="11" fill="#1565c0">Sft</text>"##));
+        assert!(out.contains(r##"font-size="11" fill="#1565c0">L1</text>"##));
+        assert!(out.contains(r#"font-size="14">&lt;</text>"#));
+    }
+
+    #[test]
+    fn mirrored_halves() {
+        assert_eq!(position(0, 0), (0.0, 0.6));
+        assert_eq!(position(0, 9), (10.0, 0.6));
+        assert_eq!(position(3, 3), (3.4, 4.0));
+        assert_eq!(position(3, 6), (6.6, 4.0));
+    }
+}